@@ -1,6 +1,6 @@
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, Fields, Ident, ItemStruct};
+use syn::{parse_macro_input, Fields, Ident, ItemStruct, Token};
 
 #[proc_macro_attribute]
 pub fn entity(attr: TokenStream, item: TokenStream) -> TokenStream {
@@ -11,13 +11,52 @@ pub fn entity(attr: TokenStream, item: TokenStream) -> TokenStream {
 
     let struct_name_str = struct_name.to_string();
 
-    let mut indexed_field_name = vec![];
+    let mut indexed_fields: Vec<Vec<Ident>> = vec![];
+
+    let mut geo_fields: Vec<Ident> = vec![];
+
+    let mut vector_field: Option<(Ident, syn::LitInt)> = None;
+
+    let mut custom_validators: Vec<syn::Path> = vec![];
 
     let attr_parser = syn::meta::parser(|meta| {
         if meta.path.is_ident("index") {
             meta.parse_nested_meta(|meta| {
-                let name = meta.path.get_ident().unwrap().to_string();
-                indexed_field_name.push(name);
+                // Dotted paths (`address.city`) index a field of a nested struct
+                let mut segments = vec![meta.path.require_ident()?.clone()];
+                while meta.input.peek(Token![.]) {
+                    meta.input.parse::<Token![.]>()?;
+                    segments.push(meta.input.parse::<Ident>()?);
+                }
+                indexed_fields.push(segments);
+                Ok(())
+            })
+        } else if meta.path.is_ident("vector") {
+            let mut field = None;
+            let mut dim = None;
+            meta.parse_nested_meta(|meta| {
+                if meta.path.is_ident("dim") {
+                    dim = Some(meta.value()?.parse::<syn::LitInt>()?);
+                } else {
+                    field = Some(meta.path.require_ident()?.clone());
+                }
+                Ok(())
+            })?;
+            match (field, dim) {
+                (Some(field), Some(dim)) => {
+                    vector_field = Some((field, dim));
+                    Ok(())
+                }
+                _ => Err(meta.error("vector expects a field and its dimension: vector(embedding, dim = 384)")),
+            }
+        } else if meta.path.is_ident("validate") {
+            meta.parse_nested_meta(|meta| {
+                custom_validators.push(meta.path);
+                Ok(())
+            })
+        } else if meta.path.is_ident("geo") {
+            meta.parse_nested_meta(|meta| {
+                geo_fields.push(meta.path.require_ident()?.clone());
                 Ok(())
             })
         } else {
@@ -27,80 +66,149 @@ pub fn entity(attr: TokenStream, item: TokenStream) -> TokenStream {
 
     parse_macro_input!(attr with attr_parser);
 
-    // eprintln!("Indexed fields : {:?}", &indexed_field_name);
-
-    // let fields = match input.fields {
-    //     Fields::Named(ref fields) => fields.named.clone(),
-    //     _ => panic!("Expected a struct with named fields"),
-    // };
-
-    let user_fields = match input.fields {
+    let mut user_fields = match input.fields {
         Fields::Named(ref fields) => fields.named.clone(),
         _ => panic!("Expected a struct with named fields"),
     };
 
+    // Field constraints are turned into checks then removed from the struct
+    let mut field_checks = vec![];
+    for field in user_fields.iter_mut() {
+        for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("validate")) {
+            let field_name = field.ident.as_ref().unwrap();
+            let res = parse_field_validation(attr, field_name);
+            match res {
+                Ok(checks) => field_checks.extend(checks),
+                Err(error) => return TokenStream::from(error.to_compile_error()),
+            }
+        }
+        field.attrs.retain(|attr| !attr.path().is_ident("validate"));
+    }
+
     let user_field_names: Vec<&Ident> = user_fields
         .iter()
         .map(|f| f.ident.as_ref().unwrap())
         .collect();
     let user_field_types: Vec<&syn::Type> = user_fields.iter().map(|f| &f.ty).collect();
 
-    let index_fields = match input.fields {
-        Fields::Named(ref fields) => fields.named.clone(),
-        _ => panic!("Expected a struct with named fields"),
+    for segments in &indexed_fields {
+        let root_field = &segments[0];
+        if !user_field_names.contains(&root_field) {
+            return TokenStream::from(
+                syn::Error::new(root_field.span(), "Unknown field to index").to_compile_error(),
+            );
+        }
+    }
+
+    let geo_point = if geo_fields.is_empty() {
+        quote! {}
+    } else if geo_fields.len() == 2 {
+        let (lat_field, lon_field) = (&geo_fields[0], &geo_fields[1]);
+        quote! {
+            fn get_geo_point(&self) -> Option<GeoPoint> {
+                Some(GeoPoint::new(self.#lat_field as f64, self.#lon_field as f64))
+            }
+        }
+    } else {
+        return TokenStream::from(quote! {
+            compile_error!("geo expects the latitude and longitude fields: geo(lat, lon)");
+        });
     };
 
-    let index_fields = indexed_field_name
-        .iter()
-        .map(|name| {
-            let field = index_fields.iter().find(|f| {
-                if let Some(ident) = &f.ident {
-                    let field_name = ident.to_string();
-                    &field_name == name
+    let vector = if let Some((field, dim)) = &vector_field {
+        quote! {
+            fn get_vector(&self) -> Option<&[f32]> {
+                if self.#field.is_empty() {
+                    None
                 } else {
-                    false
+                    Some(&self.#field)
                 }
-            });
+            }
 
-            if let Some(f) = field {
-                let field_name = &f.ident;
-                let field_type = &f.ty;
-                let type_marker = match field_type {
-                    syn::Type::Path(type_path) => {
-                        let segment = &type_path.path.segments.last().unwrap().ident;
-                        if segment == "String" {
-                            Some(quote! {
-                                FieldIndex {
-                                    kind: stringify!(#struct_name).to_string(),
-                                    entity_id: self.id.to_string(),
-                                    name: stringify!(#field_name).to_string(),
-                                    value: self.#field_name.to_string(),
-                                    stored_type: "String".to_string()
-                                }
-                            })
-                        } else if segment == "usize" || segment == "u16" || segment == "u32" {
-                            Some(quote! {
-                                FieldIndex {
-                                    kind: stringify!(#struct_name).to_string(),
-                                    entity_id: self.id.to_string(),
-                                    name: stringify!(#field_name).to_string(),
-                                    value: self.#field_name.to_string(),
-                                    stored_type: "String".to_string()
-                                }
-                            })
-                        } else {
-                            None
-                        }
-                    }
-                    _ => None,
-                };
-                type_marker
-            } else {
-                panic!("Unknown field to index")
+            fn get_vector_dim(&self) -> usize {
+                #dim
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    let validate = if field_checks.is_empty() && custom_validators.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            fn validate(&self) -> Result<(), ValidationErrors> {
+                let mut errors = ValidationErrors::new();
+                #(#field_checks)*
+                #(#custom_validators(self, &mut errors);)*
+                errors.into_result()
+            }
+        }
+    };
+
+    let index_fields = indexed_fields
+        .iter()
+        .map(|segments| {
+            let index_name = segments
+                .iter()
+                .map(|segment| segment.to_string())
+                .collect::<Vec<String>>()
+                .join(".");
+            quote! {
+                for value in IndexValue::index_values(&self.#(#segments).*) {
+                    fields_index.push(FieldIndex {
+                        kind: stringify!(#struct_name).to_string(),
+                        entity_id: self.id.to_string(),
+                        name: #index_name.to_string(),
+                        value,
+                        stored_type: "String".to_string()
+                    });
+                }
             }
         })
         .collect::<Vec<_>>();
 
+    // Typed accessors of the indexed fields, nested paths are flattened
+    // (`address.city` becomes `address_city`) and left untyped
+    let fields_struct_name = Ident::new(&format!("{}Fields", struct_name), struct_name.span());
+    let mut query_field_names: Vec<Ident> = vec![];
+    let mut query_field_types: Vec<proc_macro2::TokenStream> = vec![];
+    let mut query_field_paths: Vec<String> = vec![];
+    for segments in &indexed_fields {
+        let path = segments
+            .iter()
+            .map(|segment| segment.to_string())
+            .collect::<Vec<String>>();
+        if query_field_paths.contains(&path.join(".")) {
+            continue;
+        }
+        let field_type = if segments.len() == 1 {
+            let field_type = user_fields
+                .iter()
+                .find(|f| f.ident.as_ref() == Some(&segments[0]))
+                .map(|f| &f.ty)
+                .unwrap();
+            quote! { #field_type }
+        } else {
+            quote! { AnyValue }
+        };
+        query_field_names.push(Ident::new(&path.join("_"), segments[0].span()));
+        query_field_types.push(field_type);
+        query_field_paths.push(path.join("."));
+    }
+
+    let diff = quote! {
+        fn diff(&self, other: &Self) -> Vec<&'static str> {
+            let mut changed_fields = vec![];
+            #(
+                if is_field_changed(&self.#user_field_names, &other.#user_field_names) {
+                    changed_fields.push(stringify!(#user_field_names));
+                }
+            )*
+            changed_fields
+        }
+    };
+
     let struct_decl = if user_fields.len() == 0 {
         quote! {
             #vis struct #struct_name {
@@ -123,6 +231,10 @@ pub fn entity(attr: TokenStream, item: TokenStream) -> TokenStream {
 
         #struct_decl
 
+        #vis struct #fields_struct_name {
+            #(pub #query_field_names: IndexedField<#query_field_types>),*
+        }
+
         impl #struct_name {
 
             pub fn new(#(#user_field_names: #user_field_types),*) -> Self {
@@ -141,6 +253,12 @@ pub fn entity(attr: TokenStream, item: TokenStream) -> TokenStream {
                 }
             }
 
+            pub fn fields() -> #fields_struct_name {
+                #fields_struct_name {
+                    #(#query_field_names: IndexedField::new(#query_field_paths)),*
+                }
+            }
+
         }
 
         impl Entity for #struct_name {
@@ -158,9 +276,19 @@ pub fn entity(attr: TokenStream, item: TokenStream) -> TokenStream {
             }
 
             fn get_fields_index(&self) -> Vec<FieldIndex> {
-                vec![#(#index_fields),*]
+                let mut fields_index = vec![];
+                #(#index_fields)*
+                fields_index
             }
 
+            #geo_point
+
+            #vector
+
+            #validate
+
+            #diff
+
         }
 
     };
@@ -168,6 +296,65 @@ pub fn entity(attr: TokenStream, item: TokenStream) -> TokenStream {
     TokenStream::from(expanded)
 }
 
+// #[validate(length(min = 1, max = 20), range(min = 0), email, regex = "^[a-z]+$")]
+fn parse_field_validation(
+    attr: &syn::Attribute,
+    field_name: &Ident,
+) -> syn::Result<Vec<proc_macro2::TokenStream>> {
+    let field_name_str = field_name.to_string();
+    let mut checks = vec![];
+    attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("length") || meta.path.is_ident("range") {
+            let mut min = None;
+            let mut max = None;
+            meta.parse_nested_meta(|bound| {
+                if bound.path.is_ident("min") {
+                    min = Some(bound.value()?.parse::<syn::Expr>()?);
+                } else if bound.path.is_ident("max") {
+                    max = Some(bound.value()?.parse::<syn::Expr>()?);
+                } else {
+                    return Err(bound.error("expected min or max"));
+                }
+                Ok(())
+            })?;
+            let (check, bound_type) = if meta.path.is_ident("length") {
+                (quote! { check_length }, quote! { usize })
+            } else {
+                (quote! { check_range }, quote! { f64 })
+            };
+            let min = match min {
+                Some(min) => quote! { Some((#min) as #bound_type) },
+                None => quote! { None },
+            };
+            let max = match max {
+                Some(max) => quote! { Some((#max) as #bound_type) },
+                None => quote! { None },
+            };
+            checks.push(quote! {
+                errors.#check(&self.id, #field_name_str, &self.#field_name, #min, #max);
+            });
+            Ok(())
+        } else if meta.path.is_ident("email") {
+            checks.push(quote! {
+                errors.check_email(&self.id, #field_name_str, &self.#field_name);
+            });
+            Ok(())
+        } else if meta.path.is_ident("regex") {
+            let pattern = meta.value()?.parse::<syn::LitStr>()?;
+            if let Err(error) = regex::Regex::new(&pattern.value()) {
+                return Err(syn::Error::new(pattern.span(), format!("invalid regex : {}", error)));
+            }
+            checks.push(quote! {
+                errors.check_regex(&self.id, #field_name_str, &self.#field_name, #pattern);
+            });
+            Ok(())
+        } else {
+            Err(meta.error("unsupported validation, expected length, range, email or regex"))
+        }
+    })?;
+    Ok(checks)
+}
+
 #[proc_macro_attribute]
 pub fn entity_part(_attr: TokenStream, item: TokenStream) -> TokenStream {
     let input = parse_macro_input!(item as ItemStruct);
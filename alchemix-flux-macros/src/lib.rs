@@ -58,6 +58,7 @@ pub fn flux_context(attr: TokenStream, item: TokenStream) -> TokenStream {
     let event_arms = build_event_arms(&struct_name, &classes);
     let get_entities_arms = build_get_entities_arms(&struct_name, &classes);
     let query_entities_arms = build_query_entities_arms(&struct_name, &classes);
+    let query_geo_arms = build_query_geo_arms(&struct_name, &classes);
 
     let expanded = quote! {
 
@@ -119,6 +120,16 @@ pub fn flux_context(attr: TokenStream, item: TokenStream) -> TokenStream {
                 }
             }
 
+            fn query_geo(&self, state: &FluxState, query: &StateGeoQuery) -> Value {
+                match(query.kind.as_str()) {
+                    #query_geo_arms
+                    _ => {
+                        let res: Vec<String> = vec![];
+                        serde_json::to_value(res).unwrap()
+                    },
+                }
+            }
+
         }
 
     };
@@ -179,6 +190,22 @@ fn build_query_entities_arms(struct_name: &Ident, classes: &Vec<Path>) -> proc_m
     expanded
 }
 
+fn build_query_geo_arms(struct_name: &Ident, classes: &Vec<Path>) -> proc_macro2::TokenStream {
+    let mut match_arms = Vec::new();
+    for class in classes {
+        let class_name = class.get_ident().unwrap();
+        let class_name_sk = camel_to_snake_uppercase(&class_name.to_string());
+        let class_name_sk = Ident::new(&class_name_sk, Span::call_site());
+        match_arms.push(quote! {
+            stringify!(#class_name) => {
+                let res = state.query_geo(&query.shard, &#struct_name::#class_name_sk, &query.query);
+                serde_json::to_value(res).unwrap()
+            },
+        });
+    }
+    let expanded = quote! {#(#match_arms)*};
+    expanded
+}
 
 #[proc_macro_attribute]
 pub fn flux_hook(_attr: TokenStream, item: TokenStream) -> TokenStream {
@@ -2,7 +2,7 @@ use proc_macro::TokenStream;
 use proc_macro2::Span;
 use quote::{quote, ToTokens};
 use syn::{
-    parse_macro_input, FnArg, Ident, ItemFn, ItemStruct, Pat, PatType, Path, Type, TypeReference,
+    parse_macro_input, FnArg, Ident, ItemFn, ItemStruct, LitInt, LitStr, Pat, PatType, Path, Type, TypeReference,
 };
 
 #[proc_macro_attribute]
@@ -37,7 +37,11 @@ pub fn rx_context(attr: TokenStream, item: TokenStream) -> TokenStream {
     let update_entities_arms = build_update_entities_arms(&struct_name, &classes);
     let delete_entities_arms = build_delete_entities_arms(&struct_name, &classes);
     let query_property_arms = build_query_property_arms(&struct_name, &classes);
+    let query_geo_arms = build_query_geo_arms(&struct_name, &classes);
     let signal_arms = build_signal_arms(&struct_name, &classes);
+    let apply_changes_arms = build_apply_changes_arms(&struct_name, &classes);
+    let batch_update_arms = build_batch_update_arms(&struct_name, &classes);
+    let batch_delete_arms = build_batch_delete_arms(&struct_name, &classes);
 
     let expanded = quote! {
 
@@ -54,18 +58,20 @@ pub fn rx_context(attr: TokenStream, item: TokenStream) -> TokenStream {
                 self
             }
 
-            async fn delete_entities(&self, store: &RxStore, kind: &str, ids: &Vec<&str>) {
+            async fn delete_entities(&self, store: &RxStore, kind: &str, ids: &Vec<&str>) -> RxResponse {
                 match(kind) {
                     #delete_entities_arms
                     _ => println!("Unknown kind {}", kind),
                 }
+                RxResponse::Failure(format!("Unknown kind {}", kind))
             }
 
-            async fn update_entities(&self, store: &RxStore, kind: &str, entities_values: Value) {
+            async fn update_entities(&self, store: &RxStore, kind: &str, entities_values: Value) -> RxResponse {
                 match(kind) {
                     #update_entities_arms
                     _ => println!("Unknown kind {}", kind),
                 }
+                RxResponse::Failure(format!("Unknown kind {}", kind))
             }
 
             async fn get_entities(&self, store: &RxStore, kind: &str, ids: &Vec<&str>) -> RxResponse {
@@ -84,6 +90,14 @@ pub fn rx_context(attr: TokenStream, item: TokenStream) -> TokenStream {
                 RxResponse::Failure(format!("Unknown kind {}", kind))
             }
 
+            async fn query_geo(&self, store: &RxStore, kind: &str, query: &GeoQuery) -> RxResponse {
+                match(kind) {
+                    #query_geo_arms
+                    _ => println!("Unknown kind {}", kind),
+                }
+                RxResponse::Failure(format!("Unknown kind {}", kind))
+            }
+
             async fn signal(&self, store: &RxStore, signal_value: Value) -> RxResponse {
                 let kind = signal_value.get("kind")
                 .unwrap()
@@ -95,6 +109,30 @@ pub fn rx_context(attr: TokenStream, item: TokenStream) -> TokenStream {
                 }
                 RxResponse::Failure(format!("Unable to match signal - Unknown kind {}", kind))
             }
+
+            async fn batch_update_entities(&self, store: &RxStore, batch: &mut RxBatch, kind: &str, entities_values: Value) -> RxResponse {
+                match(kind) {
+                    #batch_update_arms
+                    _ => println!("Unknown kind {}", kind),
+                }
+                RxResponse::Failure(format!("Unknown kind {}", kind))
+            }
+
+            async fn batch_delete_entities(&self, store: &RxStore, batch: &mut RxBatch, kind: &str, ids: &Vec<&str>) -> RxResponse {
+                match(kind) {
+                    #batch_delete_arms
+                    _ => println!("Unknown kind {}", kind),
+                }
+                RxResponse::Failure(format!("Unknown kind {}", kind))
+            }
+
+            async fn apply_changes(&self, store: &RxStore, kind: &str, changes: &[Change]) -> Result<ApplyReport, String> {
+                match(kind) {
+                    #apply_changes_arms
+                    _ => println!("Unknown kind {}", kind),
+                }
+                Err(format!("Unknown kind {}", kind))
+            }
             
 
         }
@@ -115,7 +153,10 @@ fn build_delete_entities_arms(
         let class_name_sk = Ident::new(&class_name_sk, Span::call_site());
         match_arms.push(quote! {
             stringify!(#class_name) => {
-                store.delete_entities(#struct_name::#class_name_sk, &ids).await;
+                return match store.delete_entities(#struct_name::#class_name_sk, &ids).await {
+                    Ok(_) => RxResponse::Success(),
+                    Err(error) => error.into(),
+                }
             },
         });
     }
@@ -134,10 +175,76 @@ fn build_update_entities_arms(
         let _class_name_sk = Ident::new(&class_name_sk, Span::call_site());
         match_arms.push(quote! {
             stringify!(#class_name) => {
-                if let Ok(entities) = serde_json::from_value::<Vec<#class_name>>(entities_values) {
-                    store.save_entities(&entities).await;
+                return match serde_json::from_value::<Vec<#class_name>>(entities_values) {
+                    Ok(entities) => match store.save_entities(&entities).await {
+                        Ok(_) => RxResponse::Success(),
+                        Err(error) => error.into(),
+                    },
+                    Err(error) => RxResponse::Failure(format!("Invalid {} : {}", kind, error)),
+                }
+
+            },
+        });
+    }
+    let expanded = quote! {#(#match_arms)*};
+    expanded
+}
+
+fn build_batch_update_arms(
+    _struct_name: &Ident,
+    classes: &Vec<Path>,
+) -> proc_macro2::TokenStream {
+    let mut match_arms = Vec::new();
+    for class in classes {
+        let class_name = class.get_ident().unwrap();
+        match_arms.push(quote! {
+            stringify!(#class_name) => {
+                return match serde_json::from_value::<Vec<#class_name>>(entities_values) {
+                    Ok(entities) => match store.batch_update_entities(batch, entities).await {
+                        Ok(_) => RxResponse::Success(),
+                        Err(error) => error.into(),
+                    },
+                    Err(error) => RxResponse::Failure(format!("Invalid {} : {}", kind, error)),
                 }
+            },
+        });
+    }
+    let expanded = quote! {#(#match_arms)*};
+    expanded
+}
 
+fn build_batch_delete_arms(
+    struct_name: &Ident,
+    classes: &Vec<Path>,
+) -> proc_macro2::TokenStream {
+    let mut match_arms = Vec::new();
+    for class in classes {
+        let class_name = class.get_ident().unwrap();
+        let class_name_sk = camel_to_snake_uppercase(&class_name.to_string());
+        let class_name_sk = Ident::new(&class_name_sk, Span::call_site());
+        match_arms.push(quote! {
+            stringify!(#class_name) => {
+                return match store.batch_delete_entities(batch, #struct_name::#class_name_sk, ids).await {
+                    Ok(_) => RxResponse::Success(),
+                    Err(error) => error.into(),
+                }
+            },
+        });
+    }
+    let expanded = quote! {#(#match_arms)*};
+    expanded
+}
+
+fn build_apply_changes_arms(
+    _struct_name: &Ident,
+    classes: &Vec<Path>,
+) -> proc_macro2::TokenStream {
+    let mut match_arms = Vec::new();
+    for class in classes {
+        let class_name = class.get_ident().unwrap();
+        match_arms.push(quote! {
+            stringify!(#class_name) => {
+                return store.apply_changes::<#class_name>(changes).await
             },
         });
     }
@@ -181,6 +288,24 @@ fn build_query_property_arms(struct_name: &Ident, classes: &Vec<Path>) -> proc_m
     expanded
 }
 
+fn build_query_geo_arms(struct_name: &Ident, classes: &Vec<Path>) -> proc_macro2::TokenStream {
+    let mut match_arms = Vec::new();
+    for class in classes {
+        let class_name = class.get_ident().unwrap();
+        let class_name_sk = camel_to_snake_uppercase(&class_name.to_string());
+        let class_name_sk = Ident::new(&class_name_sk, Span::call_site());
+        match_arms.push(quote! {
+            stringify!(#class_name) => {
+                let entries = store.query_geo(#struct_name::#class_name_sk, query).await;
+                let values = serde_json::to_value(entries).unwrap();
+                return RxResponse::QueryResponse(values)
+            },
+        });
+    }
+    let expanded = quote! {#(#match_arms)*};
+    expanded
+}
+
 fn build_signal_arms(_struct_name: &Ident, classes: &Vec<Path>) -> proc_macro2::TokenStream {
     let mut match_arms = Vec::new();
     for class in classes {
@@ -190,12 +315,7 @@ fn build_signal_arms(_struct_name: &Ident, classes: &Vec<Path>) -> proc_macro2::
         match_arms.push(quote! {
             stringify!(#class_name) => {
                 if let Ok(signal_entity) = serde_json::from_value::<#class_name>(signal_value) {
-                    let result = store.signal_action(signal_entity).await;
-                    let result = match result {
-                        Ok(value) => RxResponse::SignalResponse(value),
-                        Err(message) => RxResponse::Failure(message)
-                    };
-                    return result;
+                    return store.signal_response(signal_entity).await;
                 }
             },
         });
@@ -204,6 +324,11 @@ fn build_signal_arms(_struct_name: &Ident, classes: &Vec<Path>) -> proc_macro2::
     expanded
 }
 
+/// `#[rx_entity_update(Kind, priority = 10, mode = sequential, deferred)]`,
+/// a deferred handler runs as a job queued in the write transaction. `open`
+/// does not start the job workers, the jobs only run after `RxStore::start_jobs`
+/// or when `RxStore::run_pending_jobs` is called. The same applies to the
+/// deferred delete and change handlers.
 #[proc_macro_attribute]
 pub fn rx_entity_update(attr: TokenStream, item: TokenStream) -> TokenStream {
     entity_handler(attr, item, "Update")
@@ -214,7 +339,21 @@ pub fn rx_entity_delete(attr: TokenStream, item: TokenStream) -> TokenStream {
     entity_handler(attr, item, "Delete")
 }
 
-fn entity_handler(attr: TokenStream, item: TokenStream, action: &str) -> TokenStream {
+/// The handler gets `&mut Vec<Kind>`, or `&mut [Kind]` when it only edits
+/// the entities, and returns `Result<(), String>`, an error rejects the save.
+#[proc_macro_attribute]
+pub fn rx_entity_before_update(attr: TokenStream, item: TokenStream) -> TokenStream {
+    before_entity_handler(attr, item, "BeforeUpdate")
+}
+
+/// The handler gets the stored entities, the ones removed from the `Vec`
+/// are not deleted and an error rejects the whole deletion.
+#[proc_macro_attribute]
+pub fn rx_entity_before_delete(attr: TokenStream, item: TokenStream) -> TokenStream {
+    before_entity_handler(attr, item, "BeforeDelete")
+}
+
+fn before_entity_handler(attr: TokenStream, item: TokenStream, action: &str) -> TokenStream {
     let input = parse_macro_input!(item as ItemFn);
     let fn_name = &input.sig.ident;
     let action = Ident::new(action, Span::call_site());
@@ -229,6 +368,119 @@ fn entity_handler(attr: TokenStream, item: TokenStream, action: &str) -> TokenSt
     let entity_kind = &metas.first().unwrap();
     let entity_kind_str = entity_kind.to_token_stream().to_string();
 
+    if input.sig.inputs.is_empty() {
+        return TokenStream::from(quote! {
+            compile_error!("Function has no parameters");
+        });
+    }
+    let context_param_sig = get_param_signature(input.sig.inputs.get(2));
+
+    let cc_fn_name = snake_to_camel(&fn_name.to_string());
+    let handler_name_str = format!("{}Handler", cc_fn_name);
+    let handler_name = Ident::new(&handler_name_str, Span::call_site());
+
+    let invocation = if let Some((_, context_param_type)) = context_param_sig {
+        quote! {
+            let context = payload.store.get_context::<#context_param_type>();
+            #fn_name(&mut data, payload.store, context).await?;
+        }
+    } else {
+        quote! {
+            #fn_name(&mut data, payload.store).await?;
+        }
+    };
+
+    let expanded = quote! {
+
+        #input
+
+        pub struct #handler_name;
+
+        #[async_trait]
+        impl DataHookHandler for #handler_name {
+
+            async fn handle(&self, _payload: Arc<DispatchPayload<'_>>, _value: Arc<Payload>) -> Result<(), HookError> {
+                Ok(())
+            }
+
+            async fn intercept(
+                &self,
+                payload: Arc<DispatchPayload<'_>>,
+                value: Box<Payload>,
+            ) -> Result<Box<Payload>, String> {
+                match value.downcast::<Vec<#entity_kind>>() {
+                    Ok(mut data) => {
+                        #invocation
+                        Ok(data)
+                    }
+                    Err(_) => Err("Downcast Error".to_string()),
+                }
+            }
+
+            fn get_action(&self) -> EntityAction {
+                EntityAction::#action
+            }
+
+            fn get_entity_kind(&self) -> &str {
+                #entity_kind_str
+            }
+
+            fn get_name(&self) -> &str {
+                stringify!(#fn_name)
+            }
+
+        }
+
+    };
+    TokenStream::from(expanded)
+}
+
+/// The handler gets `&[(Option<Kind>, Kind)]`, the previous value of each
+/// saved entity with the new one.
+#[proc_macro_attribute]
+pub fn rx_entity_change(attr: TokenStream, item: TokenStream) -> TokenStream {
+    entity_handler(attr, item, "Change")
+}
+
+fn entity_handler(attr: TokenStream, item: TokenStream, action: &str) -> TokenStream {
+    let input = parse_macro_input!(item as ItemFn);
+    let fn_name = &input.sig.ident;
+    let action = Ident::new(action, Span::call_site());
+
+    // #[rx_entity_update(Kind, priority = 10, mode = sequential, deferred)]
+    let mut metas = vec![];
+    let mut deferred = false;
+    let mut priority = 0i32;
+    let mut mode = Ident::new("Parallel", Span::call_site());
+    let metas_parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("priority") {
+            let value = meta.value()?.parse::<syn::Expr>()?;
+            priority = parse_priority(&value)
+                .ok_or_else(|| meta.error("priority expects an integer"))?;
+        } else if meta.path.is_ident("mode") {
+            let value = meta.value()?.parse::<Ident>()?;
+            mode = match value.to_string().as_str() {
+                "parallel" => Ident::new("Parallel", value.span()),
+                "sequential" => Ident::new("Sequential", value.span()),
+                _ => return Err(meta.error("mode expects parallel or sequential")),
+            };
+        } else if meta.path.is_ident("deferred") {
+            deferred = true;
+        } else {
+            metas.push(meta.path.clone());
+        }
+        Ok(())
+    });
+    parse_macro_input!(attr with metas_parser);
+
+    let entity_kind = &metas.first().unwrap();
+    let entity_kind_str = entity_kind.to_token_stream().to_string();
+    let payload_type = if action == "Change" {
+        quote! { Vec<(Option<#entity_kind>, #entity_kind)> }
+    } else {
+        quote! { Vec<#entity_kind> }
+    };
+
     let context_param_sig = get_param_signature(input.sig.inputs.get(2));
 
     // let (context_param_name, context_param_type) = context_param_sig.unwrap();
@@ -239,7 +491,8 @@ fn entity_handler(attr: TokenStream, item: TokenStream, action: &str) -> TokenSt
             compile_error!("Function has no parameters");
         });
     }
-    let (value_param_name, value_param_type) = value_param_sig.unwrap();
+    // The value is given as `&Vec<Kind>`, which the handler can also take as a slice
+    let (value_param_name, _) = value_param_sig.unwrap();
 
     let wrapper_name = format!("{}_wrapper", fn_name);
     let wrapper_fn_name = Ident::new(&wrapper_name, Span::call_site());
@@ -250,14 +503,15 @@ fn entity_handler(attr: TokenStream, item: TokenStream, action: &str) -> TokenSt
 
     let payload_type_name = Ident::new("DispatchPayload", Span::call_site());
 
+    // Hooks return either () or Result<(), E>
     let invocation = if let Some((_, context_param_type)) = context_param_sig {
         quote! {
             let context = payload.store.get_context::<#context_param_type>();
-            #fn_name(&#value_param_name, payload.store, context).await;
+            #fn_name(&#value_param_name, payload.store, context).await.into_hook_result()
         }
     } else {
         quote! {
-            #fn_name(&#value_param_name, payload.store).await;
+            #fn_name(&#value_param_name, payload.store).await.into_hook_result()
         }
     };
 
@@ -265,8 +519,8 @@ fn entity_handler(attr: TokenStream, item: TokenStream, action: &str) -> TokenSt
 
         #input
 
-        async fn #wrapper_fn_name(payload: Arc<#payload_type_name<'_>>, #value_param_name: Arc<#value_param_type>) {
-            #invocation;
+        async fn #wrapper_fn_name(payload: Arc<#payload_type_name<'_>>, #value_param_name: Arc<#payload_type>) -> Result<(), HookError> {
+            #invocation
         }
 
         pub struct #handler_name;
@@ -274,14 +528,18 @@ fn entity_handler(attr: TokenStream, item: TokenStream, action: &str) -> TokenSt
         #[async_trait]
         impl DataHookHandler for #handler_name {
 
-            async fn handle(&self, payload: Arc<DispatchPayload<'_>>, value: Arc<Payload>){
-                if let Ok(data) = value.downcast::<Vec<#entity_kind>>() {
-                    #wrapper_fn_name(payload, data).await;
+            async fn handle(&self, payload: Arc<DispatchPayload<'_>>, value: Arc<Payload>) -> Result<(), HookError> {
+                if let Ok(data) = value.downcast::<#payload_type>() {
+                    #wrapper_fn_name(payload, data).await
                 }else {
-                    println!("Downcast Error");
+                    Err(HookError::new("Downcast Error"))
                 }
             }
 
+            fn get_name(&self) -> &str {
+                stringify!(#fn_name)
+            }
+
             fn get_action(&self) -> EntityAction {
                 EntityAction::#action
             }
@@ -290,12 +548,41 @@ fn entity_handler(attr: TokenStream, item: TokenStream, action: &str) -> TokenSt
                 #entity_kind_str
             }
 
+            fn get_priority(&self) -> i32 {
+                #priority
+            }
+
+            fn get_mode(&self) -> HookMode {
+                HookMode::#mode
+            }
+
+            fn is_deferred(&self) -> bool {
+                #deferred
+            }
+
+            async fn handle_job(&self, payload: Arc<DispatchPayload<'_>>, data: &str) -> Result<(), HookError> {
+                match serde_json::from_str::<#payload_type>(data) {
+                    Ok(data) => #wrapper_fn_name(payload, Arc::new(data)).await,
+                    Err(error) => Err(HookError::from(format!("Invalid job payload : {}", error))),
+                }
+            }
+
         }
 
     };
     TokenStream::from(expanded)
 }
 
+fn parse_priority(value: &syn::Expr) -> Option<i32> {
+    match value {
+        syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Int(int), .. }) => int.base10_parse().ok(),
+        syn::Expr::Unary(syn::ExprUnary { op: syn::UnOp::Neg(_), expr, .. }) => {
+            parse_priority(expr).map(|priority| -priority)
+        }
+        _ => None,
+    }
+}
+
 #[proc_macro]
 pub fn entity_hooks(input: TokenStream) -> TokenStream {
     let mut hook_names = vec![];
@@ -328,10 +615,15 @@ pub fn entity_hooks(input: TokenStream) -> TokenStream {
 }
 
 #[proc_macro_attribute]
-pub fn rx_signal_handler(_attr: TokenStream, item: TokenStream) -> TokenStream {
+pub fn rx_signal_handler(attr: TokenStream, item: TokenStream) -> TokenStream {
     let input = parse_macro_input!(item as ItemFn);
     let fn_name = &input.sig.ident;
 
+    let (timeout_fn, max_concurrency_fn) = match parse_signal_limits(attr) {
+        Ok(limits) => limits,
+        Err(error) => return error.to_compile_error().into(),
+    };
+
     let cc_fn_name = snake_to_camel(&fn_name.to_string());
     let handler_name_str = format!("{}SignalHandler", cc_fn_name);
     let handler_name = Ident::new(&handler_name_str, Span::call_site());
@@ -402,9 +694,177 @@ pub fn rx_signal_handler(_attr: TokenStream, item: TokenStream) -> TokenStream {
                 fn get_name(&self) -> &str {
                     #trigger_kind_str
                 }
+
+                #timeout_fn
+
+                #max_concurrency_fn
+            }
+
+    };
+    TokenStream::from(expanded)
+}
+
+// "500ms", "5s", "2m" or "1h"
+// #[rx_signal_handler(timeout = "5s", max_concurrency = 4)], also used by the stream handlers
+fn parse_signal_limits(
+    attr: TokenStream,
+) -> syn::Result<(Option<proc_macro2::TokenStream>, Option<proc_macro2::TokenStream>)> {
+    let mut timeout = None;
+    let mut max_concurrency = None;
+    let metas_parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("timeout") {
+            let value: LitStr = meta.value()?.parse()?;
+            let millis = parse_duration_millis(&value.value())
+                .ok_or_else(|| meta.error("Invalid timeout, expected a duration such as \"500ms\", \"5s\" or \"2m\""))?;
+            if millis == 0 {
+                return Err(meta.error("timeout must be greater than zero"));
             }
+            timeout = Some(millis);
+            Ok(())
+        } else if meta.path.is_ident("max_concurrency") {
+            let value: LitInt = meta.value()?.parse()?;
+            let value = value.base10_parse::<usize>()?;
+            if value == 0 {
+                return Err(meta.error("max_concurrency must be greater than zero"));
+            }
+            max_concurrency = Some(value);
+            Ok(())
+        } else {
+            Err(meta.error("Unknown signal handler attribute"))
+        }
+    });
+    syn::parse::Parser::parse(metas_parser, attr)?;
+
+    let timeout_fn = timeout.map(|millis| {
+        quote! {
+            fn get_timeout(&self) -> Option<std::time::Duration> {
+                Some(std::time::Duration::from_millis(#millis))
+            }
+        }
+    });
+    let max_concurrency_fn = max_concurrency.map(|max_concurrency| {
+        quote! {
+            fn get_max_concurrency(&self) -> Option<usize> {
+                Some(#max_concurrency)
+            }
+        }
+    });
+    Ok((timeout_fn, max_concurrency_fn))
+}
 
+fn parse_duration_millis(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let split = text.find(|c: char| !c.is_ascii_digit())?;
+    let (value, unit) = text.split_at(split);
+    let value: u64 = value.parse().ok()?;
+    let factor = match unit.trim() {
+        "ms" => 1,
+        "s" => 1_000,
+        "m" => 60_000,
+        "h" => 3_600_000,
+        _ => return None,
     };
+    value.checked_mul(factor)
+}
+
+/// The handler takes the signal by value and returns
+/// `impl Stream<Item = T> + Send + 'a`, `'a` being the lifetime of the store.
+#[proc_macro_attribute]
+pub fn rx_stream_handler(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as ItemFn);
+    let fn_name = &input.sig.ident;
+
+    let (timeout_fn, max_concurrency_fn) = match parse_signal_limits(attr) {
+        Ok(limits) => limits,
+        Err(error) => return error.to_compile_error().into(),
+    };
+
+    let cc_fn_name = snake_to_camel(&fn_name.to_string());
+    let handler_name_str = format!("{}StreamHandler", cc_fn_name);
+    let handler_name = Ident::new(&handler_name_str, Span::call_site());
+
+    let value_param_sig = get_param_signature(input.sig.inputs.get(0));
+    if value_param_sig.is_none() {
+        return TokenStream::from(quote! {
+            compile_error!("Function has no parameters");
+        });
+    }
+    let (_, value_param_type) = value_param_sig.unwrap();
+
+    let trigger_kind_str = value_param_type.to_token_stream().to_string();
+
+    let context_param_sig = get_param_signature(input.sig.inputs.get(2));
+
+    let invocation = if let Some((_, context_param_type)) = context_param_sig {
+        quote! {
+            let flow_context = store.get_context::<#context_param_type>();
+            let stream = #fn_name(input, store, flow_context);
+        }
+    } else {
+        quote! {
+            let stream = #fn_name(input, store);
+        }
+    };
+
+    let expanded = quote! {
+            #input
+
+            pub struct #handler_name {}
+
+            impl StreamHookHandler for #handler_name {
+                fn handle_stream<'a>(
+                    &self,
+                    payload: Arc<DispatchPayload<'a>>,
+                    value: Value,
+                ) -> Result<SignalStream<'a>, String> {
+                    let input = serde_json::from_value::<#value_param_type>(value)
+                        .map_err(|error| format!("Invalid signal : {}", error))?;
+                    let store: &'a RxStore = payload.store;
+                    #invocation
+                    Ok(to_signal_stream(stream))
+                }
+
+                fn get_name(&self) -> &str {
+                    #trigger_kind_str
+                }
+
+                #timeout_fn
+
+                #max_concurrency_fn
+            }
+
+    };
+    TokenStream::from(expanded)
+}
+
+#[proc_macro]
+pub fn stream_hooks(input: TokenStream) -> TokenStream {
+    let mut hook_names = vec![];
+    let metas_parser = syn::meta::parser(|meta| {
+        hook_names.push(meta.path.clone());
+        Ok(())
+    });
+
+    parse_macro_input!(input with metas_parser);
+
+    let mut camel_case_hooks = Vec::new();
+    for hook_name in &hook_names {
+        let camel_case_name = snake_to_camel(&hook_name.to_token_stream().to_string());
+        let handler_name = format!("{}StreamHandler", camel_case_name);
+        let ident = Ident::new(&handler_name, Span::call_site());
+        camel_case_hooks.push(quote! {
+            hooks.push(Box::new(#ident {}));
+        });
+    }
+
+    let expanded = quote! {
+        {
+            let mut hooks: Vec<Box<dyn StreamHookHandler + Send + Sync>> = Vec::new();
+            #(#camel_case_hooks)*
+            hooks
+        }
+    };
+
     TokenStream::from(expanded)
 }
 
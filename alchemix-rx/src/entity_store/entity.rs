@@ -2,11 +2,42 @@ use std::any::Any;
 
 use serde::{de::DeserializeOwned, Serialize};
 
+use crate::entity_store::{GeoPoint, ValidationErrors};
+
 pub trait Entity: Any + Serialize + DeserializeOwned + Clone + Sync + Send + 'static{
     fn get_id(&self) -> &str;
     fn get_kind(&self) -> &str;
     fn get_key(&self) -> String;
     fn get_fields_index(&self) -> Vec<FieldIndex>;
+
+    fn get_geo_point(&self) -> Option<GeoPoint> {
+        None
+    }
+
+    fn get_vector(&self) -> Option<&[f32]> {
+        None
+    }
+
+    fn get_vector_dim(&self) -> usize {
+        0
+    }
+
+    /// Checks the `#[validate(...)]` constraints of the fields and the custom
+    /// validation hook, entities are validated before being stored.
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        Ok(())
+    }
+
+    /// Names of the fields whose value differs in `other`
+    fn diff(&self, _other: &Self) -> Vec<&'static str> {
+        vec![]
+    }
+}
+
+/// Compares two field values through their serialized form, field types
+/// are not required to implement `PartialEq`.
+pub fn is_field_changed<V: Serialize + ?Sized>(value: &V, other: &V) -> bool {
+    bincode::serialize(value).ok() != bincode::serialize(other).ok()
 }
 
 #[derive(Debug)]
@@ -16,4 +47,41 @@ pub struct FieldIndex {
     pub name: String,
     pub value: String,
     pub stored_type: String
-}
\ No newline at end of file
+}
+
+/// Values written to the properties table for an indexed field.
+/// Collections produce one index row per element.
+pub trait IndexValue {
+    fn index_values(&self) -> Vec<String>;
+}
+
+macro_rules! impl_scalar_index_value {
+    ($($scalar:ty),*) => {
+        $(
+            impl IndexValue for $scalar {
+                fn index_values(&self) -> Vec<String> {
+                    vec![self.to_string()]
+                }
+            }
+        )*
+    };
+}
+
+impl_scalar_index_value!(
+    String, &str, bool, char, u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, f32, f64
+);
+
+impl<T: IndexValue> IndexValue for Vec<T> {
+    fn index_values(&self) -> Vec<String> {
+        self.iter().flat_map(|value| value.index_values()).collect()
+    }
+}
+
+impl<T: IndexValue> IndexValue for Option<T> {
+    fn index_values(&self) -> Vec<String> {
+        match self {
+            Some(value) => value.index_values(),
+            None => vec![],
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use std::{
+    any::Any,
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::entity_store::Entity;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub kind: String,
+    pub capacity: usize,
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// Bounded cache of decoded entities, one LRU per kind, keyed by entity key.
+pub struct EntityCache {
+    capacity: usize,
+    kinds: Mutex<HashMap<String, KindCache>>,
+}
+
+impl EntityCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            kinds: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get_capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn get<E: Entity>(&self, kind: &str, id: &str) -> Option<E> {
+        let mut kinds = self.kinds.lock().unwrap();
+        let kind_cache = kinds
+            .entry(kind.to_string())
+            .or_insert_with(|| KindCache::new(self.capacity));
+        let key = format!("{}#{}", kind, id);
+        let entity = kind_cache
+            .get(&key)
+            .and_then(|value| value.downcast_ref::<E>().cloned());
+        if entity.is_some() {
+            kind_cache.hits += 1;
+        } else {
+            kind_cache.misses += 1;
+        }
+        entity
+    }
+
+    /// Incremented by every invalidation of `kind`, entities loaded while it
+    /// changed may already be stale
+    pub fn get_generation(&self, kind: &str) -> u64 {
+        let mut kinds = self.kinds.lock().unwrap();
+        kinds
+            .entry(kind.to_string())
+            .or_insert_with(|| KindCache::new(self.capacity))
+            .generation
+    }
+
+    /// Inserts entities of `kind` loaded at `generation`, nothing is inserted
+    /// when the kind was invalidated since
+    pub fn insert_loaded<E: Entity>(&self, kind: &str, generation: u64, entities: &[E]) {
+        let mut kinds = self.kinds.lock().unwrap();
+        let kind_cache = kinds
+            .entry(kind.to_string())
+            .or_insert_with(|| KindCache::new(self.capacity));
+        if kind_cache.generation != generation {
+            return;
+        }
+        for entity in entities {
+            kind_cache.insert(entity.get_key(), Arc::new(entity.clone()));
+        }
+    }
+
+    pub fn insert<E: Entity>(&self, entities: &[E]) {
+        let mut kinds = self.kinds.lock().unwrap();
+        for entity in entities {
+            let kind_cache = kinds
+                .entry(entity.get_kind().to_string())
+                .or_insert_with(|| KindCache::new(self.capacity));
+            kind_cache.insert(entity.get_key(), Arc::new(entity.clone()));
+        }
+    }
+
+    pub fn invalidate(&self, kind: &str, ids: &[&str]) {
+        let mut kinds = self.kinds.lock().unwrap();
+        let kind_cache = kinds
+            .entry(kind.to_string())
+            .or_insert_with(|| KindCache::new(self.capacity));
+        kind_cache.generation += 1;
+        for id in ids {
+            kind_cache.remove(&format!("{}#{}", kind, id));
+        }
+    }
+
+    pub fn clear(&self) {
+        let mut kinds = self.kinds.lock().unwrap();
+        for kind_cache in kinds.values_mut() {
+            kind_cache.clear();
+        }
+    }
+
+    pub fn stats(&self) -> Vec<CacheStats> {
+        let kinds = self.kinds.lock().unwrap();
+        let mut stats: Vec<CacheStats> = kinds
+            .iter()
+            .map(|(kind, kind_cache)| CacheStats {
+                kind: kind.to_string(),
+                capacity: kind_cache.capacity,
+                entries: kind_cache.entries.len(),
+                hits: kind_cache.hits,
+                misses: kind_cache.misses,
+            })
+            .collect();
+        stats.sort_by(|a, b| a.kind.cmp(&b.kind));
+        stats
+    }
+}
+
+struct KindCache {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<String, (u64, Arc<dyn Any + Send + Sync>)>,
+    recency: BTreeMap<u64, String>,
+    generation: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl KindCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            tick: 0,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            generation: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    fn get(&mut self, key: &str) -> Option<Arc<dyn Any + Send + Sync>> {
+        self.tick += 1;
+        let tick = self.tick;
+        if let Some((last_used, value)) = self.entries.get_mut(key) {
+            self.recency.remove(last_used);
+            self.recency.insert(tick, key.to_string());
+            *last_used = tick;
+            Some(value.clone())
+        } else {
+            None
+        }
+    }
+
+    fn insert(&mut self, key: String, value: Arc<dyn Any + Send + Sync>) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.remove(&key);
+        while self.entries.len() >= self.capacity {
+            if let Some((_, oldest_key)) = self.recency.pop_first() {
+                self.entries.remove(&oldest_key);
+            } else {
+                break;
+            }
+        }
+        self.tick += 1;
+        self.recency.insert(self.tick, key.clone());
+        self.entries.insert(key, (self.tick, value));
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some((last_used, _)) = self.entries.remove(key) {
+            self.recency.remove(&last_used);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.generation += 1;
+        self.entries.clear();
+        self.recency.clear();
+    }
+}
@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+
+const EARTH_RADIUS_IN_METERS: f64 = 6_371_000.0;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct GeoPoint {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+impl GeoPoint {
+    pub fn new(lat: f64, lon: f64) -> Self {
+        Self { lat, lon }
+    }
+
+    /// Great-circle distance in meters (haversine formula).
+    pub fn distance_to(&self, other: &GeoPoint) -> f64 {
+        let d_lat = (other.lat - self.lat).to_radians();
+        let d_lon = (other.lon - self.lon).to_radians();
+        let a = (d_lat / 2.0).sin().powi(2)
+            + self.lat.to_radians().cos() * other.lat.to_radians().cos() * (d_lon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_IN_METERS * a.sqrt().asin()
+    }
+}
+
+/// A box with `min_lon` greater than `max_lon` crosses the antimeridian.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct GeoBounds {
+    pub min_lat: f64,
+    pub min_lon: f64,
+    pub max_lat: f64,
+    pub max_lon: f64,
+}
+
+impl GeoBounds {
+    pub fn new(min_lat: f64, min_lon: f64, max_lat: f64, max_lon: f64) -> Self {
+        Self {
+            min_lat,
+            min_lon,
+            max_lat,
+            max_lon,
+        }
+    }
+
+    /// Smallest box containing the circle of `radius` meters around `center`.
+    pub fn around(center: &GeoPoint, radius: f64) -> Self {
+        let d_lat = (radius / EARTH_RADIUS_IN_METERS).to_degrees();
+        let min_lat = (center.lat - d_lat).max(-90.0);
+        let max_lat = (center.lat + d_lat).min(90.0);
+        let widest_lat = min_lat.abs().max(max_lat.abs()).to_radians();
+        if widest_lat.cos() < 1e-6 {
+            return Self::new(min_lat, -180.0, max_lat, 180.0);
+        }
+        let d_lon = d_lat / widest_lat.cos();
+        if d_lon >= 180.0 {
+            return Self::new(min_lat, -180.0, max_lat, 180.0);
+        }
+        Self::new(min_lat, wrap_lon(center.lon - d_lon), max_lat, wrap_lon(center.lon + d_lon))
+    }
+
+    /// Longitude ranges covered by the box, two when it crosses the antimeridian
+    pub fn lon_ranges(&self) -> Vec<(f64, f64)> {
+        if self.min_lon <= self.max_lon {
+            vec![(self.min_lon, self.max_lon)]
+        } else {
+            vec![(self.min_lon, 180.0), (-180.0, self.max_lon)]
+        }
+    }
+
+    pub fn contains(&self, point: &GeoPoint) -> bool {
+        point.lat >= self.min_lat
+            && point.lat <= self.max_lat
+            && self
+                .lon_ranges()
+                .iter()
+                .any(|(min_lon, max_lon)| point.lon >= *min_lon && point.lon <= *max_lon)
+    }
+}
+
+// Longitude brought back to [-180, 180)
+fn wrap_lon(lon: f64) -> f64 {
+    (lon + 180.0).rem_euclid(360.0) - 180.0
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum GeoQuery {
+    BoundingBox(GeoBounds),
+    /// Entities within a distance in meters from a point, nearest first
+    Radius(GeoPoint, f64),
+}
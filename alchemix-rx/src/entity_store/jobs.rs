@@ -0,0 +1,75 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+pub const JOB_PENDING: &str = "pending";
+pub const JOB_RUNNING: &str = "running";
+pub const JOB_DEAD: &str = "dead";
+
+/// A deferred entity hook call, `payload` holds the entities as JSON.
+/// Jobs failing `max_attempts` times are moved to the dead jobs table.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct Job {
+    pub id: i64,
+    pub hook: String,
+    pub action: String,
+    pub kind: String,
+    pub payload: String,
+    pub attempts: i64,
+    pub status: String,
+    pub run_at: i64,
+    pub created_at: i64,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct JobSettings {
+    pub workers: usize,
+    pub max_attempts: i64,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Wait of an idle worker before looking for due jobs again
+    pub poll_interval: Duration,
+}
+
+impl JobSettings {
+    pub fn new() -> Self {
+        Self {
+            workers: 2,
+            max_attempts: 5,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(300),
+            poll_interval: Duration::from_millis(500),
+        }
+    }
+
+    /// Exponential backoff before the retry following `attempts` failures
+    pub fn get_retry_delay(&self, attempts: i64) -> Duration {
+        let factor = 2u32.saturating_pow(attempts.saturating_sub(1).clamp(0, 31) as u32);
+        self.base_delay.saturating_mul(factor).min(self.max_delay)
+    }
+}
+
+impl Default for JobSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Value given to a deferred hook, `Changes` are the `(previous, new)` pairs
+/// of the change hooks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobPayload {
+    Entities,
+    Changes,
+}
+
+/// A deferred hook of a write, its job is queued in the write transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeferredHook {
+    pub hook: String,
+    pub action: String,
+    pub kind: String,
+    pub payload: JobPayload,
+}
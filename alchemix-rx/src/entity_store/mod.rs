@@ -1,7 +1,25 @@
 mod entity;
+mod entity_cache;
+mod geo;
+mod jobs;
+mod query;
+mod replication;
+mod vector_index;
 mod sqlite_entity_store;
+mod store_stats;
+mod validation;
+mod store_error;
 mod entity_schema;
 
 pub use entity::*;
+pub use entity_cache::*;
+pub use geo::*;
+pub use jobs::*;
+pub use query::*;
+pub use replication::*;
+pub use vector_index::*;
 pub use sqlite_entity_store::*;
+pub use store_stats::*;
+pub use validation::*;
+pub use store_error::*;
 pub use entity_schema::*;
\ No newline at end of file
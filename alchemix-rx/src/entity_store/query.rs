@@ -0,0 +1,299 @@
+use std::marker::PhantomData;
+
+use serde::{Deserialize, Serialize};
+
+/// A value compared against the properties table.
+/// Numbers are compared numerically, text lexicographically.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum QueryValue {
+    Text(String),
+    Number(f64),
+}
+
+macro_rules! impl_query_value_from {
+    ($variant:ident, $convert:expr, $($source:ty),*) => {
+        $(
+            impl From<$source> for QueryValue {
+                fn from(value: $source) -> Self {
+                    QueryValue::$variant($convert(value))
+                }
+            }
+        )*
+    };
+}
+
+impl_query_value_from!(Text, |value: String| value, String);
+impl_query_value_from!(Text, |value: &str| value.to_string(), &str);
+impl_query_value_from!(Text, |value: bool| value.to_string(), bool);
+impl_query_value_from!(Text, |value: char| value.to_string(), char);
+impl_query_value_from!(Number, |value: i8| value as f64, i8);
+impl_query_value_from!(Number, |value: i16| value as f64, i16);
+impl_query_value_from!(Number, |value: i32| value as f64, i32);
+impl_query_value_from!(Number, |value: i64| value as f64, i64);
+impl_query_value_from!(Number, |value: isize| value as f64, isize);
+impl_query_value_from!(Number, |value: u8| value as f64, u8);
+impl_query_value_from!(Number, |value: u16| value as f64, u16);
+impl_query_value_from!(Number, |value: u32| value as f64, u32);
+impl_query_value_from!(Number, |value: u64| value as f64, u64);
+impl_query_value_from!(Number, |value: usize| value as f64, usize);
+impl_query_value_from!(Number, |value: f32| value as f64, f32);
+impl_query_value_from!(Number, |value: f64| value, f64);
+
+/// Field type of indexed paths going through an `entity_part`,
+/// their value type is not known by the `entity` macro.
+pub struct AnyValue;
+
+/// Values accepted when comparing a field of type `T`.
+pub trait FieldOperand<T>: Into<QueryValue> {}
+
+macro_rules! impl_field_operand {
+    ($($scalar:ty),*) => {
+        $(
+            impl FieldOperand<$scalar> for $scalar {}
+        )*
+    };
+}
+
+impl_field_operand!(
+    String, bool, char, u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, f32, f64
+);
+
+impl FieldOperand<String> for &str {}
+
+impl<T, V: FieldOperand<T>> FieldOperand<Vec<T>> for V {}
+
+impl<T, V: FieldOperand<T>> FieldOperand<Option<T>> for V {}
+
+impl<V: Into<QueryValue>> FieldOperand<AnyValue> for V {}
+
+/// Field types supporting text operators.
+pub trait TextField {}
+
+impl TextField for String {}
+impl TextField for AnyValue {}
+impl<T: TextField> TextField for Vec<T> {}
+impl<T: TextField> TextField for Option<T> {}
+
+/// An indexed field of an entity, generated by the `entity` macro
+/// in the `fields()` accessor of the entity.
+pub struct IndexedField<T> {
+    name: &'static str,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> IndexedField<T> {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn get_name(&self) -> &str {
+        self.name
+    }
+
+    fn compare(&self, op: CompareOp, value: impl Into<QueryValue>) -> Predicate {
+        Predicate::Compare {
+            field: self.name.to_string(),
+            op,
+            value: value.into(),
+        }
+    }
+
+    pub fn eq(&self, value: impl FieldOperand<T>) -> Predicate {
+        self.compare(CompareOp::Eq, value)
+    }
+
+    pub fn ne(&self, value: impl FieldOperand<T>) -> Predicate {
+        self.compare(CompareOp::Ne, value)
+    }
+
+    pub fn gt(&self, value: impl FieldOperand<T>) -> Predicate {
+        self.compare(CompareOp::Gt, value)
+    }
+
+    pub fn gte(&self, value: impl FieldOperand<T>) -> Predicate {
+        self.compare(CompareOp::Gte, value)
+    }
+
+    pub fn lt(&self, value: impl FieldOperand<T>) -> Predicate {
+        self.compare(CompareOp::Lt, value)
+    }
+
+    pub fn lte(&self, value: impl FieldOperand<T>) -> Predicate {
+        self.compare(CompareOp::Lte, value)
+    }
+
+    pub fn is_in<V: FieldOperand<T>>(&self, values: Vec<V>) -> Predicate {
+        Predicate::In {
+            field: self.name.to_string(),
+            values: values.into_iter().map(|value| value.into()).collect(),
+        }
+    }
+}
+
+impl<T: TextField> IndexedField<T> {
+    pub fn starts_with(&self, prefix: &str) -> Predicate {
+        Predicate::StartsWith {
+            field: self.name.to_string(),
+            prefix: prefix.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+}
+
+impl CompareOp {
+    fn to_sql(self) -> &'static str {
+        match self {
+            CompareOp::Eq => "=",
+            CompareOp::Ne => "!=",
+            CompareOp::Gt => ">",
+            CompareOp::Gte => ">=",
+            CompareOp::Lt => "<",
+            CompareOp::Lte => "<=",
+        }
+    }
+}
+
+/// A condition on the indexed fields of an entity kind.
+/// A field condition matches an entity when any of its index values matches,
+/// so conditions on multi-valued fields test each element.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Predicate {
+    Compare {
+        field: String,
+        op: CompareOp,
+        value: QueryValue,
+    },
+    StartsWith {
+        field: String,
+        prefix: String,
+    },
+    In {
+        field: String,
+        values: Vec<QueryValue>,
+    },
+    And(Vec<Predicate>),
+    Or(Vec<Predicate>),
+    Not(Box<Predicate>),
+}
+
+impl Predicate {
+    pub fn and(self, other: Predicate) -> Predicate {
+        match self {
+            Predicate::And(mut predicates) => {
+                predicates.push(other);
+                Predicate::And(predicates)
+            }
+            predicate => Predicate::And(vec![predicate, other]),
+        }
+    }
+
+    pub fn or(self, other: Predicate) -> Predicate {
+        match self {
+            Predicate::Or(mut predicates) => {
+                predicates.push(other);
+                Predicate::Or(predicates)
+            }
+            predicate => Predicate::Or(vec![predicate, other]),
+        }
+    }
+
+    /// Compiles the predicate to a SQL condition on the `key` column of the
+    /// entity table, the values to bind are pushed in order into `binds`.
+    pub fn to_sql(&self, kind: &str, binds: &mut Vec<QueryValue>) -> String {
+        match self {
+            Predicate::Compare { field, op, value } => {
+                let column = match value {
+                    QueryValue::Number(_) => "CAST(value AS REAL)",
+                    QueryValue::Text(_) => "value",
+                };
+                let condition = format!("{} {} ?", column, op.to_sql());
+                Self::property_sql(kind, field, &condition, vec![value.clone()], binds)
+            }
+            Predicate::StartsWith { field, prefix } => {
+                // LIKE ignores the case of ASCII letters, the prefix is compared as is
+                let prefix = QueryValue::Text(prefix.clone());
+                Self::property_sql(
+                    kind,
+                    field,
+                    "substr(value, 1, length(?)) = ?",
+                    vec![prefix.clone(), prefix],
+                    binds,
+                )
+            }
+            Predicate::In { field, values } => {
+                if values.is_empty() {
+                    return "0".to_string();
+                }
+                let conditions: Vec<&str> = values
+                    .iter()
+                    .map(|value| match value {
+                        QueryValue::Number(_) => "CAST(value AS REAL) = ?",
+                        QueryValue::Text(_) => "value = ?",
+                    })
+                    .collect();
+                let condition = format!("({})", conditions.join(" OR "));
+                Self::property_sql(kind, field, &condition, values.clone(), binds)
+            }
+            Predicate::And(predicates) => {
+                Self::join_sql(kind, predicates, " AND ", "1", binds)
+            }
+            Predicate::Or(predicates) => {
+                Self::join_sql(kind, predicates, " OR ", "0", binds)
+            }
+            Predicate::Not(predicate) => format!("NOT ({})", predicate.to_sql(kind, binds)),
+        }
+    }
+
+    fn property_sql(
+        kind: &str,
+        field: &str,
+        condition: &str,
+        values: Vec<QueryValue>,
+        binds: &mut Vec<QueryValue>,
+    ) -> String {
+        binds.push(QueryValue::Text(kind.to_string()));
+        binds.push(QueryValue::Text(field.to_string()));
+        binds.extend(values);
+        format!(
+            "key IN (SELECT key FROM properties WHERE kind = ? AND name = ? AND {})",
+            condition
+        )
+    }
+
+    fn join_sql(
+        kind: &str,
+        predicates: &[Predicate],
+        separator: &str,
+        empty: &str,
+        binds: &mut Vec<QueryValue>,
+    ) -> String {
+        if predicates.is_empty() {
+            return empty.to_string();
+        }
+        let conditions: Vec<String> = predicates
+            .iter()
+            .map(|predicate| format!("({})", predicate.to_sql(kind, binds)))
+            .collect();
+        conditions.join(separator)
+    }
+}
+
+impl std::ops::Not for Predicate {
+    type Output = Predicate;
+
+    fn not(self) -> Predicate {
+        Predicate::Not(Box::new(self))
+    }
+}
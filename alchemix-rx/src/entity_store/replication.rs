@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use crate::entity_store::Entity;
+
+/// Latest revision of an entity in the replication log.
+/// A revision is identified by its `revision` counter and the `origin` node
+/// which wrote it, `parent_*` identify the revision it replaced.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct Change {
+    pub key: String,
+    pub id: String,
+    pub kind: String,
+    pub revision: i64,
+    pub origin: String,
+    pub parent_revision: i64,
+    pub parent_origin: String,
+    pub timestamp: i64,
+    pub deleted: bool,
+    pub seq: i64,
+    pub data: Option<Vec<u8>>,
+}
+
+impl Change {
+    pub fn is_same_revision(&self, other: &Change) -> bool {
+        self.revision == other.revision && self.origin == other.origin
+    }
+
+    pub fn is_child_of(&self, other: &Change) -> bool {
+        self.parent_revision == other.revision && self.parent_origin == other.origin
+    }
+}
+
+/// Changes of a node following a sequence cursor, `last_seq` is the cursor
+/// to use for the next pull.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChangeSet {
+    pub node_id: String,
+    pub changes: Vec<Change>,
+    pub last_seq: i64,
+}
+
+/// Body of the pull requests sent to a remote node.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PullRequest {
+    pub since: i64,
+    pub limit: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ApplyReport {
+    pub applied: usize,
+    pub skipped: usize,
+    pub conflicts: usize,
+    /// Keys of the remote entities failing their validation, they are not written
+    pub rejected: Vec<String>,
+}
+
+impl ApplyReport {
+    pub fn merge(&mut self, other: &ApplyReport) {
+        self.applied += other.applied;
+        self.skipped += other.skipped;
+        self.conflicts += other.conflicts;
+        self.rejected.extend(other.rejected.iter().cloned());
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SyncReport {
+    pub pulled: ApplyReport,
+    pub pushed: ApplyReport,
+}
+
+pub enum Resolution {
+    KeepLocal,
+    TakeRemote,
+    /// Entity data written as a new local revision
+    Merged(Vec<u8>),
+}
+
+/// Decides which side wins when both nodes changed an entity concurrently.
+pub trait ConflictResolver: Send + Sync {
+    fn resolve(&self, local: &Change, remote: &Change) -> Resolution;
+}
+
+/// Keeps the most recent write, ties are broken on revision then origin so
+/// every node picks the same winner.
+pub struct LastWriterWins;
+
+impl ConflictResolver for LastWriterWins {
+    fn resolve(&self, local: &Change, remote: &Change) -> Resolution {
+        let local_order = (local.timestamp, local.revision, &local.origin);
+        let remote_order = (remote.timestamp, remote.revision, &remote.origin);
+        if remote_order > local_order {
+            Resolution::TakeRemote
+        } else {
+            Resolution::KeepLocal
+        }
+    }
+}
+
+type MergeFn = Box<dyn Fn(&[u8], &[u8]) -> Option<Vec<u8>> + Send + Sync>;
+
+/// Merges conflicting entities with a function registered per kind,
+/// other kinds and deletions fall back to last writer wins.
+pub struct MergeResolver {
+    merges: HashMap<String, MergeFn>,
+}
+
+impl MergeResolver {
+    pub fn new() -> Self {
+        Self {
+            merges: HashMap::new(),
+        }
+    }
+
+    pub fn with_merge<E: Entity>(mut self, kind: &str, merge: fn(&E, &E) -> E) -> Self {
+        let merge_fn = move |local: &[u8], remote: &[u8]| -> Option<Vec<u8>> {
+            let local: E = bincode::deserialize(local).ok()?;
+            let remote: E = bincode::deserialize(remote).ok()?;
+            bincode::serialize(&merge(&local, &remote)).ok()
+        };
+        self.merges.insert(kind.to_string(), Box::new(merge_fn));
+        self
+    }
+}
+
+impl Default for MergeResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConflictResolver for MergeResolver {
+    fn resolve(&self, local: &Change, remote: &Change) -> Resolution {
+        let merged = match (self.merges.get(&remote.kind), &local.data, &remote.data) {
+            (Some(merge), Some(local_data), Some(remote_data)) => merge(local_data, remote_data),
+            _ => None,
+        };
+        match merged {
+            Some(data) => Resolution::Merged(data),
+            None => LastWriterWins.resolve(local, remote),
+        }
+    }
+}
+
+/// A node to synchronize with, either a local store or a remote server.
+#[async_trait]
+pub trait ReplicationPeer: Send + Sync {
+    async fn get_node_id(&self) -> Result<String, String>;
+
+    async fn pull(&self, since: i64, limit: usize) -> Result<ChangeSet, String>;
+
+    async fn push(&self, change_set: ChangeSet) -> Result<ApplyReport, String>;
+}
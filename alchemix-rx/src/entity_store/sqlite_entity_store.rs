@@ -1,29 +1,130 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
 use alchemix_utils::file_io;
 use futures::executor::block_on;
-use sqlx::{migrate::MigrateDatabase, sqlite::SqlitePoolOptions, FromRow, Pool, Sqlite};
+use sqlx::{
+    migrate::MigrateDatabase, sqlite::SqlitePoolOptions, FromRow, Pool, Sqlite, SqliteConnection, Transaction,
+};
 
-use crate::entity_store::{Entity, FieldIndex};
+use crate::entity_store::{
+    vector_from_bytes, vector_to_bytes, ApplyReport, CacheStats, Change, ChangeSet,
+    ConflictResolver, DeferredHook, Entity, EntityCache, GeoBounds, GeoQuery, HnswIndex, HnswSettings, Job,
+    JobPayload, KindStats, JOB_DEAD, JOB_PENDING, JOB_RUNNING, LastWriterWins, Predicate, QueryValue, Resolution, StoreError, StoreStats, ValidationErrors, VectorMetric,
+};
 
 #[derive(FromRow)]
 struct EntityData {
     data: Vec<u8>,
 }
 
+/// Clones are handles on the same store, they share the connection pool,
+/// the cache and the vector indexes.
+#[derive(Clone)]
 pub struct SQLiteEntityStore {
     pool: Option<Pool<Sqlite>>,
     max_pool: usize,
     path: String,
     pragmas: Option<String>,
+    cache: Option<Arc<EntityCache>>,
+    vector_settings: Option<HnswSettings>,
+    // None until built from the vectors table
+    vector_indexes: Arc<Mutex<Option<HashMap<String, HnswIndex>>>>,
+    // Node id written in the replication log, None when replication is disabled
+    node_id: Option<String>,
+    conflict_resolver: Arc<dyn ConflictResolver>,
+}
+
+// Kind, id and vector of an entity, None removes it from the index
+type VectorEntry = (String, String, Option<Vec<f32>>);
+
+/// Writes of several kinds in a single transaction, started by `begin_batch`.
+/// Nothing is visible to the other connections before `commit_batch`, dropping
+/// the batch rolls it back.
+pub struct StoreBatch {
+    tx: Transaction<'static, Sqlite>,
+    node_id: Option<String>,
+    // Kind and id of the written and removed entities, for the cache
+    keys: Vec<(String, String)>,
+    vectors: Vec<VectorEntry>,
+}
+
+impl StoreBatch {
+    /// Same as `SQLiteEntityStore::update_entities_with_previous`, in the batch transaction
+    pub async fn update_entities<T: Entity>(
+        &mut self,
+        entities: &Vec<T>,
+        load_previous: bool,
+        deferred: &[DeferredHook],
+    ) -> Result<Vec<Option<T>>, StoreError> {
+        let load_previous = load_previous || SQLiteEntityStore::has_change_jobs(deferred);
+        let mut previous_entities = vec![];
+        let mut errors = ValidationErrors::new();
+        for entity in entities {
+            if let Err(entity_errors) = entity.validate() {
+                errors.extend(entity_errors);
+            }
+        }
+        errors.into_result()?;
+        for entity in entities {
+            let key = entity.get_key();
+            if load_previous {
+                previous_entities.push(SQLiteEntityStore::read_entity(&mut self.tx, &key).await?);
+            }
+            SQLiteEntityStore::write_entity(&mut self.tx, entity).await?;
+            if let Some(node_id) = &self.node_id {
+                SQLiteEntityStore::record_revision(&mut self.tx, node_id, &key, entity.get_id(), entity.get_kind(), false)
+                    .await?;
+            }
+            self.keys.push((entity.get_kind().to_string(), entity.get_id().to_string()));
+            self.vectors.push(SQLiteEntityStore::get_vector_entry(entity));
+        }
+        SQLiteEntityStore::insert_jobs(&mut self.tx, deferred, entities, &previous_entities).await?;
+        Ok(previous_entities)
+    }
+
+    /// Removes the entities, returns the ones which were stored. A stored
+    /// entity which cannot be decoded is removed but not returned.
+    pub async fn remove_entities<T: Entity>(
+        &mut self,
+        kind: &str,
+        ids: &Vec<&str>,
+        deferred: &[DeferredHook],
+    ) -> Result<Vec<T>, StoreError> {
+        let mut removed_entities = vec![];
+        for id in ids {
+            let key = format!("{}#{}", kind, id);
+            let Some(row) = SQLiteEntityStore::read_entity_data(&mut self.tx, &key).await? else {
+                continue;
+            };
+            SQLiteEntityStore::delete_entity_rows(&mut self.tx, &key).await?;
+            if let Some(node_id) = &self.node_id {
+                SQLiteEntityStore::record_revision(&mut self.tx, node_id, &key, id, kind, true).await?;
+            }
+            self.keys.push((kind.to_string(), id.to_string()));
+            self.vectors.push((kind.to_string(), id.to_string(), None));
+            if let Ok(entity) = SQLiteEntityStore::entity_from_vec(&row.data) {
+                removed_entities.push(entity);
+            }
+        }
+        SQLiteEntityStore::insert_jobs(&mut self.tx, deferred, &removed_entities, &[]).await?;
+        Ok(removed_entities)
+    }
+}
+
+/// Entities written by `apply_changes`, for the hooks of the caller
+pub struct AppliedChanges<E> {
+    pub updated: Vec<E>,
+    pub deleted: Vec<E>,
+    pub report: ApplyReport,
 }
 
 impl SQLiteEntityStore {
     pub fn new(path: &str) -> Self {
-        let mut instance = Self {
-            pool: None,
-            max_pool: 5,
-            path: path.to_string(),
-            pragmas: None,
-        };
+        let mut instance = Self::new_closed(path);
         let res = block_on(instance.open());
         if res.is_err()  {
             println!("Error opening store : {}", path);
@@ -31,11 +132,63 @@ impl SQLiteEntityStore {
         instance
     }
 
+    // Store without a pool, `open` has to be called before using it
+    fn new_closed(path: &str) -> Self {
+        Self {
+            pool: None,
+            max_pool: 5,
+            path: path.to_string(),
+            pragmas: None,
+            cache: None,
+            vector_settings: None,
+            vector_indexes: Arc::new(Mutex::new(None)),
+            node_id: None,
+            conflict_resolver: Arc::new(LastWriterWins),
+        }
+    }
+
     pub fn with_pragmas(mut self, pragmas: &str) -> Self {
         self.pragmas = Some(pragmas.to_string());
         self
     }
 
+    /// Keeps up to `capacity` decoded entities per kind in memory for lookups by id.
+    pub fn with_cache(mut self, capacity: usize) -> Self {
+        self.cache = Some(Arc::new(EntityCache::new(capacity)));
+        self
+    }
+
+    /// Maintains an in-memory HNSW index per kind for `nearest` queries using
+    /// the same metric. The index is rebuilt from the stored vectors on open.
+    pub fn with_vector_index(mut self, settings: HnswSettings) -> Self {
+        self.vector_settings = Some(settings);
+        self
+    }
+
+    /// Records a revision of every write in the replication log, `node_id`
+    /// identifies the writes of this store on the other nodes.
+    pub fn with_replication(mut self, node_id: &str) -> Self {
+        self.node_id = Some(node_id.to_string());
+        self
+    }
+
+    /// Resolves concurrent changes received from other nodes, last writer wins by default.
+    pub fn with_conflict_resolver<R: ConflictResolver + 'static>(mut self, resolver: R) -> Self {
+        self.conflict_resolver = Arc::new(resolver);
+        self
+    }
+
+    pub fn get_node_id(&self) -> Option<&str> {
+        self.node_id.as_deref()
+    }
+
+    pub fn cache_stats(&self) -> Vec<CacheStats> {
+        match &self.cache {
+            Some(cache) => cache.stats(),
+            None => vec![],
+        }
+    }
+
     pub async fn open(&mut self) -> Result<(), sqlx::Error> {
         
         file_io::create_parent_dirs(&self.path);
@@ -66,76 +219,839 @@ impl SQLiteEntityStore {
             self.execute_batch(pragmas).await;
         }
         self.create_tables().await;
+        *self.vector_indexes.lock().unwrap() = None;
+        if self.vector_settings.is_some() {
+            self.ensure_vector_indexes().await;
+        }
         Ok(())
     }
 
-    pub async fn update_entities<T: Entity>(&self, entities: &Vec<T>) {
+    /// Validates then writes the entities, nothing is written when one of them is invalid.
+    pub async fn update_entities<T: Entity>(&self, entities: &Vec<T>) -> Result<(), StoreError> {
+        self.write_entities(entities, false, &[]).await?;
+        Ok(())
+    }
+
+    /// Same as `update_entities`, also returns the previous value of each entity,
+    /// read in the write transaction. The jobs of the `deferred` hooks are
+    /// queued in the same transaction.
+    pub async fn update_entities_with_previous<T: Entity>(
+        &self,
+        entities: &Vec<T>,
+        deferred: &[DeferredHook],
+    ) -> Result<Vec<Option<T>>, StoreError> {
+        self.write_entities(entities, true, deferred).await
+    }
+
+    async fn write_entities<T: Entity>(
+        &self,
+        entities: &Vec<T>,
+        load_previous: bool,
+        deferred: &[DeferredHook],
+    ) -> Result<Vec<Option<T>>, StoreError> {
+        let mut previous_entities = vec![];
+        let mut errors = ValidationErrors::new();
+        for entity in entities {
+            if let Err(entity_errors) = entity.validate() {
+                errors.extend(entity_errors);
+            }
+        }
+        errors.into_result()?;
         if let Some(pool) = &self.pool {
-            let insert_sql_command =
-                r#"INSERT or REPLACE INTO entity (key, id, kind, data) VALUES (?, ?, ?, ?)"#;
-            let mut tx = pool.begin().await.unwrap();
+            let mut tx = pool.begin().await.map_err(|error| format!("Unable to start write : {}", error))?;
 
             for entity in entities {
-                if let Ok(entity_vec) = Self::entity_to_vec(entity) {
-                    let _ = sqlx::query(insert_sql_command)
-                        .bind(entity.get_key())
-                        .bind(entity.get_id())
-                        .bind(entity.get_kind())
-                        .bind(entity_vec)
-                        .execute(&mut *tx)
-                        .await;
-                } else {
-                    println!("Unable to serialize entity")
-                };
+                if load_previous {
+                    previous_entities.push(Self::read_entity(&mut tx, &entity.get_key()).await?);
+                }
+                Self::write_entity(&mut tx, entity).await?;
+                if let Some(node_id) = &self.node_id {
+                    let key = entity.get_key();
+                    Self::record_revision(&mut tx, node_id, &key, entity.get_id(), entity.get_kind(), false).await?;
+                }
             }
-            let _ = tx.commit().await;
-            self.update_entities_index(entities).await;
+            Self::insert_jobs(&mut tx, deferred, entities, &previous_entities).await?;
+            tx.commit().await.map_err(|error| format!("Unable to commit write : {}", error))?;
+            if let Some(cache) = &self.cache {
+                for entity in entities {
+                    cache.invalidate(entity.get_kind(), &[entity.get_id()]);
+                }
+            }
+            self.update_vector_indexes(entities).await;
         }
+        Ok(previous_entities)
     }
 
-    async fn update_entities_index<T: Entity>(&self, entities: &Vec<T>) {
-        let fields_index: Vec<FieldIndex> = entities
-            .iter()
-            .map(|e| {
-                let fields_index: Vec<FieldIndex> = e
-                    .get_fields_index()
-                    .into_iter()
-                    .map(|field_index| field_index)
-                    .collect();
-                fields_index
-            })
-            .fold(vec![], |mut acc, mut index| {
-                acc.append(&mut index);
-                acc
-            });
+    pub async fn begin_batch(&self) -> Result<StoreBatch, String> {
+        let pool = self.pool.as_ref().ok_or(format!("Store is not open : {}", &self.path))?;
+        let tx = pool
+            .begin()
+            .await
+            .map_err(|error| format!("Unable to start batch : {}", error))?;
+        Ok(StoreBatch {
+            tx,
+            node_id: self.node_id.clone(),
+            keys: vec![],
+            vectors: vec![],
+        })
+    }
 
-        let index_queries:Vec<String> = fields_index.iter().map(|fi| {
-                let key = format!("{}#{}", fi.kind, fi.entity_id);
-                let update_index_query = format!(
-                    "INSERT or REPLACE INTO properties (key, id, kind, name, value) VALUES (\'{}\', \'{}\', \'{}\', \'{}\', \'{}\');",
-                    key, fi.entity_id, fi.kind, fi.name, fi.value
-                );
-                update_index_query
-            }).collect();
-        let update_index_query = index_queries.join("\n");
-        self.execute_batch(&update_index_query).await;
+    pub async fn commit_batch(&self, batch: StoreBatch) -> Result<(), String> {
+        batch
+            .tx
+            .commit()
+            .await
+            .map_err(|error| format!("Unable to commit batch : {}", error))?;
+        if let Some(cache) = &self.cache {
+            for (kind, id) in &batch.keys {
+                cache.invalidate(kind, &[id.as_str()]);
+            }
+        }
+        self.update_vector_entries(batch.vectors).await;
+        Ok(())
     }
 
-    pub async fn remove_entities<T: Entity>(&self, kind: &str, ids: &Vec<&str>) -> Vec<T>{
-        let stored_entities = self.get_entities_of_kind(kind, ids).await;
+    async fn read_entity<T: Entity>(tx: &mut Transaction<'_, Sqlite>, key: &str) -> Result<Option<T>, String> {
+        let row = Self::read_entity_data(tx, key).await?;
+        Ok(row.and_then(|row| Self::entity_from_vec(&row.data).ok()))
+    }
 
-        let keys_strs: Vec<String> = ids.iter().map(|id| format!("\'{}#{}\'", kind, id)).collect();
+    async fn read_entity_data(tx: &mut Transaction<'_, Sqlite>, key: &str) -> Result<Option<EntityData>, String> {
+        sqlx::query_as("SELECT data FROM entity WHERE key = ?")
+            .bind(key)
+            .fetch_optional(&mut **tx)
+            .await
+            .map_err(|error| format!("Unable to read {} : {}", key, error))
+    }
+
+    // Statement errors are returned so the caller drops the transaction
+    async fn write_entity<T: Entity>(tx: &mut Transaction<'_, Sqlite>, entity: &T) -> Result<(), String> {
+        let insert_sql_command =
+            r#"INSERT or REPLACE INTO entity (key, id, kind, data) VALUES (?, ?, ?, ?)"#;
+        let entity_vec =
+            Self::entity_to_vec(entity).map_err(|_| format!("Unable to serialize entity {}", entity.get_key()))?;
+        Self::execute_write(
+            tx,
+            sqlx::query(insert_sql_command)
+                .bind(entity.get_key())
+                .bind(entity.get_id())
+                .bind(entity.get_kind())
+                .bind(entity_vec),
+            entity,
+        )
+        .await?;
+        Self::update_entity_index(tx, entity).await?;
+        Self::update_entity_geo_index(tx, entity).await?;
+        Self::update_entity_vector(tx, entity).await
+    }
 
+    async fn execute_write<'q, T: Entity>(
+        tx: &mut Transaction<'_, Sqlite>,
+        query: sqlx::query::Query<'q, Sqlite, sqlx::sqlite::SqliteArguments<'q>>,
+        entity: &T,
+    ) -> Result<(), String> {
+        query
+            .execute(&mut **tx)
+            .await
+            .map(|_| ())
+            .map_err(|error| format!("Unable to write {} : {}", entity.get_key(), error))
+    }
 
-        let keys_str = format!("({})", keys_strs.join(", "));
+    async fn update_entity_index<T: Entity>(tx: &mut Transaction<'_, Sqlite>, entity: &T) -> Result<(), String> {
+        Self::execute_write(tx, sqlx::query("DELETE FROM properties WHERE key = ?").bind(entity.get_key()), entity).await?;
 
-        let delete_entity_query = format!("DELETE from entity WHERE key IN {};", keys_str);
+        // Multi-valued fields produce several rows sharing the same key and name
+        for field_index in entity.get_fields_index() {
+            let key = format!("{}#{}", field_index.kind, field_index.entity_id);
+            let insert_query = sqlx::query(
+                "INSERT INTO properties (key, id, kind, name, value) VALUES (?, ?, ?, ?, ?)",
+            )
+            .bind(key)
+            .bind(&field_index.entity_id)
+            .bind(&field_index.kind)
+            .bind(&field_index.name)
+            .bind(&field_index.value);
+            Self::execute_write(tx, insert_query, entity).await?;
+        }
+        Ok(())
+    }
 
-        let delete_properties_query = format!("DELETE from properties WHERE key IN {};", keys_str);
+    async fn update_entity_geo_index<T: Entity>(tx: &mut Transaction<'_, Sqlite>, entity: &T) -> Result<(), String> {
+        let delete_query = sqlx::query("DELETE FROM geo_index WHERE id IN (SELECT id FROM geo_keys WHERE key = ?)")
+            .bind(entity.get_key());
+        Self::execute_write(tx, delete_query, entity).await?;
 
-        let delete_query = format!("{}{}", delete_entity_query, delete_properties_query);
-        self.execute_batch(&delete_query).await;
-        stored_entities
+        if let Some(point) = entity.get_geo_point() {
+            let key_query = sqlx::query("INSERT OR IGNORE INTO geo_keys (key, kind) VALUES (?, ?)")
+                .bind(entity.get_key())
+                .bind(entity.get_kind());
+            Self::execute_write(tx, key_query, entity).await?;
+            let insert_query = sqlx::query(
+                "INSERT INTO geo_index (id, min_lat, max_lat, min_lon, max_lon) SELECT id, ?, ?, ?, ? FROM geo_keys WHERE key = ?",
+            )
+            .bind(point.lat)
+            .bind(point.lat)
+            .bind(point.lon)
+            .bind(point.lon)
+            .bind(entity.get_key());
+            Self::execute_write(tx, insert_query, entity).await
+        } else {
+            Self::execute_write(tx, sqlx::query("DELETE FROM geo_keys WHERE key = ?").bind(entity.get_key()), entity).await
+        }
+    }
+
+    async fn update_entity_vector<T: Entity>(tx: &mut Transaction<'_, Sqlite>, entity: &T) -> Result<(), String> {
+        let vector = entity
+            .get_vector()
+            .filter(|vector| Self::check_vector_dim(entity, vector));
+        if let Some(vector) = vector {
+            let insert_query = sqlx::query("INSERT or REPLACE INTO vectors (key, id, kind, data) VALUES (?, ?, ?, ?)")
+                .bind(entity.get_key())
+                .bind(entity.get_id())
+                .bind(entity.get_kind())
+                .bind(vector_to_bytes(vector));
+            Self::execute_write(tx, insert_query, entity).await
+        } else {
+            Self::execute_write(tx, sqlx::query("DELETE FROM vectors WHERE key = ?").bind(entity.get_key()), entity).await
+        }
+    }
+
+    fn check_vector_dim<T: Entity>(entity: &T, vector: &[f32]) -> bool {
+        if vector.len() == entity.get_vector_dim() {
+            true
+        } else {
+            println!(
+                "Vector of {} ignored : expected dimension {}, found {}",
+                entity.get_key(),
+                entity.get_vector_dim(),
+                vector.len()
+            );
+            false
+        }
+    }
+
+    async fn update_vector_indexes<T: Entity>(&self, entities: &Vec<T>) {
+        let entries = entities.iter().map(Self::get_vector_entry).collect();
+        self.update_vector_entries(entries).await;
+    }
+
+    fn get_vector_entry<T: Entity>(entity: &T) -> VectorEntry {
+        let vector = match entity.get_vector() {
+            Some(vector) if vector.len() == entity.get_vector_dim() => Some(vector.to_vec()),
+            _ => None,
+        };
+        (entity.get_kind().to_string(), entity.get_id().to_string(), vector)
+    }
+
+    async fn update_vector_entries(&self, entries: Vec<VectorEntry>) {
+        if let Some(settings) = self.vector_settings {
+            self.ensure_vector_indexes().await;
+            let mut vector_indexes = self.vector_indexes.lock().unwrap();
+            if let Some(vector_indexes) = vector_indexes.as_mut() {
+                for (kind, id, vector) in entries {
+                    let index = vector_indexes
+                        .entry(kind)
+                        .or_insert_with(|| HnswIndex::new(settings));
+                    match vector {
+                        Some(vector) => index.insert(&id, vector),
+                        None => index.remove(&id),
+                    }
+                }
+            }
+        }
+    }
+
+    async fn ensure_vector_indexes(&self) {
+        let Some(settings) = self.vector_settings else {
+            return;
+        };
+        if self.vector_indexes.lock().unwrap().is_some() {
+            return;
+        }
+        let mut vector_indexes: HashMap<String, HnswIndex> = HashMap::new();
+        if let Some(pool) = &self.pool {
+            let rows: Vec<(String, String, Vec<u8>)> =
+                sqlx::query_as("SELECT kind, id, data FROM vectors")
+                    .fetch_all(pool)
+                    .await
+                    .unwrap_or_default();
+            for (kind, id, data) in rows {
+                vector_indexes
+                    .entry(kind)
+                    .or_insert_with(|| HnswIndex::new(settings))
+                    .insert(&id, vector_from_bytes(&data));
+            }
+        }
+        let mut current_indexes = self.vector_indexes.lock().unwrap();
+        if current_indexes.is_none() {
+            *current_indexes = Some(vector_indexes);
+        }
+    }
+
+    /// Returns the `k` entities whose vector is the most similar to `query`, with their score.
+    /// Uses the HNSW index when it was configured with the same metric, a full scan otherwise.
+    pub async fn nearest<E: Entity>(
+        &self,
+        kind: &str,
+        query: &[f32],
+        k: usize,
+        metric: VectorMetric,
+    ) -> Vec<(E, f32)> {
+        let use_index = self
+            .vector_settings
+            .map(|settings| settings.metric == metric)
+            .unwrap_or(false);
+        let matches = if use_index {
+            self.ensure_vector_indexes().await;
+            let vector_indexes = self.vector_indexes.lock().unwrap();
+            vector_indexes
+                .as_ref()
+                .and_then(|indexes| indexes.get(kind))
+                .map(|index| index.search(query, k))
+                .unwrap_or_default()
+        } else {
+            self.nearest_exact(kind, query, k, metric).await
+        };
+
+        let ids: Vec<&str> = matches.iter().map(|(id, _)| id.as_str()).collect();
+        let entities: Vec<E> = self.get_entities_of_kind(kind, &ids).await;
+        let mut entities_by_id: HashMap<String, E> = entities
+            .into_iter()
+            .map(|entity| (entity.get_id().to_string(), entity))
+            .collect();
+        matches
+            .into_iter()
+            .filter_map(|(id, score)| Some((entities_by_id.remove(&id)?, score)))
+            .collect()
+    }
+
+    async fn nearest_exact(
+        &self,
+        kind: &str,
+        query: &[f32],
+        k: usize,
+        metric: VectorMetric,
+    ) -> Vec<(String, f32)> {
+        if let Some(pool) = &self.pool {
+            let rows: Vec<(String, Vec<u8>)> =
+                sqlx::query_as("SELECT id, data FROM vectors WHERE kind = ?")
+                    .bind(kind)
+                    .fetch_all(pool)
+                    .await
+                    .unwrap();
+            let mut scores: Vec<(String, f32)> = rows
+                .into_iter()
+                .map(|(id, data)| (id, vector_from_bytes(&data)))
+                .filter(|(_, vector)| vector.len() == query.len())
+                .map(|(id, vector)| (id, metric.score(query, &vector)))
+                .collect();
+            scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+            scores.truncate(k);
+            scores
+        } else {
+            vec![]
+        }
+    }
+
+    pub async fn remove_entities<T: Entity>(&self, kind: &str, ids: &Vec<&str>) -> Result<Vec<T>, String> {
+        self.remove_entities_with_hooks(kind, ids, &[]).await
+    }
+
+    /// Same as `remove_entities`, the jobs of the `deferred` hooks are queued
+    /// with the deletion.
+    pub async fn remove_entities_with_hooks<T: Entity>(
+        &self,
+        kind: &str,
+        ids: &Vec<&str>,
+        deferred: &[DeferredHook],
+    ) -> Result<Vec<T>, String> {
+        let stored_entities: Vec<T> = self.get_entities_of_kind(kind, ids).await;
+
+        if let Some(pool) = &self.pool {
+            let mut tx = pool.begin().await.map_err(|error| format!("Unable to start delete : {}", error))?;
+            for id in ids {
+                Self::delete_entity_rows(&mut tx, &format!("{}#{}", kind, id)).await?;
+            }
+            if let Some(node_id) = &self.node_id {
+                for entity in &stored_entities {
+                    let key = entity.get_key();
+                    Self::record_revision(&mut tx, node_id, &key, entity.get_id(), kind, true).await?;
+                }
+            }
+            Self::insert_jobs(&mut tx, deferred, &stored_entities, &[]).await?;
+            tx.commit().await.map_err(|error| format!("Unable to commit delete : {}", error))?;
+        }
+        self.remove_from_vector_indexes(kind, ids);
+        if let Some(cache) = &self.cache {
+            cache.invalidate(kind, ids);
+        }
+        Ok(stored_entities)
+    }
+
+    fn remove_from_vector_indexes(&self, kind: &str, ids: &[&str]) {
+        if let Some(vector_indexes) = self.vector_indexes.lock().unwrap().as_mut() {
+            if let Some(index) = vector_indexes.get_mut(kind) {
+                for id in ids {
+                    index.remove(id);
+                }
+            }
+        }
+    }
+
+    async fn delete_entity_rows(tx: &mut Transaction<'_, Sqlite>, key: &str) -> Result<(), String> {
+        let delete_query = r#"
+            DELETE from entity WHERE key = ?1;
+            DELETE from properties WHERE key = ?1;
+            DELETE from geo_index WHERE id IN (SELECT id FROM geo_keys WHERE key = ?1);
+            DELETE from geo_keys WHERE key = ?1;
+            DELETE from vectors WHERE key = ?1;
+            "#;
+        sqlx::query(delete_query)
+            .bind(key)
+            .execute(&mut **tx)
+            .await
+            .map(|_| ())
+            .map_err(|error| format!("Unable to delete {} : {}", key, error))
+    }
+
+    async fn record_revision(
+        tx: &mut Transaction<'_, Sqlite>,
+        node_id: &str,
+        key: &str,
+        id: &str,
+        kind: &str,
+        deleted: bool,
+    ) -> Result<(), String> {
+        let current: Option<(i64, String)> =
+            sqlx::query_as("SELECT revision, origin FROM revisions WHERE key = ?")
+                .bind(key)
+                .fetch_optional(&mut **tx)
+                .await
+                .map_err(|error| format!("Unable to read revision of {} : {}", key, error))?;
+        let (parent_revision, parent_origin) = current.unwrap_or((0, String::new()));
+        let change = Change {
+            key: key.to_string(),
+            id: id.to_string(),
+            kind: kind.to_string(),
+            revision: parent_revision + 1,
+            origin: node_id.to_string(),
+            parent_revision,
+            parent_origin,
+            timestamp: Self::now_millis(),
+            deleted,
+            seq: 0,
+            data: None,
+        };
+        Self::write_revision(tx, &change).await
+    }
+
+    // Every write takes the next local sequence number, so changes relayed
+    // from other nodes are also pulled by the peers of this node
+    async fn write_revision(tx: &mut Transaction<'_, Sqlite>, change: &Change) -> Result<(), String> {
+        let insert_query = r#"INSERT or REPLACE INTO revisions
+            (key, id, kind, revision, origin, parent_revision, parent_origin, timestamp, deleted, seq)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, (SELECT COALESCE(MAX(seq), 0) + 1 FROM revisions))"#;
+        sqlx::query(insert_query)
+            .bind(&change.key)
+            .bind(&change.id)
+            .bind(&change.kind)
+            .bind(change.revision)
+            .bind(&change.origin)
+            .bind(change.parent_revision)
+            .bind(&change.parent_origin)
+            .bind(change.timestamp)
+            .bind(change.deleted)
+            .execute(&mut **tx)
+            .await
+            .map(|_| ())
+            .map_err(|error| format!("Unable to write revision of {} : {}", change.key, error))
+    }
+
+    pub fn now_millis() -> i64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_millis() as i64)
+            .unwrap_or(0)
+    }
+
+    /// Changes written after the sequence number `since`, oldest first.
+    pub async fn changes_since(&self, since: i64, limit: usize) -> Result<ChangeSet, String> {
+        let node_id = self.node_id.as_ref().ok_or("Replication is not enabled")?;
+        let pool = self.pool.as_ref().ok_or(format!("Store is not open : {}", &self.path))?;
+        let changes_query = r#"SELECT r.key, r.id, r.kind, r.revision, r.origin, r.parent_revision,
+                r.parent_origin, r.timestamp, r.deleted, r.seq, e.data
+            FROM revisions r LEFT JOIN entity e ON e.key = r.key
+            WHERE r.seq > ? ORDER BY r.seq LIMIT ?"#;
+        let changes: Vec<Change> = sqlx::query_as(changes_query)
+            .bind(since)
+            .bind(limit as i64)
+            .fetch_all(pool)
+            .await
+            .map_err(|error| format!("Unable to read changes : {}", error))?;
+        let last_seq = changes.last().map(|change| change.seq).unwrap_or(since);
+        Ok(ChangeSet {
+            node_id: node_id.clone(),
+            changes,
+            last_seq,
+        })
+    }
+
+    /// Applies changes of `kind` received from another node in a single transaction.
+    /// A change replacing the local revision is applied, concurrent changes
+    /// are handed to the conflict resolver. The jobs of the deferred hooks of
+    /// the updated and deleted entities are queued in the same transaction.
+    pub async fn apply_changes<E: Entity>(
+        &self,
+        changes: &[Change],
+        update_hooks: &[DeferredHook],
+        delete_hooks: &[DeferredHook],
+    ) -> Result<AppliedChanges<E>, String> {
+        let node_id = self.node_id.as_ref().ok_or("Replication is not enabled")?;
+        let pool = self.pool.as_ref().ok_or(format!("Store is not open : {}", &self.path))?;
+        let mut applied = AppliedChanges {
+            updated: vec![],
+            deleted: vec![],
+            report: ApplyReport::default(),
+        };
+        let local_query = r#"SELECT r.key, r.id, r.kind, r.revision, r.origin, r.parent_revision,
+                r.parent_origin, r.timestamp, r.deleted, r.seq, e.data
+            FROM revisions r LEFT JOIN entity e ON e.key = r.key
+            WHERE r.key = ?"#;
+        let mut tx = pool
+            .begin()
+            .await
+            .map_err(|error| format!("Unable to apply changes : {}", error))?;
+        for remote in changes {
+            let local: Option<Change> = sqlx::query_as(local_query)
+                .bind(&remote.key)
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(|error| format!("Unable to read revision : {}", error))?;
+            let resolution = match &local {
+                None => Resolution::TakeRemote,
+                Some(local) if local.is_same_revision(remote) => Resolution::KeepLocal,
+                Some(local) if remote.is_child_of(local) => Resolution::TakeRemote,
+                // Rows not changed locally follow the newest revision of the other nodes
+                Some(local) if &local.origin != node_id && remote.revision > local.revision => {
+                    Resolution::TakeRemote
+                }
+                Some(local) => {
+                    applied.report.conflicts += 1;
+                    self.conflict_resolver.resolve(local, remote)
+                }
+            };
+            match resolution {
+                Resolution::KeepLocal => {
+                    applied.report.skipped += 1;
+                    continue;
+                }
+                Resolution::TakeRemote => {
+                    if remote.deleted {
+                        let local_data = local.as_ref().and_then(|local| local.data.as_ref());
+                        if let Some(entity) = local_data.and_then(|data| Self::entity_from_vec::<E>(data).ok()) {
+                            applied.deleted.push(entity);
+                        }
+                        Self::delete_entity_rows(&mut tx, &remote.key).await?;
+                    } else {
+                        let entity = Self::decode_change::<E>(remote.data.as_ref())?;
+                        if Self::reject_invalid(&entity, &mut applied.report) {
+                            continue;
+                        }
+                        Self::write_entity(&mut tx, &entity).await?;
+                        applied.updated.push(entity);
+                    }
+                    Self::write_revision(&mut tx, remote).await?;
+                }
+                Resolution::Merged(data) => {
+                    let entity = Self::decode_change::<E>(Some(&data))?;
+                    if Self::reject_invalid(&entity, &mut applied.report) {
+                        continue;
+                    }
+                    Self::write_entity(&mut tx, &entity).await?;
+                    applied.updated.push(entity);
+                    let local_revision = local.map(|local| local.revision).unwrap_or(0);
+                    let merged = Change {
+                        revision: local_revision.max(remote.revision) + 1,
+                        origin: node_id.clone(),
+                        parent_revision: remote.revision,
+                        parent_origin: remote.origin.clone(),
+                        timestamp: Self::now_millis(),
+                        deleted: false,
+                        ..remote.clone()
+                    };
+                    Self::write_revision(&mut tx, &merged).await?;
+                }
+            }
+            applied.report.applied += 1;
+        }
+        Self::insert_jobs(&mut tx, update_hooks, &applied.updated, &[]).await?;
+        Self::insert_jobs(&mut tx, delete_hooks, &applied.deleted, &[]).await?;
+        tx.commit()
+            .await
+            .map_err(|error| format!("Unable to apply changes : {}", error))?;
+
+        if let Some(cache) = &self.cache {
+            for change in changes {
+                cache.invalidate(&change.kind, &[change.id.as_str()]);
+            }
+        }
+        self.update_vector_indexes(&applied.updated).await;
+        for entity in &applied.deleted {
+            self.remove_from_vector_indexes(entity.get_kind(), &[entity.get_id()]);
+        }
+        Ok(applied)
+    }
+
+    // Replicated entities follow the validation rules of the local ones
+    fn reject_invalid<E: Entity>(entity: &E, report: &mut ApplyReport) -> bool {
+        match entity.validate() {
+            Ok(()) => false,
+            Err(errors) => {
+                println!("Replicated entity {} rejected : {}", entity.get_key(), errors);
+                report.rejected.push(entity.get_key());
+                true
+            }
+        }
+    }
+
+    fn decode_change<E: Entity>(data: Option<&Vec<u8>>) -> Result<E, String> {
+        data.and_then(|data| Self::entity_from_vec::<E>(data).ok())
+            .ok_or("Unable to decode replicated entity".to_string())
+    }
+
+    /// Sequence numbers pulled from and pushed to `peer`.
+    pub async fn get_peer_cursor(&self, peer: &str) -> Result<(i64, i64), String> {
+        let pool = self.pool.as_ref().ok_or(format!("Store is not open : {}", &self.path))?;
+        let cursor: Option<(i64, i64)> =
+            sqlx::query_as("SELECT pulled_seq, pushed_seq FROM replication_peers WHERE peer = ?")
+                .bind(peer)
+                .fetch_optional(pool)
+                .await
+                .map_err(|error| format!("Unable to read peer cursor : {}", error))?;
+        Ok(cursor.unwrap_or((0, 0)))
+    }
+
+    pub async fn set_peer_cursor(&self, peer: &str, pulled_seq: i64, pushed_seq: i64) -> Result<(), String> {
+        let pool = self.pool.as_ref().ok_or(format!("Store is not open : {}", &self.path))?;
+        sqlx::query("INSERT or REPLACE INTO replication_peers (peer, pulled_seq, pushed_seq) VALUES (?, ?, ?)")
+            .bind(peer)
+            .bind(pulled_seq)
+            .bind(pushed_seq)
+            .execute(pool)
+            .await
+            .map(|_| ())
+            .map_err(|error| format!("Unable to write peer cursor : {}", error))
+    }
+
+    pub async fn enqueue_job(&self, hook: &str, action: &str, kind: &str, payload: &str) -> Result<i64, String> {
+        let pool = self.pool.as_ref().ok_or("Store is not open".to_string())?;
+        let mut connection = pool.acquire().await.map_err(|error| error.to_string())?;
+        Self::insert_job(&mut connection, hook, action, kind, payload).await
+    }
+
+    fn has_change_jobs(deferred: &[DeferredHook]) -> bool {
+        deferred.iter().any(|hook| hook.payload == JobPayload::Changes)
+    }
+
+    // `previous_entities` are only read by the change hooks
+    async fn insert_jobs<T: Entity>(
+        tx: &mut Transaction<'_, Sqlite>,
+        deferred: &[DeferredHook],
+        entities: &[T],
+        previous_entities: &[Option<T>],
+    ) -> Result<(), String> {
+        if entities.is_empty() {
+            return Ok(());
+        }
+        for hook in deferred {
+            let payload = match hook.payload {
+                JobPayload::Entities => serde_json::to_string(entities),
+                JobPayload::Changes => {
+                    let changes: Vec<(Option<&T>, &T)> =
+                        previous_entities.iter().map(Option::as_ref).zip(entities).collect();
+                    serde_json::to_string(&changes)
+                }
+            }
+            .map_err(|error| format!("Unable to queue {} : {}", hook.hook, error))?;
+            Self::insert_job(tx, &hook.hook, &hook.action, &hook.kind, &payload).await?;
+        }
+        Ok(())
+    }
+
+    async fn insert_job(
+        connection: &mut SqliteConnection,
+        hook: &str,
+        action: &str,
+        kind: &str,
+        payload: &str,
+    ) -> Result<i64, String> {
+        let now = Self::now_millis();
+        let insert_job_query = r#"
+            INSERT INTO jobs (hook, action, kind, payload, attempts, status, run_at, created_at)
+            VALUES (?, ?, ?, ?, 0, ?, ?, ?)
+            "#;
+        let result = sqlx::query(insert_job_query)
+            .bind(hook)
+            .bind(action)
+            .bind(kind)
+            .bind(payload)
+            .bind(JOB_PENDING)
+            .bind(now)
+            .bind(now)
+            .execute(connection)
+            .await
+            .map_err(|error| format!("Unable to queue {} : {}", hook, error))?;
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Marks the next due job as running, a job is claimed by a single worker.
+    pub async fn claim_job(&self) -> Result<Option<Job>, String> {
+        let pool = self.pool.as_ref().ok_or("Store is not open".to_string())?;
+        let claim_job_query = r#"
+            UPDATE jobs SET status = ?1
+            WHERE id = (SELECT id FROM jobs WHERE status = ?2 AND run_at <= ?3 ORDER BY run_at, id LIMIT 1)
+            RETURNING *
+            "#;
+        sqlx::query_as(claim_job_query)
+            .bind(JOB_RUNNING)
+            .bind(JOB_PENDING)
+            .bind(Self::now_millis())
+            .fetch_optional(pool)
+            .await
+            .map_err(|error| error.to_string())
+    }
+
+    pub async fn complete_job(&self, id: i64) -> Result<(), String> {
+        let pool = self.pool.as_ref().ok_or("Store is not open".to_string())?;
+        sqlx::query("DELETE FROM jobs WHERE id = ?")
+            .bind(id)
+            .execute(pool)
+            .await
+            .map_err(|error| error.to_string())?;
+        Ok(())
+    }
+
+    pub async fn retry_job(&self, id: i64, error: &str, delay: Duration) -> Result<(), String> {
+        let pool = self.pool.as_ref().ok_or("Store is not open".to_string())?;
+        let run_at = Self::now_millis() + delay.as_millis() as i64;
+        let retry_job_query = r#"
+            UPDATE jobs SET status = ?, attempts = attempts + 1, run_at = ?, last_error = ? WHERE id = ?
+            "#;
+        sqlx::query(retry_job_query)
+            .bind(JOB_PENDING)
+            .bind(run_at)
+            .bind(error)
+            .bind(id)
+            .execute(pool)
+            .await
+            .map_err(|error| error.to_string())?;
+        Ok(())
+    }
+
+    pub async fn dead_letter_job(&self, id: i64, error: &str) -> Result<(), String> {
+        let pool = self.pool.as_ref().ok_or("Store is not open".to_string())?;
+        let dead_letter_query = r#"
+            INSERT OR REPLACE INTO dead_jobs (id, hook, action, kind, payload, attempts, status, run_at, created_at, last_error)
+            SELECT id, hook, action, kind, payload, attempts + 1, ?1, run_at, created_at, ?2 FROM jobs WHERE id = ?3;
+            DELETE FROM jobs WHERE id = ?3;
+            "#;
+        let mut tx = pool.begin().await.map_err(|error| error.to_string())?;
+        sqlx::query(dead_letter_query)
+            .bind(JOB_DEAD)
+            .bind(error)
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|error| error.to_string())?;
+        tx.commit().await.map_err(|error| error.to_string())
+    }
+
+    /// Moves a dead job back to the queue, its attempts are reset.
+    pub async fn requeue_dead_job(&self, id: i64) -> Result<(), String> {
+        let pool = self.pool.as_ref().ok_or("Store is not open".to_string())?;
+        let requeue_query = r#"
+            INSERT INTO jobs (id, hook, action, kind, payload, attempts, status, run_at, created_at, last_error)
+            SELECT id, hook, action, kind, payload, 0, ?1, ?2, created_at, last_error FROM dead_jobs WHERE id = ?3;
+            DELETE FROM dead_jobs WHERE id = ?3;
+            "#;
+        let mut tx = pool.begin().await.map_err(|error| error.to_string())?;
+        let result = sqlx::query(requeue_query)
+            .bind(JOB_PENDING)
+            .bind(Self::now_millis())
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|error| error.to_string())?;
+        if result.rows_affected() == 0 {
+            return Err(format!("Unknown dead job {}", id));
+        }
+        tx.commit().await.map_err(|error| error.to_string())
+    }
+
+    /// Jobs left running by a stopped process are run again
+    pub async fn reset_running_jobs(&self) -> Result<(), String> {
+        let pool = self.pool.as_ref().ok_or("Store is not open".to_string())?;
+        sqlx::query("UPDATE jobs SET status = ? WHERE status = ?")
+            .bind(JOB_PENDING)
+            .bind(JOB_RUNNING)
+            .execute(pool)
+            .await
+            .map_err(|error| error.to_string())?;
+        Ok(())
+    }
+
+    pub async fn get_jobs(&self) -> Result<Vec<Job>, String> {
+        self.fetch_jobs("SELECT * FROM jobs ORDER BY run_at, id").await
+    }
+
+    pub async fn get_dead_jobs(&self) -> Result<Vec<Job>, String> {
+        self.fetch_jobs("SELECT * FROM dead_jobs ORDER BY id").await
+    }
+
+    async fn fetch_jobs(&self, sql_query: &str) -> Result<Vec<Job>, String> {
+        let pool = self.pool.as_ref().ok_or("Store is not open".to_string())?;
+        sqlx::query_as(sql_query)
+            .fetch_all(pool)
+            .await
+            .map_err(|error| error.to_string())
+    }
+
+    /// Next run of a schedule, None until the scheduler first saw it
+    pub async fn get_schedule_next_run(&self, name: &str) -> Result<Option<i64>, String> {
+        let pool = self.pool.as_ref().ok_or("Store is not open".to_string())?;
+        let next_run: Option<(i64,)> = sqlx::query_as("SELECT next_run FROM schedules WHERE name = ?")
+            .bind(name)
+            .fetch_optional(pool)
+            .await
+            .map_err(|error| error.to_string())?;
+        Ok(next_run.map(|(next_run,)| next_run))
+    }
+
+    pub async fn set_schedule_next_run(&self, name: &str, next_run: i64, last_run: Option<i64>) -> Result<(), String> {
+        let pool = self.pool.as_ref().ok_or("Store is not open".to_string())?;
+        let upsert_query = r#"
+            INSERT INTO schedules (name, next_run, last_run) VALUES (?1, ?2, ?3)
+            ON CONFLICT(name) DO UPDATE SET next_run = ?2, last_run = COALESCE(?3, last_run)
+            "#;
+        sqlx::query(upsert_query)
+            .bind(name)
+            .bind(next_run)
+            .bind(last_run)
+            .execute(pool)
+            .await
+            .map_err(|error| error.to_string())?;
+        Ok(())
+    }
+
+    /// Forgets a schedule which has no further run
+    pub async fn remove_schedule(&self, name: &str) -> Result<(), String> {
+        let pool = self.pool.as_ref().ok_or("Store is not open".to_string())?;
+        sqlx::query("DELETE FROM schedules WHERE name = ?")
+            .bind(name)
+            .execute(pool)
+            .await
+            .map_err(|error| error.to_string())?;
+        Ok(())
     }
 
     async fn execute_batch(&self, sql_command: &str) {
@@ -145,19 +1061,49 @@ impl SQLiteEntityStore {
     }
 
     pub async fn get_entities_of_kind<E: Entity>(&self, kind: &str, ids: &Vec<&str>) -> Vec<E> {
+        // Whole kind scans bypass the cache, only lookups by id are served from it
+        if let (Some(cache), false) = (&self.cache, ids.is_empty()) {
+            let generation = cache.get_generation(kind);
+            let mut found: HashMap<String, E> = HashMap::new();
+            let mut missing_ids = vec![];
+            for id in ids {
+                if found.contains_key(*id) || missing_ids.contains(id) {
+                    continue;
+                }
+                match cache.get::<E>(kind, id) {
+                    Some(entity) => {
+                        found.insert(id.to_string(), entity);
+                    }
+                    None => missing_ids.push(*id),
+                }
+            }
+            if !missing_ids.is_empty() {
+                let loaded_entities: Vec<E> = self.load_entities_of_kind(kind, &missing_ids).await;
+                // Skipped when a write invalidated the kind during the load
+                cache.insert_loaded(kind, generation, &loaded_entities);
+                found.extend(loaded_entities.into_iter().map(|entity| (entity.get_id().to_string(), entity)));
+            }
+            // In the order of `ids`, once per id
+            ids.iter().filter_map(|id| found.remove(*id)).collect()
+        } else {
+            self.load_entities_of_kind(kind, ids).await
+        }
+    }
+
+    async fn load_entities_of_kind<E: Entity>(&self, kind: &str, ids: &Vec<&str>) -> Vec<E> {
         let sql_query = if ids.is_empty() {
-            let query = format!("SELECT data FROM entity WHERE kind = \'{}\'", kind);
-            query
+            "SELECT data FROM entity WHERE kind = ?".to_string()
         } else {
-            let ids_str: Vec<String> = ids.iter().map(|k| format!("\'{}\'", k)).collect();
-            let keys_str = format!("({})", ids_str.join(", "));
-            let query = format!("SELECT data FROM entity WHERE kind = \'{}\' AND id IN {}", kind, keys_str);
-            query
+            let placeholders = vec!["?"; ids.len()].join(", ");
+            format!("SELECT data FROM entity WHERE kind = ? AND id IN ({})", placeholders)
         };
 
         if let Some(pool) = &self.pool {
-            let results: Vec<EntityData> =
-                sqlx::query_as(&sql_query).fetch_all(pool).await.unwrap();
+            let mut query = sqlx::query_as(&sql_query).bind(kind);
+            for id in ids {
+                query = query.bind(*id);
+            }
+            let results: Vec<EntityData> = query.fetch_all(pool).await.unwrap();
             Self::decode_entities(&results)
         } else {
             vec![]
@@ -180,26 +1126,151 @@ impl SQLiteEntityStore {
         }
     }
 
+    pub async fn query_predicate<E: Entity>(&self, kind: &str, predicate: &Predicate) -> Vec<E> {
+        let mut binds = vec![];
+        let condition = predicate.to_sql(kind, &mut binds);
+        let sql_query = format!("SELECT data FROM entity WHERE kind = ? AND ({})", condition);
+        if let Some(pool) = &self.pool {
+            let mut query = sqlx::query_as(&sql_query).bind(kind);
+            for value in binds {
+                query = match value {
+                    QueryValue::Text(text) => query.bind(text),
+                    QueryValue::Number(number) => query.bind(number),
+                };
+            }
+            let results: Vec<EntityData> = query.fetch_all(pool).await.unwrap();
+            Self::decode_entities(&results)
+        } else {
+            vec![]
+        }
+    }
+
+    pub async fn query_geo<E: Entity>(&self, kind: &str, query: &GeoQuery) -> Vec<E> {
+        match query {
+            GeoQuery::BoundingBox(bounds) => {
+                let entities: Vec<E> = self.query_bounds(kind, bounds).await;
+                entities
+                    .into_iter()
+                    .filter(|e| e.get_geo_point().map(|p| bounds.contains(&p)).unwrap_or(false))
+                    .collect()
+            }
+            GeoQuery::Radius(center, radius) => {
+                let bounds = GeoBounds::around(center, *radius);
+                let entities: Vec<E> = self.query_bounds(kind, &bounds).await;
+                let mut entities_distance: Vec<(f64, E)> = entities
+                    .into_iter()
+                    .filter_map(|e| {
+                        let distance = center.distance_to(&e.get_geo_point()?);
+                        if distance <= *radius {
+                            Some((distance, e))
+                        } else {
+                            None
+                        }
+                    })
+                    .collect();
+                entities_distance.sort_by(|a, b| a.0.total_cmp(&b.0));
+                entities_distance.into_iter().map(|(_, e)| e).collect()
+            }
+        }
+    }
+
+    // R*Tree coordinates are stored as 32 bits floats, the candidates are
+    // selected by overlap and then filtered on the exact entity position.
+    // A box crossing the antimeridian is queried as two ranges.
+    async fn query_bounds<E: Entity>(&self, kind: &str, bounds: &GeoBounds) -> Vec<E> {
+        let sql_query = r#"SELECT data FROM entity WHERE key IN (
+                SELECT key FROM geo_keys WHERE kind = ? AND id IN (
+                    SELECT id FROM geo_index WHERE max_lat >= ? AND min_lat <= ? AND max_lon >= ? AND min_lon <= ?
+                )
+            )"#;
+        if let Some(pool) = &self.pool {
+            let mut results: Vec<EntityData> = vec![];
+            for (min_lon, max_lon) in bounds.lon_ranges() {
+                let range_results: Vec<EntityData> = sqlx::query_as(sql_query)
+                    .bind(kind)
+                    .bind(bounds.min_lat)
+                    .bind(bounds.max_lat)
+                    .bind(min_lon)
+                    .bind(max_lon)
+                    .fetch_all(pool)
+                    .await
+                    .unwrap();
+                results.extend(range_results);
+            }
+            Self::decode_entities(&results)
+        } else {
+            vec![]
+        }
+    }
+
     async fn create_tables(&self) {
+        self.migrate_properties_table().await;
         let create_tables_query = r#"
             CREATE TABLE IF NOT EXISTS entity (key TEXT not null PRIMARY KEY, id TEXT not null, kind TEXT not null, data BLOB not null);
             CREATE INDEX IF NOT EXISTS nodes_id ON entity (id);
             CREATE TABLE IF NOT EXISTS links (id TEXT not null PRIMARY KEY, predicate TEXT not null, source TEXT not null, target TEXT not null, ordering INTEGER, weight REAL);
-            CREATE TABLE IF NOT EXISTS properties (key TEXT not null PRIMARY KEY, id TEXT not null, kind TEXT not null, name TEXT not null, value TEXT );
+            CREATE TABLE IF NOT EXISTS properties (key TEXT not null, id TEXT not null, kind TEXT not null, name TEXT not null, value TEXT );
+            CREATE INDEX IF NOT EXISTS properties_keys ON properties (key);
             CREATE INDEX IF NOT EXISTS properties_values ON properties (value);
+            CREATE TABLE IF NOT EXISTS geo_keys (id INTEGER PRIMARY KEY, key TEXT not null UNIQUE, kind TEXT not null);
+            CREATE VIRTUAL TABLE IF NOT EXISTS geo_index USING rtree(id, min_lat, max_lat, min_lon, max_lon);
+            CREATE TABLE IF NOT EXISTS vectors (key TEXT not null PRIMARY KEY, id TEXT not null, kind TEXT not null, data BLOB not null);
+            CREATE INDEX IF NOT EXISTS vectors_kind ON vectors (kind);
+            CREATE TABLE IF NOT EXISTS revisions (key TEXT not null PRIMARY KEY, id TEXT not null, kind TEXT not null, revision INTEGER not null, origin TEXT not null, parent_revision INTEGER not null, parent_origin TEXT not null, timestamp INTEGER not null, deleted INTEGER not null, seq INTEGER not null);
+            CREATE INDEX IF NOT EXISTS revisions_seq ON revisions (seq);
+            CREATE TABLE IF NOT EXISTS replication_peers (peer TEXT not null PRIMARY KEY, pulled_seq INTEGER not null, pushed_seq INTEGER not null);
+            CREATE TABLE IF NOT EXISTS jobs (id INTEGER PRIMARY KEY AUTOINCREMENT, hook TEXT not null, action TEXT not null, kind TEXT not null, payload TEXT not null, attempts INTEGER not null, status TEXT not null, run_at INTEGER not null, created_at INTEGER not null, last_error TEXT);
+            CREATE INDEX IF NOT EXISTS jobs_run_at ON jobs (status, run_at);
+            CREATE TABLE IF NOT EXISTS schedules (name TEXT not null PRIMARY KEY, next_run INTEGER not null, last_run INTEGER);
+            CREATE TABLE IF NOT EXISTS dead_jobs (id INTEGER PRIMARY KEY, hook TEXT not null, action TEXT not null, kind TEXT not null, payload TEXT not null, attempts INTEGER not null, status TEXT not null, run_at INTEGER not null, created_at INTEGER not null, last_error TEXT);
             "#;
         self.execute_batch(create_tables_query).await;
     }
 
+    /// Stores created before multi-valued indexes used the entity key as primary key
+    /// of the properties table, which kept a single index row per entity.
+    async fn migrate_properties_table(&self) {
+        if let Some(pool) = &self.pool {
+            let legacy_schema: Option<(i64,)> = sqlx::query_as(
+                "SELECT pk FROM pragma_table_info('properties') WHERE name = 'key' AND pk = 1",
+            )
+            .fetch_optional(pool)
+            .await
+            .unwrap_or(None);
+            if legacy_schema.is_some() {
+                let migrate_query = r#"
+                    ALTER TABLE properties RENAME TO properties_legacy;
+                    CREATE TABLE properties (key TEXT not null, id TEXT not null, kind TEXT not null, name TEXT not null, value TEXT );
+                    INSERT INTO properties (key, id, kind, name, value) SELECT key, id, kind, name, value FROM properties_legacy;
+                    DROP TABLE properties_legacy;
+                    "#;
+                self.execute_batch(migrate_query).await;
+            }
+        }
+    }
+
     pub async fn clear(&self) {
         let drop_tables_query = r#"
             DROP TABLE entity;
             DROP TABLE links;
             DROP TABLE properties;
+            DROP TABLE IF EXISTS geo_keys;
+            DROP TABLE IF EXISTS geo_index;
+            DROP TABLE IF EXISTS vectors;
+            DROP TABLE IF EXISTS revisions;
+            DROP TABLE IF EXISTS replication_peers;
+            DROP TABLE IF EXISTS jobs;
+            DROP TABLE IF EXISTS dead_jobs;
+            DROP TABLE IF EXISTS schedules;
             DROP INDEX IF EXISTS nodes_id;
+            DROP INDEX IF EXISTS properties_keys;
             DROP INDEX IF EXISTS properties_values;
             "#;
         self.execute_batch(drop_tables_query).await;
+        if let Some(cache) = &self.cache {
+            cache.clear();
+        }
+        *self.vector_indexes.lock().unwrap() = None;
         self.create_tables().await;
     }
 
@@ -209,6 +1280,167 @@ impl SQLiteEntityStore {
         }
     }
 
+    pub fn get_path(&self) -> &str {
+        &self.path
+    }
+
+    pub async fn stats(&self) -> Result<StoreStats, String> {
+        let pool = self.pool.as_ref().ok_or(format!("Store is not open : {}", &self.path))?;
+        let map_error = |error: sqlx::Error| format!("Unable to read stats of {} : {}", &self.path, error);
+
+        let kinds_query = r#"SELECT kind, COUNT(*), COALESCE(SUM(LENGTH(data)), 0), COALESCE(AVG(LENGTH(data)), 0)
+            FROM entity GROUP BY kind ORDER BY kind"#;
+        let kinds: Vec<(String, i64, i64, f64)> =
+            sqlx::query_as(kinds_query).fetch_all(pool).await.map_err(map_error)?;
+
+        let properties: Vec<(String, String)> =
+            sqlx::query_as("SELECT DISTINCT kind, name FROM properties ORDER BY kind, name")
+                .fetch_all(pool)
+                .await
+                .map_err(map_error)?;
+
+        let (links,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM links")
+            .fetch_one(pool)
+            .await
+            .map_err(map_error)?;
+
+        let kinds = kinds
+            .into_iter()
+            .map(|(kind, count, total_size, average_size)| KindStats {
+                indexed_properties: properties
+                    .iter()
+                    .filter(|(property_kind, _)| property_kind == &kind)
+                    .map(|(_, name)| name.clone())
+                    .collect(),
+                kind,
+                count,
+                total_size,
+                average_size,
+            })
+            .collect();
+
+        Ok(StoreStats {
+            path: self.path.clone(),
+            file_size: std::fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0),
+            links,
+            kinds,
+        })
+    }
+
+    /// Kind, id and encoded data of every entity, ordered by kind and id
+    pub async fn get_entity_rows(&self) -> Result<Vec<(String, String, Vec<u8>)>, String> {
+        let pool = self.pool.as_ref().ok_or(format!("Store is not open : {}", &self.path))?;
+        sqlx::query_as("SELECT kind, id, data FROM entity ORDER BY kind, id")
+            .fetch_all(pool)
+            .await
+            .map_err(|error| format!("Unable to read entities of {} : {}", &self.path, error))
+    }
+
+    /// Writes a consistent snapshot of the store to `path` using `VACUUM INTO`.
+    /// The store stays available for reads and writes during the backup.
+    pub async fn backup_to(&self, path: &str) -> Result<(), String> {
+        if file_io::is_file(path) {
+            return Err(format!("Backup file already exists : {}", path));
+        }
+        if let Some(pool) = &self.pool {
+            file_io::create_parent_dirs(path);
+            let backup_query = format!("VACUUM INTO \'{}\'", path.replace('\'', "''"));
+            match sqlx::query(&backup_query).execute(pool).await {
+                Ok(_) => Ok(()),
+                Err(error) => Err(format!("Unable to backup {} : {}", &self.path, error)),
+            }
+        } else {
+            Err(format!("Store is not open : {}", &self.path))
+        }
+    }
+
+    /// Replaces the database file with the backup found at `path`.
+    /// The store must be closed, and has to be opened again afterwards.
+    pub fn restore_from(&mut self, path: &str) -> Result<(), String> {
+        if let Some(pool) = &self.pool {
+            if !pool.is_closed() {
+                return Err(format!("Store must be closed before restore : {}", &self.path));
+            }
+        }
+        if !file_io::is_file(path) {
+            return Err(format!("Backup file not found : {}", path));
+        }
+        // Copy next to the database then swap files, so connections still
+        // releasing the previous file can't write into the restored one
+        let restore_path = format!("{}.restore", &self.path);
+        file_io::create_parent_dirs(&self.path);
+        let res = std::fs::copy(path, &restore_path)
+            .and_then(|_| std::fs::rename(&restore_path, &self.path));
+        if let Err(error) = res {
+            file_io::remove_file(&restore_path);
+            return Err(format!("Unable to restore {} : {}", &self.path, error));
+        }
+        // Stale journal files would be replayed on top of the restored database
+        file_io::remove_file(&format!("{}-wal", &self.path));
+        file_io::remove_file(&format!("{}-shm", &self.path));
+        self.pool = None;
+        Ok(())
+    }
+
+    /// Tenants are stored in their own database, `<path without .db>/<tenant>.db`
+    pub fn get_tenant_path(&self, tenant: &str) -> Result<String, String> {
+        Self::check_tenant_id(tenant)?;
+        Ok(format!("{}/{}.db", self.get_tenants_dir(), tenant))
+    }
+
+    /// Returns a store on the database of `tenant`, configured like this store.
+    /// The store is not open yet, so the pragmas apply to its first connections.
+    pub fn for_tenant(&self, tenant: &str) -> Result<SQLiteEntityStore, String> {
+        let mut store = SQLiteEntityStore::new_closed(&self.get_tenant_path(tenant)?);
+        store.pragmas = self.pragmas.clone();
+        store.cache = self.cache.as_ref().map(|cache| Arc::new(EntityCache::new(cache.get_capacity())));
+        store.vector_settings = self.vector_settings;
+        store.node_id = self.node_id.clone();
+        store.conflict_resolver = self.conflict_resolver.clone();
+        Ok(store)
+    }
+
+    pub fn tenants(&self) -> Vec<String> {
+        let mut tenants: Vec<String> = file_io::list_files(self.get_tenants_dir(), Some(1))
+            .iter()
+            .filter(|path| path.extension().map(|ext| ext == "db").unwrap_or(false))
+            .filter_map(|path| path.file_stem().map(|stem| stem.to_string_lossy().to_string()))
+            .collect();
+        tenants.sort();
+        tenants
+    }
+
+    /// Removes the database of `tenant`, its stores must be closed beforehand.
+    pub fn delete_tenant(&self, tenant: &str) -> Result<(), String> {
+        let tenant_path = self.get_tenant_path(tenant)?;
+        if !file_io::is_file(&tenant_path) {
+            return Err(format!("Unknown tenant : {}", tenant));
+        }
+        if let Err(error) = std::fs::remove_file(&tenant_path) {
+            return Err(format!("Unable to delete tenant {} : {}", tenant, error));
+        }
+        file_io::remove_file(&format!("{}-wal", &tenant_path));
+        file_io::remove_file(&format!("{}-shm", &tenant_path));
+        Ok(())
+    }
+
+    fn get_tenants_dir(&self) -> &str {
+        self.path.strip_suffix(".db").unwrap_or(&self.path)
+    }
+
+    /// Tenant ids end up in file names, only `[A-Za-z0-9_-]` is accepted.
+    pub fn check_tenant_id(tenant: &str) -> Result<(), String> {
+        let valid = !tenant.is_empty()
+            && tenant
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if valid {
+            Ok(())
+        } else {
+            Err(format!("Invalid tenant id : {}", tenant))
+        }
+    }
+
     fn entity_to_vec<E: Entity>(entity: &E) -> Result<Vec<u8>, ()> {
         if let Ok(bytes) = bincode::serialize(entity) {
             Ok(bytes)
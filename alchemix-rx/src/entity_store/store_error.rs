@@ -0,0 +1,33 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::entity_store::ValidationErrors;
+
+/// Error of a write, the entities were rejected or the database failed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum StoreError {
+    Validation(ValidationErrors),
+    Database(String),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Validation(errors) => write!(f, "{}", errors),
+            StoreError::Database(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<ValidationErrors> for StoreError {
+    fn from(errors: ValidationErrors) -> Self {
+        StoreError::Validation(errors)
+    }
+}
+
+impl From<String> for StoreError {
+    fn from(message: String) -> Self {
+        StoreError::Database(message)
+    }
+}
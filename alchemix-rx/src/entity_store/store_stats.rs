@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KindStats {
+    pub kind: String,
+    pub count: i64,
+    /// Size of the encoded entities in bytes
+    pub total_size: i64,
+    pub average_size: f64,
+    pub indexed_properties: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoreStats {
+    pub path: String,
+    /// Size of the database file in bytes, without its write-ahead log
+    pub file_size: u64,
+    pub links: i64,
+    pub kinds: Vec<KindStats>,
+}
@@ -0,0 +1,224 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Mutex, OnceLock},
+};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldError {
+    pub entity_id: String,
+    pub field: String,
+    pub code: String,
+    pub message: String,
+}
+
+/// Errors collected by `Entity::validate`, one entry per failed constraint.
+/// The `check_*` methods are called by the code generated for `#[validate(...)]`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ValidationErrors {
+    pub errors: Vec<FieldError>,
+}
+
+impl ValidationErrors {
+    pub fn new() -> Self {
+        Self { errors: vec![] }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn add(&mut self, entity_id: &str, field: &str, code: &str, message: &str) {
+        self.errors.push(FieldError {
+            entity_id: entity_id.to_string(),
+            field: field.to_string(),
+            code: code.to_string(),
+            message: message.to_string(),
+        });
+    }
+
+    pub fn extend(&mut self, other: ValidationErrors) {
+        self.errors.extend(other.errors);
+    }
+
+    pub fn into_result(self) -> Result<(), ValidationErrors> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+
+    pub fn check_length<V: ValidateLength + ?Sized>(
+        &mut self,
+        entity_id: &str,
+        field: &str,
+        value: &V,
+        min: Option<usize>,
+        max: Option<usize>,
+    ) {
+        let Some(length) = value.validated_length() else {
+            return;
+        };
+        if let Some(min) = min.filter(|min| length < *min) {
+            self.add(entity_id, field, "length", &format!("length must be at least {}", min));
+        }
+        if let Some(max) = max.filter(|max| length > *max) {
+            self.add(entity_id, field, "length", &format!("length must be at most {}", max));
+        }
+    }
+
+    pub fn check_range<V: ValidateRange + ?Sized>(
+        &mut self,
+        entity_id: &str,
+        field: &str,
+        value: &V,
+        min: Option<f64>,
+        max: Option<f64>,
+    ) {
+        let Some(number) = value.validated_number() else {
+            return;
+        };
+        if let Some(min) = min.filter(|min| number < *min) {
+            self.add(entity_id, field, "range", &format!("must be greater than or equal to {}", min));
+        }
+        if let Some(max) = max.filter(|max| number > *max) {
+            self.add(entity_id, field, "range", &format!("must be less than or equal to {}", max));
+        }
+    }
+
+    pub fn check_email<V: ValidateText + ?Sized>(&mut self, entity_id: &str, field: &str, value: &V) {
+        if let Some(text) = value.validated_text() {
+            if !is_email(text) {
+                self.add(entity_id, field, "email", "must be a valid email address");
+            }
+        }
+    }
+
+    pub fn check_regex<V: ValidateText + ?Sized>(
+        &mut self,
+        entity_id: &str,
+        field: &str,
+        value: &V,
+        pattern: &str,
+    ) {
+        if let Some(text) = value.validated_text() {
+            match matches_pattern(text, pattern) {
+                Ok(true) => {}
+                Ok(false) => self.add(entity_id, field, "regex", &format!("must match {}", pattern)),
+                Err(error) => self.add(entity_id, field, "regex", &error),
+            }
+        }
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let errors: Vec<String> = self
+            .errors
+            .iter()
+            .map(|error| format!("{}.{} {}", error.entity_id, error.field, error.message))
+            .collect();
+        write!(f, "Validation failed : {}", errors.join(", "))
+    }
+}
+
+/// Length of a field, `None` skips the constraint (unset optional field)
+pub trait ValidateLength {
+    fn validated_length(&self) -> Option<usize>;
+}
+
+impl ValidateLength for String {
+    fn validated_length(&self) -> Option<usize> {
+        Some(self.chars().count())
+    }
+}
+
+impl ValidateLength for str {
+    fn validated_length(&self) -> Option<usize> {
+        Some(self.chars().count())
+    }
+}
+
+impl<T> ValidateLength for Vec<T> {
+    fn validated_length(&self) -> Option<usize> {
+        Some(self.len())
+    }
+}
+
+impl<T: ValidateLength> ValidateLength for Option<T> {
+    fn validated_length(&self) -> Option<usize> {
+        self.as_ref().and_then(|value| value.validated_length())
+    }
+}
+
+pub trait ValidateRange {
+    fn validated_number(&self) -> Option<f64>;
+}
+
+macro_rules! impl_validate_range {
+    ($($number:ty),*) => {
+        $(
+            impl ValidateRange for $number {
+                fn validated_number(&self) -> Option<f64> {
+                    Some(*self as f64)
+                }
+            }
+        )*
+    };
+}
+
+impl_validate_range!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, f32, f64);
+
+impl<T: ValidateRange> ValidateRange for Option<T> {
+    fn validated_number(&self) -> Option<f64> {
+        self.as_ref().and_then(|value| value.validated_number())
+    }
+}
+
+pub trait ValidateText {
+    fn validated_text(&self) -> Option<&str>;
+}
+
+impl ValidateText for String {
+    fn validated_text(&self) -> Option<&str> {
+        Some(self)
+    }
+}
+
+impl ValidateText for str {
+    fn validated_text(&self) -> Option<&str> {
+        Some(self)
+    }
+}
+
+impl<T: ValidateText> ValidateText for Option<T> {
+    fn validated_text(&self) -> Option<&str> {
+        self.as_ref().and_then(|value| value.validated_text())
+    }
+}
+
+fn is_email(text: &str) -> bool {
+    let Some((local, domain)) = text.split_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && !domain.contains('@')
+        && !text.chars().any(char::is_whitespace)
+        && domain.split('.').count() > 1
+        && domain.split('.').all(|part| !part.is_empty())
+}
+
+// Patterns come from the entity declarations, each one is compiled once
+fn matches_pattern(text: &str, pattern: &str) -> Result<bool, String> {
+    static REGEXES: OnceLock<Mutex<HashMap<String, Regex>>> = OnceLock::new();
+    let mut regexes = REGEXES.get_or_init(|| Mutex::new(HashMap::new())).lock().unwrap();
+    if !regexes.contains_key(pattern) {
+        let regex = Regex::new(pattern).map_err(|error| format!("invalid pattern {} : {}", pattern, error))?;
+        regexes.insert(pattern.to_string(), regex);
+    }
+    Ok(regexes[pattern].is_match(text))
+}
@@ -0,0 +1,313 @@
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BinaryHeap, HashMap, HashSet},
+};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum VectorMetric {
+    Cosine,
+    Dot,
+}
+
+impl VectorMetric {
+    /// Similarity between two vectors, higher is closer.
+    pub fn score(&self, a: &[f32], b: &[f32]) -> f32 {
+        let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+        match self {
+            VectorMetric::Dot => dot,
+            VectorMetric::Cosine => {
+                let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+                let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+                if norm_a == 0.0 || norm_b == 0.0 {
+                    0.0
+                } else {
+                    dot / (norm_a * norm_b)
+                }
+            }
+        }
+    }
+}
+
+pub fn vector_to_bytes(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|value| value.to_le_bytes()).collect()
+}
+
+pub fn vector_from_bytes(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct HnswSettings {
+    pub metric: VectorMetric,
+    pub max_connections: usize,
+    pub ef_construction: usize,
+    pub ef_search: usize,
+    /// Share of tombstones past which the graph is rebuilt from the live vectors
+    pub max_deleted_ratio: f32,
+}
+
+impl HnswSettings {
+    pub fn new(metric: VectorMetric) -> Self {
+        Self {
+            metric,
+            max_connections: 16,
+            ef_construction: 100,
+            ef_search: 50,
+            max_deleted_ratio: 0.5,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct Candidate {
+    distance: f32,
+    node: usize,
+}
+
+impl Eq for Candidate {}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance
+            .total_cmp(&other.distance)
+            .then(self.node.cmp(&other.node))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+struct HnswNode {
+    id: String,
+    vector: Vec<f32>,
+    neighbours: Vec<Vec<usize>>,
+    deleted: bool,
+}
+
+/// In-memory approximate nearest neighbour index (Hierarchical Navigable Small World).
+/// Replaced or removed vectors are kept as tombstones to preserve graph connectivity,
+/// the graph is rebuilt once they pass `max_deleted_ratio` of the nodes.
+pub struct HnswIndex {
+    settings: HnswSettings,
+    nodes: Vec<HnswNode>,
+    ids: HashMap<String, usize>,
+    deleted: usize,
+    entry_point: Option<usize>,
+    max_level: usize,
+    seed: u64,
+}
+
+impl HnswIndex {
+    pub fn new(settings: HnswSettings) -> Self {
+        Self {
+            settings,
+            nodes: vec![],
+            ids: HashMap::new(),
+            deleted: 0,
+            entry_point: None,
+            max_level: 0,
+            seed: 0x2545_F491_4F6C_DD1D,
+        }
+    }
+
+    pub fn get_metric(&self) -> VectorMetric {
+        self.settings.metric
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Number of tombstones still in the graph
+    pub fn deleted_len(&self) -> usize {
+        self.deleted
+    }
+
+    pub fn insert(&mut self, id: &str, vector: Vec<f32>) {
+        self.remove(id);
+
+        let level = self.random_level();
+        let node = self.nodes.len();
+        self.nodes.push(HnswNode {
+            id: id.to_string(),
+            vector,
+            neighbours: vec![vec![]; level + 1],
+            deleted: false,
+        });
+        self.ids.insert(id.to_string(), node);
+
+        let Some(mut entry_point) = self.entry_point else {
+            self.entry_point = Some(node);
+            self.max_level = level;
+            return;
+        };
+
+        let query = self.nodes[node].vector.clone();
+        for layer in (level + 1..=self.max_level).rev() {
+            entry_point = self.search_layer(&query, entry_point, 1, layer)[0].node;
+        }
+        for layer in (0..=level.min(self.max_level)).rev() {
+            let candidates =
+                self.search_layer(&query, entry_point, self.settings.ef_construction, layer);
+            let max_connections = self.max_connections(layer);
+            let neighbours: Vec<usize> = candidates
+                .iter()
+                .take(max_connections)
+                .map(|candidate| candidate.node)
+                .collect();
+            for neighbour in &neighbours {
+                self.nodes[*neighbour].neighbours[layer].push(node);
+                if self.nodes[*neighbour].neighbours[layer].len() > max_connections {
+                    self.prune(*neighbour, layer, max_connections);
+                }
+            }
+            self.nodes[node].neighbours[layer] = neighbours;
+            entry_point = candidates[0].node;
+        }
+        if level > self.max_level {
+            self.max_level = level;
+            self.entry_point = Some(node);
+        }
+    }
+
+    pub fn remove(&mut self, id: &str) {
+        if let Some(node) = self.ids.remove(id) {
+            self.nodes[node].deleted = true;
+            self.deleted += 1;
+            if self.deleted as f32 > self.settings.max_deleted_ratio * self.nodes.len() as f32 {
+                self.rebuild();
+            }
+        }
+    }
+
+    // Inserts the live vectors in a new graph, in their insertion order
+    fn rebuild(&mut self) {
+        let nodes = std::mem::take(&mut self.nodes);
+        self.ids.clear();
+        self.deleted = 0;
+        self.entry_point = None;
+        self.max_level = 0;
+        for node in nodes.into_iter().filter(|node| !node.deleted) {
+            self.insert(&node.id, node.vector);
+        }
+    }
+
+    /// Returns up to `k` ids with their similarity score, best first. The search
+    /// is widened until `k` live vectors are found or the whole graph is visited.
+    pub fn search(&self, query: &[f32], k: usize) -> Vec<(String, f32)> {
+        let Some(mut entry_point) = self.entry_point else {
+            return vec![];
+        };
+        for layer in (1..=self.max_level).rev() {
+            entry_point = self.search_layer(query, entry_point, 1, layer)[0].node;
+        }
+        let k = k.min(self.ids.len());
+        let mut ef = self.settings.ef_search.max(k);
+        loop {
+            let candidates: Vec<Candidate> = self
+                .search_layer(query, entry_point, ef, 0)
+                .into_iter()
+                .filter(|candidate| !self.nodes[candidate.node].deleted)
+                .take(k)
+                .collect();
+            if candidates.len() >= k || ef >= self.nodes.len() {
+                return candidates
+                    .into_iter()
+                    .map(|candidate| (self.nodes[candidate.node].id.clone(), -candidate.distance))
+                    .collect();
+            }
+            ef = (ef * 2).min(self.nodes.len());
+        }
+    }
+
+    fn distance(&self, query: &[f32], node: usize) -> f32 {
+        -self.settings.metric.score(query, &self.nodes[node].vector)
+    }
+
+    fn max_connections(&self, layer: usize) -> usize {
+        if layer == 0 {
+            self.settings.max_connections * 2
+        } else {
+            self.settings.max_connections
+        }
+    }
+
+    fn random_level(&mut self) -> usize {
+        // xorshift64*, a deterministic generator is enough to draw levels
+        self.seed ^= self.seed >> 12;
+        self.seed ^= self.seed << 25;
+        self.seed ^= self.seed >> 27;
+        let random = self.seed.wrapping_mul(0x2545_F491_4F6C_DD1D);
+        let uniform = ((random >> 11) as f64 + 1.0) / (1u64 << 53) as f64;
+        let level_factor = 1.0 / (self.settings.max_connections.max(2) as f64).ln();
+        (-uniform.ln() * level_factor) as usize
+    }
+
+    fn prune(&mut self, node: usize, layer: usize, max_connections: usize) {
+        let vector = self.nodes[node].vector.clone();
+        let mut neighbours: Vec<Candidate> = self.nodes[node].neighbours[layer]
+            .iter()
+            .map(|neighbour| Candidate {
+                distance: self.distance(&vector, *neighbour),
+                node: *neighbour,
+            })
+            .collect();
+        neighbours.sort();
+        self.nodes[node].neighbours[layer] = neighbours
+            .into_iter()
+            .take(max_connections)
+            .map(|candidate| candidate.node)
+            .collect();
+    }
+
+    /// Best-first search of a single layer, results sorted from closest to farthest.
+    fn search_layer(&self, query: &[f32], entry_point: usize, ef: usize, layer: usize) -> Vec<Candidate> {
+        let entry = Candidate {
+            distance: self.distance(query, entry_point),
+            node: entry_point,
+        };
+        let mut visited = HashSet::from([entry_point]);
+        let mut candidates = BinaryHeap::from([Reverse(entry)]);
+        let mut results = BinaryHeap::from([entry]);
+
+        while let Some(Reverse(candidate)) = candidates.pop() {
+            let farthest = results.peek().map(|c| c.distance).unwrap_or(f32::MAX);
+            if candidate.distance > farthest && results.len() >= ef {
+                break;
+            }
+            let node = &self.nodes[candidate.node];
+            if let Some(neighbours) = node.neighbours.get(layer) {
+                for neighbour in neighbours {
+                    if !visited.insert(*neighbour) {
+                        continue;
+                    }
+                    let next = Candidate {
+                        distance: self.distance(query, *neighbour),
+                        node: *neighbour,
+                    };
+                    let farthest = results.peek().map(|c| c.distance).unwrap_or(f32::MAX);
+                    if results.len() < ef || next.distance < farthest {
+                        candidates.push(Reverse(next));
+                        results.push(next);
+                        if results.len() > ef {
+                            results.pop();
+                        }
+                    }
+                }
+            }
+        }
+        results.into_sorted_vec()
+    }
+}
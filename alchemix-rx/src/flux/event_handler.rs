@@ -50,7 +50,9 @@ pub struct HookResponse {
     pub success: bool,
     pub handler: String,
     pub entities: Vec<Value>,
-    pub message: String
+    pub message: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
 }
 
 impl HookResponse {
@@ -64,7 +66,18 @@ impl HookResponse {
             success: false,
             handler: "".to_string(),
             entities: vec![],
-            message: message.to_string()
+            message: message.to_string(),
+            errors: vec![],
+        }
+    }
+
+    pub fn invalid(errors: ValidationErrors) -> Self {
+        Self {
+            success: false,
+            handler: "".to_string(),
+            entities: vec![],
+            message: errors.to_string(),
+            errors: errors.errors,
         }
     }
 
@@ -73,7 +86,8 @@ impl HookResponse {
             success: true,
             handler: "".to_string(),
             entities: vec![],
-            message: "".to_string()
+            message: "".to_string(),
+            errors: vec![],
         }
     }
 
@@ -82,7 +96,8 @@ impl HookResponse {
             success: true,
             handler: "".to_string(),
             entities: vec![serde_json::to_value(entity).unwrap()],
-            message: "".to_string()
+            message: "".to_string(),
+            errors: vec![],
         }
     }
 
@@ -1,10 +1,18 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+};
 
 use serde_json::Value;
 
-use crate::{flux::EventHandler, prelude::Entity};
+use crate::{
+    flux::EventHandler,
+    prelude::{Entity, LiveQuery},
+};
 
-use super::{FluxContext, FluxState, HookResponse, StateGetEntities, StateQuery};
+use super::{FluxContext, FluxState, FluxStats, HookResponse, StateGeoQuery, StateGetEntities, StateQuery};
 
 pub struct Flux {
     state: FluxState,
@@ -42,6 +50,9 @@ impl Flux {
     }
 
     pub async fn push<T: Entity>(&self, action: T) -> Vec<HookResponse> {
+        if let Err(errors) = action.validate() {
+            return vec![HookResponse::invalid(errors)];
+        }
         let event_kind = action.get_kind();
         let data_hooks = &self.action_handlers;
         if let Some(handlers) = data_hooks.get(event_kind) {
@@ -82,8 +93,29 @@ impl Flux {
         res
     }
 
+    /// Runs the query now, then again after every save of its kind in its shard
+    pub async fn subscribe_query(&self, query: StateQuery) -> LiveQuery<'_> {
+        let changes = self.state.subscribe_changes(&query.shard);
+        let kind = query.kind.clone();
+        let run_query = move || {
+            let values = self.query_entities(&query);
+            let future: Pin<Box<dyn Future<Output = Value> + Send + '_>> = Box::pin(async move { values });
+            future
+        };
+        LiveQuery::new(&kind, changes, Box::new(run_query)).await
+    }
+
     pub fn get_entities(&self, query: &StateGetEntities) -> Value {
         let res = self.context.get_entities(&self.state, &query);
         res
     }
+
+    pub fn query_geo(&self, query: &StateGeoQuery) -> Value {
+        let res = self.context.query_geo(&self.state, &query);
+        res
+    }
+
+    pub async fn stats(&self) -> Result<FluxStats, String> {
+        self.state.stats().await
+    }
 }
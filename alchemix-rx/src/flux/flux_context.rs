@@ -3,7 +3,9 @@ use std::any::Any;
 use async_trait::async_trait;
 use serde_json::Value;
 
-use super::{EventHandler, Flux, FluxState, HookResponse, StateGetEntities, StateQuery};
+use super::{
+    EventHandler, Flux, FluxState, HookResponse, StateGeoQuery, StateGetEntities, StateQuery,
+};
 
 #[async_trait]
 pub trait FluxContext: Any + Send + Sync {
@@ -19,5 +21,10 @@ pub trait FluxContext: Any + Send + Sync {
 
     fn get_entities(&self, state: &FluxState, query: &StateGetEntities) -> Value;
 
+    /// Generated by `#[flux_context]`, contexts written by hand have no geo index
+    fn query_geo(&self, _state: &FluxState, _query: &StateGeoQuery) -> Value {
+        Value::Array(vec![])
+    }
+
     
 }
\ No newline at end of file
@@ -1,21 +1,55 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+};
+
+use alchemix_utils::file_io;
+use tokio::sync::broadcast;
+
 use crate::prelude::*;
 
+/// Statistics of the shards of a state by shard name, and of the shards of
+/// each tenant by tenant then shard name.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FluxStats {
+    pub shards: BTreeMap<String, StoreStats>,
+    pub tenants: BTreeMap<String, BTreeMap<String, StoreStats>>,
+}
+
 pub struct FluxState {
     root_path: String,
+    // Change channels of the shards with live queries
+    changes: Mutex<HashMap<String, broadcast::Sender<EntityChange>>>,
 }
 
 impl FluxState {
     pub fn new(root_path: &str) -> Self {
         Self {
             root_path: root_path.to_string(),
+            changes: Mutex::new(HashMap::new()),
         }
     }
 
-    pub fn save<T: Entity>(&self, shard: &str, entities: &Vec<T>) {
+    pub fn save<T: Entity>(&self, shard: &str, entities: &Vec<T>) -> Result<(), StoreError> {
         let store = self.get_store(shard);
-        block_on(store.update_entities(entities));
+        block_on(store.update_entities(entities))?;
         // WARNING: May hangs the app
         // block_on(store.close());
+        if let (Some(sender), Some(entity)) = (self.changes.lock().unwrap().get(shard), entities.first()) {
+            let ids = entities.iter().map(|entity| entity.get_id().to_string()).collect();
+            sender.send(EntityChange::new(entity.get_kind(), ids)).ok();
+        }
+        Ok(())
+    }
+
+    /// Entities saved in `shard` from now on
+    pub fn subscribe_changes(&self, shard: &str) -> broadcast::Receiver<EntityChange> {
+        self.changes
+            .lock()
+            .unwrap()
+            .entry(shard.to_string())
+            .or_insert_with(|| broadcast::channel(CHANGE_CHANNEL_CAPACITY).0)
+            .subscribe()
     }
 
     pub fn get_entities_of_kind<E: Entity>(
@@ -43,6 +77,121 @@ impl FluxState {
         res
     }
 
+    pub fn query_predicate<E: Entity>(
+        &self,
+        shard: &str,
+        kind: &EntitySchema<E>,
+        predicate: &Predicate,
+    ) -> Vec<E> {
+        let store = self.get_store(shard);
+        let res = block_on(store.query_predicate(&kind.name, predicate));
+        res
+    }
+
+    pub fn query_geo<E: Entity>(
+        &self,
+        shard: &str,
+        kind: &EntitySchema<E>,
+        query: &GeoQuery,
+    ) -> Vec<E> {
+        let store = self.get_store(shard);
+        let res = block_on(store.query_geo(&kind.name, query));
+        res
+    }
+
+    /// Returns a state whose shards are stored under `<root>/tenants/<tenant>`.
+    pub fn for_tenant(&self, tenant: &str) -> Result<FluxState, String> {
+        SQLiteEntityStore::check_tenant_id(tenant)?;
+        Ok(FluxState::new(&format!("{}/{}", self.get_tenants_dir(), tenant)))
+    }
+
+    pub fn tenants(&self) -> Vec<String> {
+        let mut tenants: Vec<String> = file_io::list_folders(self.get_tenants_dir(), Some(1))
+            .iter()
+            .filter_map(|path| path.file_name().map(|name| name.to_string_lossy().to_string()))
+            .collect();
+        tenants.sort();
+        tenants
+    }
+
+    /// Deletes every shard of `tenant`.
+    pub fn delete_tenant(&self, tenant: &str) -> Result<(), String> {
+        let tenant_state = self.for_tenant(tenant)?;
+        if !std::path::Path::new(tenant_state.get_root_path()).is_dir() {
+            return Err(format!("Unknown tenant : {}", tenant));
+        }
+        std::fs::remove_dir_all(tenant_state.get_root_path())
+            .map_err(|error| format!("Unable to delete tenant {} : {}", tenant, error))
+    }
+
+    fn get_tenants_dir(&self) -> String {
+        format!("{}/tenants", self.root_path)
+    }
+
+    pub fn get_root_path(&self) -> &str {
+        &self.root_path
+    }
+
+    /// Shards stored directly under the root path, the shards of the tenants
+    /// are listed by `for_tenant(tenant).shards()`.
+    pub fn shards(&self) -> Vec<String> {
+        let mut shards: Vec<String> = file_io::list_files(&self.root_path, Some(1))
+            .iter()
+            .filter(|path| path.extension().map(|ext| ext == "db").unwrap_or(false))
+            .filter_map(|path| path.file_stem().map(|stem| stem.to_string_lossy().to_string()))
+            .collect();
+        shards.sort();
+        shards
+    }
+
+    /// Snapshots every shard into `backup_dir`, one `<shard>.db` file per shard.
+    /// The shards of the tenants are written under `tenants/<tenant>`.
+    pub async fn backup_to(&self, backup_dir: &str) -> Result<Vec<String>, String> {
+        let mut backups = self.backup_shards(backup_dir).await?;
+        for tenant in self.tenants() {
+            let tenant_dir = format!("{}/tenants/{}", backup_dir, tenant);
+            backups.extend(self.for_tenant(&tenant)?.backup_shards(&tenant_dir).await?);
+        }
+        Ok(backups)
+    }
+
+    async fn backup_shards(&self, backup_dir: &str) -> Result<Vec<String>, String> {
+        let mut backups = vec![];
+        for shard in self.shards() {
+            let store = self.get_store(&shard);
+            let backup_path = format!("{}/{}.db", backup_dir, shard);
+            let res = store.backup_to(&backup_path).await;
+            store.close().await;
+            res?;
+            backups.push(backup_path);
+        }
+        Ok(backups)
+    }
+
+    /// Statistics of every shard, including the shards of the tenants.
+    pub async fn stats(&self) -> Result<FluxStats, String> {
+        let mut tenants = BTreeMap::new();
+        for tenant in self.tenants() {
+            let tenant_stats = self.for_tenant(&tenant)?.shard_stats().await?;
+            tenants.insert(tenant, tenant_stats);
+        }
+        Ok(FluxStats {
+            shards: self.shard_stats().await?,
+            tenants,
+        })
+    }
+
+    async fn shard_stats(&self) -> Result<BTreeMap<String, StoreStats>, String> {
+        let mut stats = BTreeMap::new();
+        for shard in self.shards() {
+            let store = self.get_store(&shard);
+            let res = store.stats().await;
+            store.close().await;
+            stats.insert(shard, res?);
+        }
+        Ok(stats)
+    }
+
     fn get_store(&self, shard: &str) -> SQLiteEntityStore {
         let db_path = format!("{}/{}.db", self.root_path, shard);
         let store = SQLiteEntityStore::new(&db_path);
@@ -70,6 +219,23 @@ impl StateQuery {
     }
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct StateGeoQuery {
+    pub shard: String,
+    pub kind: String,
+    pub query: GeoQuery,
+}
+
+impl StateGeoQuery {
+    pub fn new(shard: &str, kind: &str, query: GeoQuery) -> Self {
+        Self {
+            shard: shard.to_string(),
+            kind: kind.to_string(),
+            query,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct StateGetEntities {
     pub shard: String,
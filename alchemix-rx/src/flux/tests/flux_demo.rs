@@ -36,7 +36,7 @@ pub async fn sum_history(
     _context: &TestContext,
 ) -> HookResponse {
     println!("SUM History: {}", action.result);
-    state.save("default", &vec![action.clone()]);
+    state.save("default", &vec![action.clone()]).unwrap();
     HookResponse::ok()
 }
 
@@ -4,6 +4,7 @@ pub use ts_rs::TS;
 pub use uuid::Uuid;
 pub use std::marker::PhantomData;
 pub use futures::executor::block_on;
+pub use futures::stream::{Stream, StreamExt};
 pub use async_trait::async_trait;
 pub use std::{any::Any, future::Future, pin::Pin, sync::Arc};
 
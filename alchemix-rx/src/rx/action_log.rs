@@ -0,0 +1,117 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::{fs, io::AsyncWriteExt, sync::Mutex};
+
+use crate::{entity_store::SQLiteEntityStore, rx::{RxAction, RxResponse}};
+
+/// An action received by a store, `timestamp` is in milliseconds since the epoch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoggedAction {
+    pub timestamp: i64,
+    pub action: RxAction,
+}
+
+/// Append-only log of the actions executed by a store, one JSON object per
+/// line. The file is created on the first action.
+pub struct ActionLog {
+    path: String,
+    file: Mutex<Option<fs::File>>,
+}
+
+impl ActionLog {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            file: Mutex::new(None),
+        }
+    }
+
+    pub fn get_path(&self) -> &str {
+        &self.path
+    }
+
+    pub async fn append(&self, action: &RxAction) -> Result<(), String> {
+        let logged_action = LoggedAction {
+            timestamp: SQLiteEntityStore::now_millis(),
+            action: action.clone(),
+        };
+        let line = serde_json::to_string(&logged_action).map_err(|error| error.to_string())?;
+        let map_error = |error: std::io::Error| format!("Unable to write action log {} : {}", &self.path, error);
+        // The lock keeps the order of the lines and the order of the actions the same
+        let mut file = self.file.lock().await;
+        if file.is_none() {
+            if let Some(parent) = Path::new(&self.path).parent() {
+                fs::create_dir_all(parent).await.map_err(map_error)?;
+            }
+            *file = Some(fs::OpenOptions::new().create(true).append(true).open(&self.path).await.map_err(map_error)?);
+        }
+        let file = file.as_mut().unwrap();
+        file.write_all(format!("{}\n", line).as_bytes()).await.map_err(map_error)?;
+        file.flush().await.map_err(map_error)
+    }
+
+    /// Actions of the log at `path`, oldest first
+    pub fn read(path: &str) -> Result<Vec<LoggedAction>, String> {
+        let file = File::open(path).map_err(|error| format!("Unable to open action log {} : {}", path, error))?;
+        let mut actions = vec![];
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|error| format!("Unable to read action log {} : {}", path, error))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let action = serde_json::from_str(&line)
+                .map_err(|error| format!("Invalid action at line {} of {} : {}", index + 1, path, error))?;
+            actions.push(action);
+        }
+        Ok(actions)
+    }
+}
+
+/// Entity hooks settings of a replay, the signal handlers always run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayHooks {
+    Enabled,
+    Disabled,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DivergenceReason {
+    /// The entity is in the original store only
+    Missing,
+    /// The entity is in the replayed store only
+    Unexpected,
+    Different,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Divergence {
+    pub kind: String,
+    pub id: String,
+    pub reason: DivergenceReason,
+}
+
+/// A replayed action which did not get a success response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayFailure {
+    pub position: usize,
+    pub timestamp: i64,
+    pub response: RxResponse,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReplayReport {
+    pub actions: usize,
+    pub failures: Vec<ReplayFailure>,
+    pub divergences: Vec<Divergence>,
+}
+
+impl ReplayReport {
+    pub fn is_identical(&self) -> bool {
+        self.divergences.is_empty()
+    }
+}
@@ -0,0 +1,231 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    future::Future,
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    entity_store::Entity,
+    rx::{HookChain, RxAction},
+};
+
+/// An update or deletion of a session, with the action restoring the values
+/// it replaced
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Command {
+    pub action: RxAction,
+    pub inverse: RxAction,
+}
+
+impl Command {
+    pub fn new(action: RxAction, inverse: RxAction) -> Self {
+        Self { action, inverse }
+    }
+}
+
+pub const DEFAULT_MAX_SESSIONS: usize = 1000;
+
+#[derive(Default)]
+struct SessionLog {
+    undo: VecDeque<Command>,
+    redo: Vec<Command>,
+    // Value of the clock when the session was last used
+    last_used: u64,
+}
+
+impl SessionLog {
+    fn is_empty(&self) -> bool {
+        self.undo.is_empty() && self.redo.is_empty()
+    }
+}
+
+#[derive(Default)]
+struct Sessions {
+    logs: HashMap<String, SessionLog>,
+    clock: u64,
+}
+
+impl Sessions {
+    // Past `max_sessions` the least recently used session is dropped
+    fn get_log(&mut self, session: &str, max_sessions: usize) -> &mut SessionLog {
+        if !self.logs.contains_key(session) && self.logs.len() >= max_sessions {
+            let oldest = self
+                .logs
+                .iter()
+                .min_by_key(|(_, log)| log.last_used)
+                .map(|(session, _)| session.clone());
+            if let Some(oldest) = oldest {
+                self.logs.remove(&oldest);
+            }
+        }
+        self.clock += 1;
+        let log = self.logs.entry(session.to_string()).or_default();
+        log.last_used = self.clock;
+        log
+    }
+
+    // Sessions with nothing to undo or redo are dropped
+    fn pop<F>(&mut self, session: &str, pop: F) -> Option<Command>
+    where
+        F: FnOnce(&mut SessionLog) -> Option<Command>,
+    {
+        let log = self.logs.get_mut(session)?;
+        let command = pop(log);
+        if log.is_empty() {
+            self.logs.remove(session);
+        }
+        command
+    }
+}
+
+/// Undo and redo stacks of each session, past `capacity` commands the oldest
+/// ones can no longer be undone. Up to `max_sessions` sessions are kept, the
+/// least recently used one is dropped to record a new one.
+pub struct CommandLog {
+    capacity: usize,
+    max_sessions: usize,
+    sessions: Mutex<Sessions>,
+}
+
+impl CommandLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            max_sessions: DEFAULT_MAX_SESSIONS,
+            sessions: Mutex::new(Sessions::default()),
+        }
+    }
+
+    pub fn with_max_sessions(mut self, max_sessions: usize) -> Self {
+        self.max_sessions = max_sessions.max(1);
+        self
+    }
+
+    pub fn get_capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn get_max_sessions(&self) -> usize {
+        self.max_sessions
+    }
+
+    /// Number of sessions with commands to undo or redo
+    pub fn get_session_count(&self) -> usize {
+        self.sessions.lock().unwrap().logs.len()
+    }
+
+    /// Commands which can be undone, oldest first
+    pub fn get_commands(&self, session: &str) -> Vec<Command> {
+        let sessions = self.sessions.lock().unwrap();
+        sessions
+            .logs
+            .get(session)
+            .map(|log| log.undo.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Commands which can be redone, the next one last
+    pub fn get_undone_commands(&self, session: &str) -> Vec<Command> {
+        let sessions = self.sessions.lock().unwrap();
+        sessions.logs.get(session).map(|log| log.redo.clone()).unwrap_or_default()
+    }
+
+    /// A new command drops the undone ones
+    pub(crate) fn record(&self, session: &str, command: Command) {
+        let mut sessions = self.sessions.lock().unwrap();
+        let log = sessions.get_log(session, self.max_sessions);
+        log.redo.clear();
+        Self::push_command(log, command, self.capacity);
+    }
+
+    pub(crate) fn push_undo(&self, session: &str, command: Command) {
+        let mut sessions = self.sessions.lock().unwrap();
+        Self::push_command(sessions.get_log(session, self.max_sessions), command, self.capacity);
+    }
+
+    pub(crate) fn pop_undo(&self, session: &str) -> Option<Command> {
+        self.sessions.lock().unwrap().pop(session, |log| log.undo.pop_back())
+    }
+
+    pub(crate) fn push_redo(&self, session: &str, command: Command) {
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.get_log(session, self.max_sessions).redo.push(command);
+    }
+
+    pub(crate) fn pop_redo(&self, session: &str) -> Option<Command> {
+        self.sessions.lock().unwrap().pop(session, |log| log.redo.pop())
+    }
+
+    fn push_command(log: &mut SessionLog, command: Command, capacity: usize) {
+        log.undo.push_back(command);
+        while log.undo.len() > capacity {
+            log.undo.pop_front();
+        }
+    }
+}
+
+tokio::task_local! {
+    static COMMAND_CAPTURE: CommandCapture;
+}
+
+/// Inverse operations of the writes done by a recorded action
+#[derive(Clone, Default)]
+pub(crate) struct CommandCapture {
+    inverses: Arc<Mutex<Vec<RxAction>>>,
+}
+
+impl CommandCapture {
+    /// Writes done by hooks are not captured, the hooks run again when the
+    /// action is undone.
+    pub(crate) fn current() -> Option<CommandCapture> {
+        if HookChain::current().depth() != 1 {
+            return None;
+        }
+        COMMAND_CAPTURE.try_with(|capture| capture.clone()).ok()
+    }
+
+    pub(crate) async fn scope<F: Future>(self, future: F) -> F::Output {
+        COMMAND_CAPTURE.scope(self, future).await
+    }
+
+    /// `previous_entities` are the stored values of `entities` before the update
+    pub(crate) fn record_update<T: Entity>(&self, kind: &str, entities: &[T], previous_entities: &[Option<T>]) {
+        let restored: Vec<&T> = previous_entities.iter().flatten().collect();
+        let created: Vec<String> = entities
+            .iter()
+            .zip(previous_entities)
+            .filter(|(_, previous)| previous.is_none())
+            .map(|(entity, _)| entity.get_id().to_string())
+            .collect();
+        let mut inverses = self.inverses.lock().unwrap();
+        if !restored.is_empty() {
+            inverses.push(RxAction::UpdateEntities(kind.to_string(), serde_json::to_value(restored).unwrap()));
+        }
+        if !created.is_empty() {
+            inverses.push(RxAction::new_delete_action(kind, created));
+        }
+    }
+
+    pub(crate) fn record_delete<T: Entity>(&self, kind: &str, removed_entities: &[T]) {
+        if !removed_entities.is_empty() {
+            let values = serde_json::to_value(removed_entities).unwrap();
+            self.inverses.lock().unwrap().push(RxAction::UpdateEntities(kind.to_string(), values));
+        }
+    }
+
+    /// Several inverse operations are undone together in a batch, None when
+    /// nothing was written
+    pub(crate) fn take_inverse(&self) -> Option<RxAction> {
+        let mut inverses = std::mem::take(&mut *self.inverses.lock().unwrap());
+        match inverses.len() {
+            0 => None,
+            1 => inverses.pop(),
+            _ => {
+                inverses.reverse();
+                Some(RxAction::new_batch(inverses))
+            }
+        }
+    }
+}
@@ -1,4 +1,13 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, AtomicU8, AtomicUsize, Ordering},
+        RwLock,
+    },
+    time::Duration,
+};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 
 use crate::prelude::*;
 
@@ -22,6 +31,11 @@ pub type SafeDataHookHandler = dyn DataHookHandler + Send + Sync;
 
 pub type SafeSignalHookHandler = dyn SignalHookHandler + Send + Sync;
 
+pub type SafeStreamHookHandler = dyn StreamHookHandler + Send + Sync;
+
+/// Items of a streamed signal response, as JSON
+pub type SignalStream<'a> = Pin<Box<dyn Stream<Item = Value> + Send + 'a>>;
+
 #[async_trait]
 pub trait SignalHookHandler {
     async fn handle(
@@ -37,69 +51,316 @@ pub trait SignalHookHandler {
     ) -> Result<Value, String>;
 
     fn get_name(&self) -> &str;
+
+    /// The handler is cancelled when it runs longer
+    fn get_timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Signals received while the handler already runs this number of times are rejected
+    fn get_max_concurrency(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// Handler of a signal whose response is sent item by item
+pub trait StreamHookHandler {
+    fn handle_stream<'a>(&self, context: Arc<DispatchPayload<'a>>, value: Value) -> Result<SignalStream<'a>, String>;
+
+    fn get_name(&self) -> &str;
+
+    /// Longest wait for the next item, the stream ends past it
+    fn get_timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Streams started while this number of them are still running are rejected
+    fn get_max_concurrency(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// Serializes the items of a stream handler, the stream ends at the first
+/// item which can not be serialized.
+pub fn to_signal_stream<'a, T, S>(stream: S) -> SignalStream<'a>
+where
+    T: Serialize,
+    S: Stream<Item = T> + Send + 'a,
+{
+    let values = stream.map(|item| serde_json::to_value(item)).scan((), |_, value| {
+        futures::future::ready(match value {
+            Ok(value) => Some(value),
+            Err(error) => {
+                println!("Unable to serialize stream item : {}", error);
+                None
+            }
+        })
+    });
+    Box::pin(values)
 }
 
 #[async_trait]
 pub trait DataHookHandler {
-    async fn handle(&self, context: Arc<DispatchPayload<'_>>, value: Arc<Payload>);
+    async fn handle(&self, context: Arc<DispatchPayload<'_>>, value: Arc<Payload>) -> Result<(), HookError>;
+
+    /// Called by the before hooks, the entities returned replace the input
+    /// and an error cancels the whole operation.
+    async fn intercept(
+        &self,
+        _context: Arc<DispatchPayload<'_>>,
+        value: Box<Payload>,
+    ) -> Result<Box<Payload>, String> {
+        Ok(value)
+    }
+
     fn get_action(&self) -> EntityAction;
     fn get_entity_kind(&self) -> &str;
+
+    /// Name reported in the hook failures
+    fn get_name(&self) -> &str {
+        ""
+    }
+
+    /// Hooks with a higher priority run first
+    fn get_priority(&self) -> i32 {
+        0
+    }
+
+    fn get_mode(&self) -> HookMode {
+        HookMode::Parallel
+    }
+
+    /// Deferred hooks are queued as jobs instead of being run by the dispatch
+    fn is_deferred(&self) -> bool {
+        false
+    }
+
+    /// Runs the hook on the JSON payload of a job
+    async fn handle_job(&self, _context: Arc<DispatchPayload<'_>>, _payload: &str) -> Result<(), HookError> {
+        Err(HookError::new("Hook can not run as a job"))
+    }
+}
+
+/// Parallel hooks of the same priority run concurrently, a sequential hook
+/// runs alone once the hooks before it are complete.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookMode {
+    Parallel,
+    Sequential,
+}
+
+tokio::task_local! {
+    // Set while an action log is replayed without the entity hooks
+    static ENTITY_HOOKS_DISABLED: bool;
+}
+
+/// Identifies the hooks added by one registration, to remove them later
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HookHandle(u64);
+
+// Handlers read by the dispatches. A registration replaces the whole table,
+// the dispatches in flight keep the table they started with.
+#[derive(Clone, Default)]
+struct HookTable {
+    data_hooks: HashMap<String, Vec<(HookHandle, Arc<SafeDataHookHandler>)>>,
+    // A registration replacing the handler of a kind hides the previous one,
+    // which runs again once the registration is removed. The last one is active.
+    signal_hooks: HashMap<String, Vec<SignalHookEntry>>,
+    stream_hooks: HashMap<String, Vec<StreamHookEntry>>,
+}
+
+impl HookTable {
+    fn get_signal_hook(&self, kind: &str) -> Option<&SignalHookEntry> {
+        self.signal_hooks.get(kind).and_then(|entries| entries.last())
+    }
+
+    fn get_stream_hook(&self, kind: &str) -> Option<&StreamHookEntry> {
+        self.stream_hooks.get(kind).and_then(|entries| entries.last())
+    }
+}
+
+#[derive(Clone)]
+struct SignalHookEntry {
+    handle: HookHandle,
+    handler: Arc<SafeSignalHookHandler>,
+    // Running signals of the handlers with a concurrency limit
+    permits: Option<Arc<Semaphore>>,
+}
+
+#[derive(Clone)]
+struct StreamHookEntry {
+    handle: HookHandle,
+    handler: Arc<SafeStreamHookHandler>,
+    // Held by the running streams of the handlers with a concurrency limit
+    permits: Option<Arc<Semaphore>>,
 }
 
 pub struct Dispatcher {
-    data_hooks: HashMap<String, Vec<Box<SafeDataHookHandler>>>,
-    signal_hook: HashMap<String, Box<SafeSignalHookHandler>>,
+    hooks: RwLock<Arc<HookTable>>,
+    next_handle: AtomicU64,
+    // Settings can be changed while the dispatcher is shared with tenant handles
+    failure_policy: AtomicU8,
+    max_hook_depth: AtomicUsize,
 }
 
 impl Dispatcher {
     pub fn new() -> Self {
         Dispatcher {
-            data_hooks: HashMap::new(),
-            signal_hook: HashMap::new(),
+            hooks: RwLock::new(Arc::new(HookTable::default())),
+            next_handle: AtomicU64::new(1),
+            failure_policy: AtomicU8::new(HookFailurePolicy::default().to_u8()),
+            max_hook_depth: AtomicUsize::new(DEFAULT_MAX_HOOK_DEPTH),
         }
     }
 
-    pub fn register_entity_hooks(&mut self, hooks: Vec<Box<SafeDataHookHandler>>) {
-        let data_hooks = &mut self.data_hooks;
-        for handler in hooks {
-            let action = handler.get_action();
-            let entity_kind = handler.get_entity_kind();
+    pub fn set_max_hook_depth(&self, depth: usize) {
+        self.max_hook_depth.store(depth, Ordering::SeqCst);
+    }
 
-            let action_id = format!("{}_{}", action.get_text(), entity_kind);
+    pub fn get_max_hook_depth(&self) -> usize {
+        self.max_hook_depth.load(Ordering::SeqCst)
+    }
 
-            let handlers = data_hooks.entry(action_id).or_insert(vec![]);
-            handlers.push(handler);
-        }
-        // let hooks: Vec<&str> = data_hooks.iter().map(|(k, _)| k.as_str()).collect();
-        // println!("Hooks : {:?}", hooks);
+    pub fn set_failure_policy(&self, policy: HookFailurePolicy) {
+        self.failure_policy.store(policy.to_u8(), Ordering::SeqCst);
     }
 
-    pub fn register_signal_hooks(&mut self, hooks: Vec<Box<SafeSignalHookHandler>>) {
-        for handler in hooks {
-            self.signal_hook.insert(handler.get_name().to_string(), handler);
-        }
+    pub fn get_failure_policy(&self) -> HookFailurePolicy {
+        HookFailurePolicy::from_u8(self.failure_policy.load(Ordering::SeqCst))
+    }
+
+    fn get_hooks(&self) -> Arc<HookTable> {
+        self.hooks.read().unwrap().clone()
+    }
+
+    // Registrations are serialized by the write lock, the readers only hold
+    // the read lock while cloning the table pointer
+    fn update_hooks<F: FnOnce(&mut HookTable)>(&self, update: F) {
+        let mut hooks = self.hooks.write().unwrap();
+        let mut table = HookTable::clone(&hooks);
+        update(&mut table);
+        *hooks = Arc::new(table);
+    }
+
+    fn next_handle(&self) -> HookHandle {
+        HookHandle(self.next_handle.fetch_add(1, Ordering::SeqCst))
+    }
+
+    pub fn register_entity_hooks(&self, hooks: Vec<Box<SafeDataHookHandler>>) -> HookHandle {
+        let handle = self.next_handle();
+        self.update_hooks(|table| {
+            for handler in hooks {
+                let action = handler.get_action();
+                let entity_kind = handler.get_entity_kind();
+
+                let action_id = format!("{}_{}", action.get_text(), entity_kind);
+
+                let handlers = table.data_hooks.entry(action_id).or_insert(vec![]);
+                handlers.push((handle, Arc::from(handler)));
+                // Stable sort, registration order is kept within a priority
+                handlers.sort_by_key(|(_, handler)| std::cmp::Reverse(handler.get_priority()));
+            }
+        });
+        handle
+    }
+
+    /// A signal has a single handler, registering another one for the same kind replaces it
+    pub fn register_signal_hooks(&self, hooks: Vec<Box<SafeSignalHookHandler>>) -> HookHandle {
+        let handle = self.next_handle();
+        self.update_hooks(|table| {
+            for handler in hooks {
+                let entries = table.signal_hooks.entry(handler.get_name().to_string()).or_default();
+                // A replacement with the same limit shares the permits of the signals still running
+                let permits = match entries.last() {
+                    Some(previous) if previous.handler.get_max_concurrency() == handler.get_max_concurrency() => {
+                        previous.permits.clone()
+                    }
+                    _ => handler
+                        .get_max_concurrency()
+                        .map(|max_concurrency| Arc::new(Semaphore::new(max_concurrency))),
+                };
+                entries.push(SignalHookEntry {
+                    handle,
+                    handler: Arc::from(handler),
+                    permits,
+                });
+            }
+        });
+        handle
+    }
+
+    pub fn register_stream_hooks(&self, hooks: Vec<Box<SafeStreamHookHandler>>) -> HookHandle {
+        let handle = self.next_handle();
+        self.update_hooks(|table| {
+            for handler in hooks {
+                let entries = table.stream_hooks.entry(handler.get_name().to_string()).or_default();
+                let permits = match entries.last() {
+                    Some(previous) if previous.handler.get_max_concurrency() == handler.get_max_concurrency() => {
+                        previous.permits.clone()
+                    }
+                    _ => handler
+                        .get_max_concurrency()
+                        .map(|max_concurrency| Arc::new(Semaphore::new(max_concurrency))),
+                };
+                entries.push(StreamHookEntry {
+                    handle,
+                    handler: Arc::from(handler),
+                    permits,
+                });
+            }
+        });
+        handle
+    }
+
+    /// Removes the hooks of a registration, the signal and stream handlers they
+    /// replaced are active again. Returns false when the registration had no
+    /// hook left. The dispatches already started still run them.
+    pub fn unregister_hooks(&self, handle: HookHandle) -> bool {
+        let mut removed = false;
+        self.update_hooks(|table| {
+            for handlers in table.data_hooks.values_mut() {
+                let count = handlers.len();
+                handlers.retain(|(hook_handle, _)| *hook_handle != handle);
+                removed |= handlers.len() != count;
+            }
+            table.data_hooks.retain(|_, handlers| !handlers.is_empty());
+            for entries in table.signal_hooks.values_mut() {
+                let count = entries.len();
+                entries.retain(|entry| entry.handle != handle);
+                removed |= entries.len() != count;
+            }
+            table.signal_hooks.retain(|_, entries| !entries.is_empty());
+            for entries in table.stream_hooks.values_mut() {
+                let count = entries.len();
+                entries.retain(|entry| entry.handle != handle);
+                removed |= entries.len() != count;
+            }
+            table.stream_hooks.retain(|_, entries| !entries.is_empty());
+        });
+        removed
     }
 
     pub async fn dispatch_signal_hook<'a, T: Entity, R: Entity>(
         &'a self,
         context: Arc<DispatchPayload<'a>>,
         signal_entity: T,
-    ) -> Result<R, String>{
-        if let Some(handler) =  self.signal_hook.get(signal_entity.get_kind()) {
+    ) -> Result<R, RxError>{
+        let hooks = self.get_hooks();
+        if let Some(entry) = hooks.get_signal_hook(signal_entity.get_kind()) {
             let value_ref = Arc::new(signal_entity);
-            let response = handler.handle(context, value_ref).await;
+            let response = Self::run_signal_handler(entry, entry.handler.handle(context, value_ref)).await;
             match response {
                 Ok(data) => {
                     if let Ok(data) = data.downcast::<R>() {
                         Ok(*data)
                     }else {
-                        Err("Downcast error".to_string())
+                        Err(RxError::Failure("Downcast error".to_string()))
                     }
                 },
-                Err(message) => Err(message)
+                Err(error) => Err(error)
             }
         }else {
-            Err("Unable to find signal handler".to_string())
+            Err(RxError::Failure("Unable to find signal handler".to_string()))
         }
     }
 
@@ -107,13 +368,149 @@ impl Dispatcher {
         &'a self,
         context: Arc<DispatchPayload<'a>>,
         signal_entity: T,
-    ) -> Result<Value, String>{
-        if let Some(handler) =  self.signal_hook.get(signal_entity.get_kind()) {
+    ) -> Result<Value, RxError>{
+        let hooks = self.get_hooks();
+        if let Some(entry) = hooks.get_signal_hook(signal_entity.get_kind()) {
             let value_ref = Arc::new(signal_entity);
-            let response = handler.handle_json_action(context, value_ref).await;
-            response
+            Self::run_signal_handler(entry, entry.handler.handle_json_action(context, value_ref)).await
         }else {
-            Err("Unable to find signal handler".to_string())
+            Err(RxError::Failure("Unable to find signal handler".to_string()))
+        }
+    }
+
+    /// Starts the stream handler of the kind of `signal`. The stream holds its
+    /// concurrency permit until it is dropped.
+    pub fn dispatch_stream_hook<'a>(
+        &'a self,
+        context: Arc<DispatchPayload<'a>>,
+        signal: Value,
+    ) -> Result<SignalStream<'a>, RxError> {
+        let kind = signal.get("kind").and_then(Value::as_str).unwrap_or_default().to_string();
+        let hooks = self.get_hooks();
+        let Some(entry) = hooks.get_stream_hook(&kind) else {
+            return Err(RxError::Failure(format!("Unable to find stream handler of {}", kind)));
+        };
+        let permit = match &entry.permits {
+            Some(permits) => Some(permits.clone().try_acquire_owned().map_err(|_| RxError::Overloaded(kind.clone()))?),
+            None => None,
+        };
+        let stream = entry.handler.handle_stream(context, signal).map_err(RxError::Failure)?;
+        Ok(Self::limit_stream(stream, permit, entry.handler.get_timeout(), kind))
+    }
+
+    fn limit_stream<'a>(
+        stream: SignalStream<'a>,
+        permit: Option<OwnedSemaphorePermit>,
+        timeout: Option<Duration>,
+        kind: String,
+    ) -> SignalStream<'a> {
+        if permit.is_none() && timeout.is_none() {
+            return stream;
+        }
+        let items = futures::stream::unfold((stream, permit), move |(mut stream, permit)| {
+            let kind = kind.clone();
+            async move {
+                let item = match timeout {
+                    Some(timeout) => tokio::time::timeout(timeout, stream.next()).await.unwrap_or_else(|_| {
+                        println!("Stream handler {} timed out", kind);
+                        None
+                    }),
+                    None => stream.next().await,
+                };
+                item.map(|item| (item, (stream, permit)))
+            }
+        });
+        Box::pin(items)
+    }
+
+    /// Applies the concurrency limit and the timeout of the handler, dropping
+    /// the future cancels the handler.
+    async fn run_signal_handler<V, F>(entry: &SignalHookEntry, future: F) -> Result<V, RxError>
+    where
+        F: Future<Output = Result<V, String>>,
+    {
+        let kind = entry.handler.get_name();
+        let _permit = match &entry.permits {
+            Some(permits) => Some(permits.try_acquire().map_err(|_| RxError::Overloaded(kind.to_string()))?),
+            None => None,
+        };
+        let result = match entry.handler.get_timeout() {
+            Some(timeout) => tokio::time::timeout(timeout, future)
+                .await
+                .map_err(|_| RxError::Timeout(kind.to_string()))?,
+            None => future.await,
+        };
+        result.map_err(RxError::Failure)
+    }
+
+    /// Runs `future` without the entity hooks, the signal handlers still run
+    pub async fn without_entity_hooks<F: Future>(future: F) -> F::Output {
+        ENTITY_HOOKS_DISABLED.scope(true, future).await
+    }
+
+    // Handlers of `action_key`, none while the entity hooks are disabled
+    fn get_entity_hooks(&self, action_key: &str) -> Vec<Arc<SafeDataHookHandler>> {
+        if ENTITY_HOOKS_DISABLED.try_with(|disabled| *disabled).unwrap_or(false) {
+            return vec![];
+        }
+        self.get_hooks()
+            .data_hooks
+            .get(action_key)
+            .map(|handlers| handlers.iter().map(|(_, handler)| handler.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn has_entity_hooks(&self, action: EntityAction, entity_kind: &str) -> bool {
+        let action_key = format!("{}_{}", action.get_text(), entity_kind);
+        !self.get_entity_hooks(&action_key).is_empty()
+    }
+
+    /// Deferred hooks of `actions`, their jobs are queued by the store in the
+    /// transaction writing the entities.
+    pub fn get_deferred_hooks(&self, actions: &[EntityAction], entity_kind: &str) -> Vec<DeferredHook> {
+        let mut deferred = vec![];
+        for action in actions {
+            let action_key = format!("{}_{}", action.get_text(), entity_kind);
+            for handler in self.get_entity_hooks(&action_key) {
+                if handler.is_deferred() {
+                    deferred.push(DeferredHook {
+                        hook: handler.get_name().to_string(),
+                        action: action.get_text().to_string(),
+                        kind: entity_kind.to_string(),
+                        payload: match action {
+                            EntityAction::Change => JobPayload::Changes,
+                            _ => JobPayload::Entities,
+                        },
+                    });
+                }
+            }
+        }
+        deferred
+    }
+
+    /// Runs the before hooks of `action` one after the other, each one gets the
+    /// entities returned by the previous one.
+    pub async fn dispatch_before_hook<'a, T: Entity>(
+        &'a self,
+        context: Arc<DispatchPayload<'a>>,
+        action: EntityAction,
+        value: Vec<T>,
+    ) -> Result<Vec<T>, String> {
+        let Some(entity_kind) = value.first().map(|entity| entity.get_kind().to_string()) else {
+            return Ok(value);
+        };
+        let action_key = format!("{}_{}", action.get_text(), entity_kind);
+        let handlers = self.get_entity_hooks(&action_key);
+        if handlers.is_empty() {
+            return Ok(value);
+        }
+        let mut value: Box<Payload> = Box::new(value);
+        for handler in &handlers {
+            value = handler.intercept(context.clone(), value).await?;
+        }
+        match value.downcast::<Vec<T>>() {
+            Ok(entities) => Ok(*entities),
+            Err(_) => Err("Downcast error".to_string()),
         }
     }
 
@@ -122,7 +519,7 @@ impl Dispatcher {
         context: Arc<DispatchPayload<'a>>,
         action: EntityAction,
         value: Vec<T>,
-    ) {
+    ) -> HookReport {
         let entity_kind = if let Some(entity) = value.first() {
             Some(entity.get_kind().to_string())
         } else {
@@ -130,27 +527,106 @@ impl Dispatcher {
         };
         if let Some(entity_kind) = entity_kind {
             let action_key = format!("{}_{}", action.get_text(), entity_kind);
-            let data_hooks = &self.data_hooks;
-            if let Some(handlers) = data_hooks.get(&action_key) {
-                let value_ref = Arc::new(value);
+            self.dispatch_data_hook(context, &action_key, value).await
+        } else {
+            HookReport::default()
+        }
+    }
+
+    /// Dispatches `(previous, new)` pairs to the change hooks
+    pub async fn dispatch_change_hook<'a, T: Entity>(
+        &'a self,
+        context: Arc<DispatchPayload<'a>>,
+        value: Vec<(Option<T>, T)>,
+    ) -> HookReport {
+        if let Some((_, entity)) = value.first() {
+            let action_key = format!("{}_{}", EntityAction::Change.get_text(), entity.get_kind());
+            self.dispatch_data_hook(context, &action_key, value).await
+        } else {
+            HookReport::default()
+        }
+    }
+
+    async fn dispatch_data_hook<'a, V: Send + Sync + 'static>(
+        &'a self,
+        context: Arc<DispatchPayload<'a>>,
+        action_key: &str,
+        value: V,
+    ) -> HookReport {
+        let mut report = HookReport::default();
+        // The jobs of the deferred hooks were queued with the write
+        let handlers = self.get_entity_hooks(action_key);
+        let handlers: Vec<_> = handlers.iter().filter(|handler| !handler.is_deferred()).collect();
+        if !handlers.is_empty() {
+            let value_ref: Arc<Payload> = Arc::new(value);
+            for stage in Self::get_stages(&handlers) {
                 let mut futures = vec![];
-                for handler in handlers {
-                    // handler.handle(context.clone(), value_ref.clone()).await;
+                for handler in stage {
                     let future = handler.handle(context.clone(), value_ref.clone());
                     futures.push(future);
                 }
-                futures::future::join_all(futures).await;
+                let results = futures::future::join_all(futures).await;
+                for (handler, result) in stage.iter().zip(results) {
+                    if let Err(error) = result {
+                        self.add_failure(&mut report, handler.as_ref(), error);
+                    }
+                }
+                if !report.is_success() && self.get_failure_policy() == HookFailurePolicy::FailFast {
+                    break;
+                }
             }
         }
+        report
+    }
+
+    fn add_failure(&self, report: &mut HookReport, handler: &SafeDataHookHandler, error: HookError) {
+        let failure = HookFailure {
+            hook: handler.get_name().to_string(),
+            action: handler.get_action().get_text().to_string(),
+            kind: handler.get_entity_kind().to_string(),
+            error,
+        };
+        if self.get_failure_policy() == HookFailurePolicy::Log {
+            println!("Hook {} failed on {} {} : {}", failure.hook, failure.action, failure.kind, failure.error);
+        }
+        report.failures.push(failure);
+    }
+
+    /// Handler of a queued job
+    pub fn get_data_hook(&self, action: &str, entity_kind: &str, name: &str) -> Option<Arc<SafeDataHookHandler>> {
+        let action_key = format!("{}_{}", action, entity_kind);
+        self.get_hooks()
+            .data_hooks
+            .get(&action_key)?
+            .iter()
+            .find(|(_, handler)| handler.get_name() == name)
+            .map(|(_, handler)| handler.clone())
+    }
+
+    /// Splits handlers sorted by priority into stages run one after the other
+    fn get_stages<'h>(handlers: &'h [&'h Arc<SafeDataHookHandler>]) -> Vec<&'h [&'h Arc<SafeDataHookHandler>]> {
+        handlers
+            .chunk_by(|previous, handler| {
+                previous.get_mode() == HookMode::Parallel
+                    && handler.get_mode() == HookMode::Parallel
+                    && previous.get_priority() == handler.get_priority()
+            })
+            .collect()
     }
 }
 
 const UPDATE_ENTITY_ACTION: &str = "update";
 const DELETE_ENTITY_ACTION: &str = "delete";
+const BEFORE_UPDATE_ENTITY_ACTION: &str = "before_update";
+const BEFORE_DELETE_ENTITY_ACTION: &str = "before_delete";
+const CHANGE_ENTITY_ACTION: &str = "change";
 
 pub enum EntityAction {
     Update,
     Delete,
+    BeforeUpdate,
+    BeforeDelete,
+    Change,
 }
 
 impl EntityAction {
@@ -158,6 +634,9 @@ impl EntityAction {
         match self {
             EntityAction::Update => UPDATE_ENTITY_ACTION,
             EntityAction::Delete => DELETE_ENTITY_ACTION,
+            EntityAction::BeforeUpdate => BEFORE_UPDATE_ENTITY_ACTION,
+            EntityAction::BeforeDelete => BEFORE_DELETE_ENTITY_ACTION,
+            EntityAction::Change => CHANGE_ENTITY_ACTION,
         }
     }
 }
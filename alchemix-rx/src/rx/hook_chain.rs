@@ -0,0 +1,71 @@
+use std::{fmt, future::Future};
+
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_MAX_HOOK_DEPTH: usize = 8;
+
+/// A store operation which triggered entity hooks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HookCause {
+    pub action: String,
+    pub kind: String,
+}
+
+impl HookCause {
+    pub fn new(action: &str, kind: &str) -> Self {
+        Self {
+            action: action.to_string(),
+            kind: kind.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for HookCause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.action, self.kind)
+    }
+}
+
+/// Operations in progress in the current task, from the first one to the
+/// innermost. Hooks run in the task of the operation which dispatched them,
+/// so writes done by a hook extend the chain of their cause.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HookChain {
+    pub causes: Vec<HookCause>,
+}
+
+tokio::task_local! {
+    static HOOK_CHAIN: HookChain;
+}
+
+impl HookChain {
+    pub fn current() -> HookChain {
+        HOOK_CHAIN.try_with(|chain| chain.clone()).unwrap_or_default()
+    }
+
+    pub fn depth(&self) -> usize {
+        self.causes.len()
+    }
+
+    pub fn contains(&self, cause: &HookCause) -> bool {
+        self.causes.contains(cause)
+    }
+
+    pub fn with_cause(&self, cause: HookCause) -> HookChain {
+        let mut chain = self.clone();
+        chain.causes.push(cause);
+        chain
+    }
+
+    /// Runs `future` with this chain as the current one
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        HOOK_CHAIN.scope(self, future).await
+    }
+}
+
+impl fmt::Display for HookChain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let causes: Vec<String> = self.causes.iter().map(|cause| cause.to_string()).collect();
+        write!(f, "{}", causes.join(" -> "))
+    }
+}
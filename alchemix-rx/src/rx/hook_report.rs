@@ -0,0 +1,118 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HookError {
+    pub message: String,
+}
+
+impl HookError {
+    pub fn new(message: &str) -> Self {
+        Self {
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for HookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl From<String> for HookError {
+    fn from(message: String) -> Self {
+        Self { message }
+    }
+}
+
+impl From<&str> for HookError {
+    fn from(message: &str) -> Self {
+        Self::new(message)
+    }
+}
+
+/// Return values accepted from entity hooks, hooks returning `()` never fail.
+pub trait IntoHookResult {
+    fn into_hook_result(self) -> Result<(), HookError>;
+}
+
+impl IntoHookResult for () {
+    fn into_hook_result(self) -> Result<(), HookError> {
+        Ok(())
+    }
+}
+
+impl<E: Into<HookError>> IntoHookResult for Result<(), E> {
+    fn into_hook_result(self) -> Result<(), HookError> {
+        self.map_err(Into::into)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HookFailure {
+    pub hook: String,
+    pub action: String,
+    pub kind: String,
+    pub error: HookError,
+}
+
+/// Failed hooks of a dispatch, empty when every hook succeeded.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HookReport {
+    pub failures: Vec<HookFailure>,
+}
+
+impl HookReport {
+    pub fn is_success(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    pub fn merge(&mut self, other: HookReport) {
+        self.failures.extend(other.failures);
+    }
+}
+
+impl fmt::Display for HookReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let failures: Vec<String> = self
+            .failures
+            .iter()
+            .map(|failure| format!("{} ({} {}) : {}", failure.hook, failure.action, failure.kind, failure.error))
+            .collect();
+        write!(f, "Hooks failed : {}", failures.join(", "))
+    }
+}
+
+/// What the store does when an entity hook fails
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HookFailurePolicy {
+    /// Failures are only returned in the report
+    Ignore,
+    /// Failures are printed and returned in the report
+    #[default]
+    Log,
+    /// The following stages are skipped and the operation returns an error,
+    /// the entities are already written. The change hooks are the first stages
+    /// of an update, a failing one skips the update hooks.
+    FailFast,
+}
+
+impl HookFailurePolicy {
+    pub(crate) fn to_u8(self) -> u8 {
+        match self {
+            HookFailurePolicy::Ignore => 0,
+            HookFailurePolicy::Log => 1,
+            HookFailurePolicy::FailFast => 2,
+        }
+    }
+
+    pub(crate) fn from_u8(value: u8) -> Self {
+        match value {
+            0 => HookFailurePolicy::Ignore,
+            2 => HookFailurePolicy::FailFast,
+            _ => HookFailurePolicy::Log,
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::rx::{RxAction, RxResponse, RxStore};
+
+/// Middleware around `RxStore::execute_action`, `signal` and `signal_action`,
+/// typed signals are seen as `RxAction::Signal`.
+/// `before` runs in registration order and `after` in reverse order, only
+/// for the interceptors whose `before` was called.
+#[async_trait]
+pub trait RxInterceptor: Send + Sync {
+    /// Can modify the action, or return a response which skips its execution
+    /// and the following interceptors.
+    async fn before(&self, _store: &RxStore, _action: &mut RxAction) -> Option<RxResponse> {
+        None
+    }
+
+    async fn after(&self, _store: &RxStore, _action: &RxAction, response: RxResponse) -> RxResponse {
+        response
+    }
+}
+
+/// Lets the caller keep a handle on a registered interceptor
+#[async_trait]
+impl<T: RxInterceptor> RxInterceptor for Arc<T> {
+    async fn before(&self, store: &RxStore, action: &mut RxAction) -> Option<RxResponse> {
+        self.as_ref().before(store, action).await
+    }
+
+    async fn after(&self, store: &RxStore, action: &RxAction, response: RxResponse) -> RxResponse {
+        self.as_ref().after(store, action, response).await
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::BTreeMap;
+
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::prelude::*;
+
+/// Capacity of the change channels, a subscriber lagging behind re-runs its query
+pub const CHANGE_CHANNEL_CAPACITY: usize = 256;
+
+/// Entities of `kind` written or deleted, sent once the write is committed
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityChange {
+    pub kind: String,
+    pub ids: Vec<String>,
+}
+
+impl EntityChange {
+    pub fn new(kind: &str, ids: Vec<String>) -> Self {
+        Self {
+            kind: kind.to_string(),
+            ids,
+        }
+    }
+}
+
+/// Difference between two results of a live query, `removed` holds the ids
+/// of the entities which left the results.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QueryDiff {
+    pub added: Vec<Value>,
+    pub updated: Vec<Value>,
+    pub removed: Vec<String>,
+}
+
+impl QueryDiff {
+    fn new(previous: &BTreeMap<String, Value>, current: &BTreeMap<String, Value>) -> Self {
+        let mut diff = QueryDiff::default();
+        for (id, value) in current {
+            match previous.get(id) {
+                None => diff.added.push(value.clone()),
+                Some(previous_value) if previous_value != value => diff.updated.push(value.clone()),
+                Some(_) => {}
+            }
+        }
+        diff.removed = previous
+            .keys()
+            .filter(|id| !current.contains_key(*id))
+            .cloned()
+            .collect();
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty()
+    }
+}
+
+pub type LiveQueryRunner<'q> = Box<dyn Fn() -> Pin<Box<dyn Future<Output = Value> + Send + 'q>> + Send + Sync + 'q>;
+
+/// A query run again on every change of its kind, created by
+/// `RxStore::subscribe_query` or `Flux::subscribe_query`.
+pub struct LiveQuery<'q> {
+    kind: String,
+    results: BTreeMap<String, Value>,
+    changes: broadcast::Receiver<EntityChange>,
+    run_query: LiveQueryRunner<'q>,
+}
+
+impl<'q> LiveQuery<'q> {
+    /// Runs the query once, the changes are listened from now on
+    pub async fn new(kind: &str, changes: broadcast::Receiver<EntityChange>, run_query: LiveQueryRunner<'q>) -> Self {
+        let results = Self::get_results_by_id(run_query().await);
+        Self {
+            kind: kind.to_string(),
+            results,
+            changes,
+            run_query,
+        }
+    }
+
+    /// Current results, ordered by id
+    pub fn get_results(&self) -> Vec<Value> {
+        self.results.values().cloned().collect()
+    }
+
+    /// Waits for a change of the results, None once the store is dropped
+    pub async fn next(&mut self) -> Option<QueryDiff> {
+        loop {
+            match self.changes.recv().await {
+                Ok(change) if change.kind != self.kind => continue,
+                Ok(_) | Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => return None,
+            }
+            let results = Self::get_results_by_id((self.run_query)().await);
+            let diff = QueryDiff::new(&self.results, &results);
+            self.results = results;
+            if !diff.is_empty() {
+                return Some(diff);
+            }
+        }
+    }
+
+    fn get_results_by_id(results: Value) -> BTreeMap<String, Value> {
+        let Value::Array(values) = results else {
+            return BTreeMap::new();
+        };
+        values
+            .into_iter()
+            .filter_map(|value| {
+                let id = value.get("id")?.as_str()?.to_string();
+                Some((id, value))
+            })
+            .collect()
+    }
+}
@@ -1,6 +1,22 @@
 mod rx;
 mod rx_store;
 mod dispatcher;
+mod hook_report;
+mod hook_chain;
+mod scheduler;
+mod interceptor;
+mod rx_batch;
+mod live_query;
+mod command_log;
+mod action_log;
 pub use rx::*;
 pub use rx_store::*;
-pub use dispatcher::*;
\ No newline at end of file
+pub use dispatcher::*;
+pub use hook_report::*;
+pub use hook_chain::*;
+pub use scheduler::*;
+pub use interceptor::*;
+pub use rx_batch::*;
+pub use live_query::*;
+pub use command_log::*;
+pub use action_log::*;
\ No newline at end of file
@@ -1,17 +1,23 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::entity_store::Entity;
+use std::fmt;
+
+use crate::entity_store::{Entity, GeoQuery, StoreError, ValidationErrors};
+use crate::rx::{HookChain, HookReport};
 
 // {"UpdateEntities":["DemoData",[{"id":"9c682bbb-fa84-4d7f-8e4e-d40ea8cd11df","kind":"DemoData","value":42}]]}
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum RxAction {
     UpdateEntities(String, Value),
     DeleteEntities(String, Vec<String>),
     QueryIds(String, Vec<String>),
     QueryProperty(String, String, String),
+    QueryGeo(String, GeoQuery),
     Signal(Value),
+    /// Updates and deletions written in a single transaction
+    Batch(Vec<RxAction>),
 }
 
 impl RxAction {
@@ -33,16 +39,106 @@ impl RxAction {
         RxAction::QueryProperty(kind.to_string(), property_name.to_string(), expression.to_string())
     }
 
+    pub fn new_query_geo(kind: &str, query: GeoQuery) -> Self{
+        RxAction::QueryGeo(kind.to_string(), query)
+    }
+
     pub fn new_signal<P: Entity>(signal: P) -> Self{
         let value = serde_json::to_value(signal).unwrap();
         RxAction::Signal(value)
     }
+
+    pub fn new_batch(actions: Vec<RxAction>) -> Self {
+        RxAction::Batch(actions)
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum RxResponse {
     Success(),
     QueryResponse(Value),
     SignalResponse(Value),
-    Failure(String)
+    Failure(String),
+    ValidationFailure(ValidationErrors),
+    /// One response per operation of a batch
+    BatchResponse(Vec<RxResponse>),
+    /// The signal handler did not complete in time and was cancelled
+    Timeout(String),
+    /// The signal handler already runs its maximum number of times
+    Overloaded(String),
+}
+
+impl RxResponse {
+    /// A batch fails when one of its operations failed
+    pub fn is_failure(&self) -> bool {
+        match self {
+            RxResponse::Failure(_)
+            | RxResponse::ValidationFailure(_)
+            | RxResponse::Timeout(_)
+            | RxResponse::Overloaded(_) => true,
+            RxResponse::BatchResponse(responses) => responses.iter().any(RxResponse::is_failure),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RxError {
+    Validation(ValidationErrors),
+    /// Returned by a before hook
+    Rejected(String),
+    /// Entity hooks failed with the fail fast policy
+    Hooks(HookReport),
+    /// The operation is already in progress in the hook chain
+    HookCycle(HookChain),
+    HookDepthExceeded(HookChain),
+    /// Returned by a signal handler
+    Failure(String),
+    /// Kind of the signal which timed out
+    Timeout(String),
+    /// Kind of the signal over its concurrency limit
+    Overloaded(String),
+}
+
+impl fmt::Display for RxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RxError::Validation(errors) => write!(f, "{}", errors),
+            RxError::Rejected(message) => write!(f, "Rejected : {}", message),
+            RxError::Hooks(report) => write!(f, "{}", report),
+            RxError::HookCycle(chain) => write!(f, "Hook cycle detected : {}", chain),
+            RxError::HookDepthExceeded(chain) => write!(f, "Maximum hook depth exceeded : {}", chain),
+            RxError::Failure(message) => write!(f, "{}", message),
+            RxError::Timeout(kind) => write!(f, "Signal {} timed out", kind),
+            RxError::Overloaded(kind) => write!(f, "Too many concurrent {} signals", kind),
+        }
+    }
+}
+
+impl From<ValidationErrors> for RxError {
+    fn from(errors: ValidationErrors) -> Self {
+        RxError::Validation(errors)
+    }
+}
+
+impl From<StoreError> for RxError {
+    fn from(error: StoreError) -> Self {
+        match error {
+            StoreError::Validation(errors) => RxError::Validation(errors),
+            StoreError::Database(message) => RxError::Failure(message),
+        }
+    }
+}
+
+impl From<RxError> for RxResponse {
+    fn from(error: RxError) -> Self {
+        match error {
+            RxError::Validation(errors) => RxResponse::ValidationFailure(errors),
+            RxError::Rejected(message) => RxResponse::Failure(message),
+            RxError::Hooks(report) => RxResponse::Failure(report.to_string()),
+            RxError::Timeout(_) => RxResponse::Timeout(error.to_string()),
+            RxError::Overloaded(_) => RxResponse::Overloaded(error.to_string()),
+            error => RxResponse::Failure(error.to_string()),
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,117 @@
+use crate::prelude::*;
+
+/// Operations of an `RxAction::Batch`. The before hooks run while the
+/// operations are staged, then they are written in a single transaction and
+/// their other hooks are dispatched, operation by operation, once it is committed.
+pub struct RxBatch {
+    // Index of the operation being staged
+    position: usize,
+    operations: Vec<(usize, Box<dyn BatchOperation>)>,
+}
+
+impl RxBatch {
+    pub(crate) fn new() -> Self {
+        Self {
+            position: 0,
+            operations: vec![],
+        }
+    }
+
+    pub(crate) fn set_position(&mut self, position: usize) {
+        self.position = position;
+    }
+
+    pub(crate) fn add_operation<O: BatchOperation + 'static>(&mut self, operation: O) {
+        self.operations.push((self.position, Box::new(operation)));
+    }
+
+    pub(crate) fn take_operations(&mut self) -> Vec<(usize, Box<dyn BatchOperation>)> {
+        std::mem::take(&mut self.operations)
+    }
+}
+
+#[async_trait]
+pub(crate) trait BatchOperation: Send + Sync {
+    async fn write(&mut self, batch: &mut StoreBatch) -> Result<(), RxError>;
+
+    /// Entities written, once the batch is committed
+    fn get_change(&self) -> EntityChange;
+
+    async fn dispatch_hooks(self: Box<Self>, store: &RxStore) -> Result<HookReport, RxError>;
+}
+
+pub(crate) struct UpdateOperation<T> {
+    kind: String,
+    entities: Vec<T>,
+    // Only read when the kind has change hooks
+    previous_entities: Option<Vec<Option<T>>>,
+    deferred: Vec<DeferredHook>,
+}
+
+impl<T: Entity> UpdateOperation<T> {
+    pub fn new(kind: &str, entities: Vec<T>, load_previous: bool, deferred: Vec<DeferredHook>) -> Self {
+        Self {
+            kind: kind.to_string(),
+            entities,
+            previous_entities: if load_previous { Some(vec![]) } else { None },
+            deferred,
+        }
+    }
+}
+
+#[async_trait]
+impl<T: Entity> BatchOperation for UpdateOperation<T> {
+    async fn write(&mut self, batch: &mut StoreBatch) -> Result<(), RxError> {
+        let load_previous = self.previous_entities.is_some();
+        let previous_entities = batch.update_entities(&self.entities, load_previous, &self.deferred).await?;
+        if load_previous {
+            self.previous_entities = Some(previous_entities);
+        }
+        Ok(())
+    }
+
+    fn get_change(&self) -> EntityChange {
+        EntityChange::new(&self.kind, self.entities.iter().map(|entity| entity.get_id().to_string()).collect())
+    }
+
+    async fn dispatch_hooks(self: Box<Self>, store: &RxStore) -> Result<HookReport, RxError> {
+        let hooks = store.dispatch_update_hooks(self.entities, self.previous_entities);
+        store.dispatch_in_hook_chain(EntityAction::Update, &self.kind, hooks).await
+    }
+}
+
+pub(crate) struct DeleteOperation<T> {
+    kind: String,
+    ids: Vec<String>,
+    removed_entities: Vec<T>,
+    deferred: Vec<DeferredHook>,
+}
+
+impl<T: Entity> DeleteOperation<T> {
+    pub fn new(kind: &str, ids: Vec<String>, deferred: Vec<DeferredHook>) -> Self {
+        Self {
+            kind: kind.to_string(),
+            ids,
+            removed_entities: vec![],
+            deferred,
+        }
+    }
+}
+
+#[async_trait]
+impl<T: Entity> BatchOperation for DeleteOperation<T> {
+    async fn write(&mut self, batch: &mut StoreBatch) -> Result<(), RxError> {
+        let ids_ref = self.ids.iter().map(|id| id.as_str()).collect();
+        self.removed_entities = batch.remove_entities(&self.kind, &ids_ref, &self.deferred).await?;
+        Ok(())
+    }
+
+    fn get_change(&self) -> EntityChange {
+        EntityChange::new(&self.kind, self.ids.clone())
+    }
+
+    async fn dispatch_hooks(self: Box<Self>, store: &RxStore) -> Result<HookReport, RxError> {
+        let hooks = store.dispatch_delete_hooks(self.removed_entities);
+        store.dispatch_in_hook_chain(EntityAction::Delete, &self.kind, hooks).await
+    }
+}
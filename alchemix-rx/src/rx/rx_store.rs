@@ -1,11 +1,22 @@
-use std::{any::Any, sync::Arc};
+use std::{
+    any::Any,
+    collections::BTreeMap,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
 
 use async_trait::async_trait;
+use tokio::sync::broadcast;
 
 use crate::{
-    prelude::{EntitySchema, SQLiteEntityStore, SafeDataHookHandler, SafeSignalHookHandler, Entity},
-    rx::{DispatchPayload, Dispatcher, EntityAction},
-    rx::{RxAction, RxResponse},
+    prelude::{ApplyReport, CacheStats, Change, ChangeSet, ConflictResolver, EntitySchema, ReplicationPeer, StoreStats, SyncReport, GeoQuery, HnswSettings, Job, JobSettings, Predicate, VectorMetric, SQLiteEntityStore, SafeDataHookHandler, SafeSignalHookHandler, SafeStreamHookHandler, Entity},
+    rx::{DispatchPayload, Dispatcher, HookHandle, SignalStream, EntityAction, HookCause, HookChain, HookError, HookFailurePolicy, HookReport},
+    rx::{ActionLog, Divergence, DivergenceReason, ReplayFailure, ReplayHooks, ReplayReport},
+    rx::{Command, CommandCapture, CommandLog, DEFAULT_MAX_SESSIONS, DeleteOperation, EntityChange, LiveQuery, RxAction, RxBatch, RxError, RxInterceptor, RxResponse, Schedule, ScheduledRun, ScheduledSignal, UpdateOperation, CHANGE_CHANNEL_CAPACITY},
 };
 
 use serde_json::Value;
@@ -14,9 +25,9 @@ use serde_json::Value;
 pub trait RxContext: Any + Send + Sync + 'static {
     fn as_any(&self) -> &dyn Any;
 
-    async fn update_entities(&self, store: &RxStore, kind: &str, ids: Value);
+    async fn update_entities(&self, store: &RxStore, kind: &str, ids: Value) -> RxResponse;
 
-    async fn delete_entities(&self, store: &RxStore, kind: &str, ids: &Vec<&str>);
+    async fn delete_entities(&self, store: &RxStore, kind: &str, ids: &Vec<&str>) -> RxResponse;
 
     async fn get_entities(&self, store: &RxStore, kind: &str, ids: &Vec<&str>) -> RxResponse;
 
@@ -28,21 +39,63 @@ pub trait RxContext: Any + Send + Sync + 'static {
         expression: &str,
     ) -> RxResponse;
 
+    /// Generated by `#[rx_context]`, contexts written by hand have no geo index
+    async fn query_geo(&self, _store: &RxStore, _kind: &str, _query: &GeoQuery) -> RxResponse {
+        RxResponse::QueryResponse(Value::Array(vec![]))
+    }
+
     async fn signal(&self, store: &RxStore, signal: Value) -> RxResponse;
+
+    /// Generated by `#[rx_context]`, contexts written by hand can not be batched
+    async fn batch_update_entities(&self, _store: &RxStore, _batch: &mut RxBatch, kind: &str, _values: Value) -> RxResponse {
+        RxResponse::Failure(format!("Batches are not supported for {}", kind))
+    }
+
+    async fn batch_delete_entities(&self, _store: &RxStore, _batch: &mut RxBatch, kind: &str, _ids: &Vec<&str>) -> RxResponse {
+        RxResponse::Failure(format!("Batches are not supported for {}", kind))
+    }
+
+    async fn apply_changes(&self, store: &RxStore, kind: &str, changes: &[Change]) -> Result<ApplyReport, String>;
 }
 
 pub struct RxStore {
-    dispatcher: Dispatcher,
+    // Shared with the tenant handles
+    dispatcher: Arc<Dispatcher>,
     store: SQLiteEntityStore,
-    context: Box<dyn RxContext>,
+    context: Arc<dyn RxContext>,
+    tenant: Option<String>,
+    job_settings: JobSettings,
+    job_workers: Mutex<Vec<tokio::task::JoinHandle<()>>>,
+    jobs_running: Arc<AtomicBool>,
+    schedules: Vec<ScheduledSignal>,
+    scheduler_worker: Mutex<Option<tokio::task::JoinHandle<()>>>,
+    scheduler_running: Arc<AtomicBool>,
+    interceptors: Vec<Arc<dyn RxInterceptor>>,
+    changes: broadcast::Sender<EntityChange>,
+    command_log: Option<Arc<CommandLog>>,
+    action_log: Option<Arc<ActionLog>>,
 }
 
+// Longest wait of the scheduler, so it stops quickly
+const SCHEDULER_MAX_WAIT_MS: i64 = 1000;
+
 impl RxStore {
     pub fn new<T: RxContext>(context: T, path: &str) -> Self {
         Self {
-            dispatcher: Dispatcher::new(),
+            dispatcher: Arc::new(Dispatcher::new()),
             store: SQLiteEntityStore::new(path),
-            context: Box::new(context),
+            context: Arc::new(context),
+            tenant: None,
+            job_settings: JobSettings::default(),
+            job_workers: Mutex::new(vec![]),
+            jobs_running: Arc::new(AtomicBool::new(false)),
+            schedules: vec![],
+            scheduler_worker: Mutex::new(None),
+            scheduler_running: Arc::new(AtomicBool::new(false)),
+            interceptors: vec![],
+            changes: broadcast::channel(CHANGE_CHANNEL_CAPACITY).0,
+            command_log: None,
+            action_log: None,
         }
     }
 
@@ -59,33 +112,390 @@ impl RxStore {
     }
 
     pub async fn close(&self) {
+        self.stop_scheduler().await;
+        self.stop_jobs().await;
         let _ = self.store.close().await;
     }
 
-    pub fn with_entity_hooks(mut self, hooks: Vec<Box<SafeDataHookHandler>>) -> Self {
-        self.dispatcher.register_entity_hooks(hooks);
+    pub async fn backup_to(&self, path: &str) -> Result<(), String> {
+        self.store.backup_to(path).await
+    }
+
+    pub fn restore_from(&mut self, path: &str) -> Result<(), String> {
+        self.store.restore_from(path)
+    }
+
+    pub fn with_entity_hooks(self, hooks: Vec<Box<SafeDataHookHandler>>) -> Self {
+        self.register_entity_hooks(hooks);
+        self
+    }
+
+    pub fn with_signal_hooks(self, hooks: Vec<Box<SafeSignalHookHandler>>) -> Self {
+        self.register_signal_hooks(hooks);
+        self
+    }
+
+    pub fn with_stream_hooks(self, hooks: Vec<Box<SafeStreamHookHandler>>) -> Self {
+        self.register_stream_hooks(hooks);
+        self
+    }
+
+    /// Adds hooks to a running store, its tenant handles get them as well.
+    /// The returned handle removes them with `unregister_hooks`.
+    pub fn register_entity_hooks(&self, hooks: Vec<Box<SafeDataHookHandler>>) -> HookHandle {
+        self.dispatcher.register_entity_hooks(hooks)
+    }
+
+    /// A signal handler replaces the one of the same kind until it is unregistered
+    pub fn register_signal_hooks(&self, hooks: Vec<Box<SafeSignalHookHandler>>) -> HookHandle {
+        self.dispatcher.register_signal_hooks(hooks)
+    }
+
+    pub fn register_stream_hooks(&self, hooks: Vec<Box<SafeStreamHookHandler>>) -> HookHandle {
+        self.dispatcher.register_stream_hooks(hooks)
+    }
+
+    /// The operations already dispatched still run the removed hooks
+    pub fn unregister_hooks(&self, handle: HookHandle) -> bool {
+        self.dispatcher.unregister_hooks(handle)
+    }
+
+    /// The tenant handles share the hook settings of this store
+    pub fn with_hook_failure_policy(self, policy: HookFailurePolicy) -> Self {
+        self.dispatcher.set_failure_policy(policy);
+        self
+    }
+
+    /// Adds an interceptor after the ones already registered
+    pub fn with_interceptor<I: RxInterceptor + 'static>(mut self, interceptor: I) -> Self {
+        self.interceptors.push(Arc::new(interceptor));
+        self
+    }
+
+    /// Records the updates and deletions sent with `execute_session_action`,
+    /// up to `capacity` commands per session
+    pub fn with_command_log(self, capacity: usize) -> Self {
+        self.with_command_log_sessions(capacity, DEFAULT_MAX_SESSIONS)
+    }
+
+    /// Same as `with_command_log`, keeping up to `max_sessions` sessions
+    pub fn with_command_log_sessions(mut self, capacity: usize, max_sessions: usize) -> Self {
+        self.command_log = Some(Arc::new(CommandLog::new(capacity).with_max_sessions(max_sessions)));
+        self
+    }
+
+    pub fn get_command_log(&self) -> Option<&CommandLog> {
+        self.command_log.as_deref()
+    }
+
+    /// Appends every action given to `execute_action` to the log at `path`,
+    /// tenant handles log to `{path}.{tenant}`.
+    pub fn with_action_log(mut self, path: &str) -> Self {
+        self.action_log = Some(Arc::new(ActionLog::new(path)));
+        self
+    }
+
+    pub fn get_action_log(&self) -> Option<&ActionLog> {
+        self.action_log.as_deref()
+    }
+
+    pub fn with_job_settings(mut self, settings: JobSettings) -> Self {
+        self.job_settings = settings;
+        self
+    }
+
+    /// Maximum number of nested store operations triggered by entity hooks
+    pub fn with_max_hook_depth(self, depth: usize) -> Self {
+        self.dispatcher.set_max_hook_depth(depth);
+        self
+    }
+
+    pub fn with_entity_cache(mut self, capacity: usize) -> Self {
+        self.store = self.store.with_cache(capacity);
         self
     }
 
-    pub fn with_signal_hooks(mut self, hooks: Vec<Box<SafeSignalHookHandler>>) -> Self {
-        self.dispatcher.register_signal_hooks(hooks);
+    pub fn with_vector_index(mut self, settings: HnswSettings) -> Self {
+        self.store = self.store.with_vector_index(settings);
         self
     }
 
-    pub async fn save_entities<'e, T: Entity>(&'e self, entities: &Vec<T>) {
+    /// Opens a handle on the database of `tenant`. The handle shares the hooks
+    /// and context of this store, and every read and write goes to the tenant.
+    pub async fn tenant(&self, tenant: &str) -> Result<RxStore, String> {
+        if let Some(tenant_id) = &self.tenant {
+            return Err(format!("Store is already scoped to a tenant : {}", tenant_id));
+        }
+        let mut rx_store = RxStore {
+            dispatcher: self.dispatcher.clone(),
+            store: self.store.for_tenant(tenant)?,
+            context: self.context.clone(),
+            tenant: Some(tenant.to_string()),
+            job_settings: self.job_settings,
+            job_workers: Mutex::new(vec![]),
+            jobs_running: Arc::new(AtomicBool::new(false)),
+            schedules: self.schedules.clone(),
+            scheduler_worker: Mutex::new(None),
+            scheduler_running: Arc::new(AtomicBool::new(false)),
+            interceptors: self.interceptors.clone(),
+            changes: broadcast::channel(CHANGE_CHANNEL_CAPACITY).0,
+            command_log: self
+                .command_log
+                .as_ref()
+                .map(|command_log| {
+                    Arc::new(
+                        CommandLog::new(command_log.get_capacity()).with_max_sessions(command_log.get_max_sessions()),
+                    )
+                }),
+            action_log: self
+                .action_log
+                .as_ref()
+                .map(|action_log| Arc::new(ActionLog::new(&format!("{}.{}", action_log.get_path(), tenant)))),
+        };
+        rx_store
+            .store
+            .open()
+            .await
+            .map_err(|error| format!("Unable to open tenant {} : {}", tenant, error))?;
+        Ok(rx_store)
+    }
+
+    pub fn get_tenant(&self) -> Option<&str> {
+        self.tenant.as_deref()
+    }
+
+    pub fn tenants(&self) -> Vec<String> {
+        self.store.tenants()
+    }
+
+    /// Deletes all the data of `tenant`, its handles must be closed beforehand.
+    pub fn delete_tenant(&self, tenant: &str) -> Result<(), String> {
+        self.store.delete_tenant(tenant)
+    }
+
+    pub fn with_replication(mut self, node_id: &str) -> Self {
+        self.store = self.store.with_replication(node_id);
+        self
+    }
+
+    pub fn with_conflict_resolver<R: ConflictResolver + 'static>(mut self, resolver: R) -> Self {
+        self.store = self.store.with_conflict_resolver(resolver);
+        self
+    }
+
+    pub async fn stats(&self) -> Result<StoreStats, String> {
+        self.store.stats().await
+    }
+
+    pub fn cache_stats(&self) -> Vec<CacheStats> {
+        self.store.cache_stats()
+    }
+
+    /// Returns the failures of the entity hooks, with `HookFailurePolicy::FailFast`
+    /// a failure is returned as `RxError::Hooks`. The change hooks run before
+    /// the update hooks, so a failing change hook skips them. The entities are
+    /// already written when the error is returned.
+    pub async fn save_entities<'e, T: Entity>(&'e self, entities: &Vec<T>) -> Result<HookReport, RxError> {
+        match entities.first() {
+            Some(entity) => {
+                let chain = self.enter_hook_chain(EntityAction::Update, entity.get_kind())?;
+                chain.scope(self.write_entities(entities)).await
+            }
+            None => self.write_entities(entities).await,
+        }
+    }
+
+    async fn write_entities<T: Entity>(&self, entities: &[T]) -> Result<HookReport, RxError> {
+        let entities = self.run_before_update(entities.to_vec()).await?;
+        // Previous values are only read when a change hook needs them
+        let kind = entities.first().map(|entity| entity.get_kind().to_string()).unwrap_or_default();
+        let load_previous = self.dispatcher.has_entity_hooks(EntityAction::Change, &kind);
+        let deferred = self
+            .dispatcher
+            .get_deferred_hooks(&[EntityAction::Change, EntityAction::Update], &kind);
+        let capture = CommandCapture::current();
+        let previous_entities = if load_previous || capture.is_some() || !deferred.is_empty() {
+            Some(self.store.update_entities_with_previous(&entities, &deferred).await?)
+        } else {
+            self.store.update_entities(&entities).await?;
+            None
+        };
+        if let (Some(capture), Some(previous_entities)) = (&capture, &previous_entities) {
+            capture.record_update(&kind, &entities, previous_entities);
+        }
+        let previous_entities = previous_entities.filter(|_| load_previous);
+        self.notify_change(&kind, entities.iter().map(|entity| entity.get_id().to_string()).collect());
+        self.dispatch_update_hooks(entities, previous_entities).await
+    }
+
+    async fn run_before_update<T: Entity>(&self, entities: Vec<T>) -> Result<Vec<T>, RxError> {
         let context = Arc::new(DispatchPayload::new(self));
-        self.store.update_entities(entities).await;
         self.dispatcher
-            .dispatch_entity_hook(context, EntityAction::Update, entities.clone())
-            .await;
+            .dispatch_before_hook(context, EntityAction::BeforeUpdate, entities)
+            .await
+            .map_err(RxError::Rejected)
     }
 
-    pub async fn delete_entities<T: Entity>(&self, kind: EntitySchema<T>, ids: &Vec<&str>) {
-        let removed_entities: Vec<T> = self.store.remove_entities(&kind.name, ids).await;
+    pub(crate) async fn dispatch_update_hooks<T: Entity>(
+        &self,
+        entities: Vec<T>,
+        previous_entities: Option<Vec<Option<T>>>,
+    ) -> Result<HookReport, RxError> {
         let context = Arc::new(DispatchPayload::new(self));
-        self.dispatcher
+        let mut report = HookReport::default();
+        if let Some(previous_entities) = previous_entities {
+            let changes = previous_entities.into_iter().zip(entities.iter().cloned()).collect();
+            report = self
+                .check_hook_report(self.dispatcher.dispatch_change_hook(context.clone(), changes).await)?;
+        }
+        report.merge(self.check_hook_report(
+            self.dispatcher
+                .dispatch_entity_hook(context, EntityAction::Update, entities)
+                .await,
+        )?);
+        Ok(report)
+    }
+
+    /// The before delete hooks get the stored entities, only the ones they
+    /// return are removed.
+    pub async fn delete_entities<T: Entity>(&self, kind: EntitySchema<T>, ids: &Vec<&str>) -> Result<HookReport, RxError> {
+        let chain = self.enter_hook_chain(EntityAction::Delete, kind.name)?;
+        chain.scope(self.remove_entities(kind, ids)).await
+    }
+
+    async fn remove_entities<T: Entity>(&self, kind: EntitySchema<T>, ids: &Vec<&str>) -> Result<HookReport, RxError> {
+        let ids = self.run_before_delete(&kind, ids).await?;
+        let ids_ref = ids.iter().map(|id| id.as_str()).collect();
+        let deferred = self.dispatcher.get_deferred_hooks(&[EntityAction::Delete], kind.name);
+        let removed_entities: Vec<T> = self
+            .store
+            .remove_entities_with_hooks(kind.name, &ids_ref, &deferred)
+            .await
+            .map_err(RxError::Failure)?;
+        if let Some(capture) = CommandCapture::current() {
+            capture.record_delete(kind.name, &removed_entities);
+        }
+        self.notify_change(kind.name, ids);
+        self.dispatch_delete_hooks(removed_entities).await
+    }
+
+    // Ids kept by the before delete hooks
+    async fn run_before_delete<T: Entity>(&self, kind: &EntitySchema<T>, ids: &[&str]) -> Result<Vec<String>, RxError> {
+        let ids: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+        if !self.dispatcher.has_entity_hooks(EntityAction::BeforeDelete, kind.name) {
+            return Ok(ids);
+        }
+        let context = Arc::new(DispatchPayload::new(self));
+        let ids_ref = ids.iter().map(|id| id.as_str()).collect();
+        let stored_entities: Vec<T> = self.store.get_entities_of_kind(kind.name, &ids_ref).await;
+        let entities = self
+            .dispatcher
+            .dispatch_before_hook(context, EntityAction::BeforeDelete, stored_entities)
+            .await
+            .map_err(RxError::Rejected)?;
+        Ok(entities.iter().map(|entity| entity.get_id().to_string()).collect())
+    }
+
+    pub(crate) async fn dispatch_delete_hooks<T: Entity>(&self, removed_entities: Vec<T>) -> Result<HookReport, RxError> {
+        let context = Arc::new(DispatchPayload::new(self));
+        let report = self
+            .dispatcher
             .dispatch_entity_hook(context, EntityAction::Delete, removed_entities)
             .await;
+        self.check_hook_report(report)
+    }
+
+    /// Stages the update of an `RxAction::Batch`, the before update hooks run now
+    pub async fn batch_update_entities<T: Entity>(&self, batch: &mut RxBatch, entities: Vec<T>) -> Result<(), RxError> {
+        let Some(kind) = entities.first().map(|entity| entity.get_kind().to_string()) else {
+            return Ok(());
+        };
+        let chain = self.enter_hook_chain(EntityAction::Update, &kind)?;
+        let entities = chain.scope(self.run_before_update(entities)).await?;
+        let load_previous = self.dispatcher.has_entity_hooks(EntityAction::Change, &kind);
+        let deferred = self
+            .dispatcher
+            .get_deferred_hooks(&[EntityAction::Change, EntityAction::Update], &kind);
+        batch.add_operation(UpdateOperation::new(&kind, entities, load_previous, deferred));
+        Ok(())
+    }
+
+    /// Stages the deletion of an `RxAction::Batch`, the before delete hooks run now
+    pub async fn batch_delete_entities<T: Entity>(
+        &self,
+        batch: &mut RxBatch,
+        kind: EntitySchema<T>,
+        ids: &Vec<&str>,
+    ) -> Result<(), RxError> {
+        let chain = self.enter_hook_chain(EntityAction::Delete, kind.name)?;
+        let ids = chain.scope(self.run_before_delete(&kind, ids)).await?;
+        let deferred = self.dispatcher.get_deferred_hooks(&[EntityAction::Delete], kind.name);
+        batch.add_operation(DeleteOperation::<T>::new(kind.name, ids, deferred));
+        Ok(())
+    }
+
+    pub(crate) async fn dispatch_in_hook_chain<F>(&self, action: EntityAction, kind: &str, hooks: F) -> Result<HookReport, RxError>
+    where
+        F: Future<Output = Result<HookReport, RxError>>,
+    {
+        let chain = self.enter_hook_chain(action, kind)?;
+        chain.scope(hooks).await
+    }
+
+    fn notify_change(&self, kind: &str, ids: Vec<String>) {
+        // Fails when nobody is subscribed
+        self.changes.send(EntityChange::new(kind, ids)).ok();
+    }
+
+    /// Changes committed by this store, sent after each write
+    pub fn subscribe_changes(&self) -> broadcast::Receiver<EntityChange> {
+        self.changes.subscribe()
+    }
+
+    /// Runs the property query now, then again after every change of `kind`,
+    /// the differences between results are returned by `LiveQuery::next`.
+    pub async fn subscribe_query(&self, kind: &str, property_name: &str, expression: &str) -> LiveQuery<'_> {
+        let changes = self.changes.subscribe();
+        let (query_kind, property_name, expression) = (kind.to_string(), property_name.to_string(), expression.to_string());
+        let run_query = move || {
+            let (kind, property_name, expression) = (query_kind.clone(), property_name.clone(), expression.clone());
+            let future: Pin<Box<dyn Future<Output = Value> + Send + '_>> = Box::pin(async move {
+                match self.context.query_property(self, &kind, &property_name, &expression).await {
+                    RxResponse::QueryResponse(values) => values,
+                    _ => Value::Array(vec![]),
+                }
+            });
+            future
+        };
+        LiveQuery::new(kind, changes, Box::new(run_query)).await
+    }
+
+    /// Operations in progress in the current task, hooks can log it to find
+    /// out which write triggered them.
+    pub fn get_hook_chain(&self) -> HookChain {
+        HookChain::current()
+    }
+
+    fn enter_hook_chain(&self, action: EntityAction, kind: &str) -> Result<HookChain, RxError> {
+        let current_chain = HookChain::current();
+        let cause = HookCause::new(action.get_text(), kind);
+        let is_cycle = current_chain.contains(&cause);
+        let chain = current_chain.with_cause(cause);
+        if is_cycle {
+            Err(RxError::HookCycle(chain))
+        } else if chain.depth() > self.dispatcher.get_max_hook_depth() {
+            Err(RxError::HookDepthExceeded(chain))
+        } else {
+            Ok(chain)
+        }
+    }
+
+    fn check_hook_report(&self, report: HookReport) -> Result<HookReport, RxError> {
+        if !report.is_success() && self.dispatcher.get_failure_policy() == HookFailurePolicy::FailFast {
+            Err(RxError::Hooks(report))
+        } else {
+            Ok(report)
+        }
     }
 
     pub async fn get_entities<T: Entity>(&self, kind: EntitySchema<T>, ids: &Vec<&str>) -> Vec<T> {
@@ -103,7 +513,345 @@ impl RxStore {
             .await
     }
 
+    pub async fn query_predicate<T: Entity>(&self, kind: EntitySchema<T>, predicate: &Predicate) -> Vec<T> {
+        self.store.query_predicate(&kind.name, predicate).await
+    }
+
+    pub async fn query_geo<T: Entity>(&self, kind: EntitySchema<T>, query: &GeoQuery) -> Vec<T> {
+        self.store.query_geo(&kind.name, query).await
+    }
+
+    pub async fn nearest<T: Entity>(
+        &self,
+        kind: EntitySchema<T>,
+        query: &[f32],
+        k: usize,
+        metric: VectorMetric,
+    ) -> Vec<(T, f32)> {
+        self.store.nearest(&kind.name, query, k, metric).await
+    }
+
+    pub async fn changes_since(&self, since: i64, limit: usize) -> Result<ChangeSet, String> {
+        self.store.changes_since(since, limit).await
+    }
+
+    /// Applies replicated changes of one kind, the entities written or removed
+    /// are dispatched to the entity hooks.
+    pub async fn apply_changes<T: Entity>(&self, changes: &[Change]) -> Result<ApplyReport, String> {
+        let kind = changes.first().map(|change| change.kind.as_str()).unwrap_or_default();
+        let update_hooks = self.dispatcher.get_deferred_hooks(&[EntityAction::Update], kind);
+        let delete_hooks = self.dispatcher.get_deferred_hooks(&[EntityAction::Delete], kind);
+        let applied = self.store.apply_changes::<T>(changes, &update_hooks, &delete_hooks).await?;
+        if applied.report.applied > 0 {
+            let ids = applied.updated.iter().chain(applied.deleted.iter()).map(|entity| entity.get_id().to_string());
+            self.notify_change(kind, ids.collect());
+        }
+        let context = Arc::new(DispatchPayload::new(self));
+        if !applied.updated.is_empty() {
+            let report = self
+                .dispatcher
+                .dispatch_entity_hook(context.clone(), EntityAction::Update, applied.updated)
+                .await;
+            self.check_hook_report(report).map_err(|error| error.to_string())?;
+        }
+        if !applied.deleted.is_empty() {
+            let report = self
+                .dispatcher
+                .dispatch_entity_hook(context, EntityAction::Delete, applied.deleted)
+                .await;
+            self.check_hook_report(report).map_err(|error| error.to_string())?;
+        }
+        Ok(applied.report)
+    }
+
+    pub async fn apply_change_set(&self, change_set: ChangeSet) -> Result<ApplyReport, String> {
+        let mut report = ApplyReport::default();
+        for changes in change_set.changes.chunk_by(|a, b| a.kind == b.kind) {
+            let kind_report = self
+                .context
+                .apply_changes(self, &changes[0].kind, changes)
+                .await?;
+            report.merge(&kind_report);
+        }
+        Ok(report)
+    }
+
+    /// Pulls the changes of `peer` then pushes the local ones, by batches of
+    /// `batch_size`. Cursors are kept per peer so only new changes are exchanged.
+    pub async fn sync_with(&self, peer: &dyn ReplicationPeer, batch_size: usize) -> Result<SyncReport, String> {
+        let node_id = self.get_node_id().await?;
+        let peer_id = peer.get_node_id().await?;
+        let (mut pulled_seq, mut pushed_seq) = self.store.get_peer_cursor(&peer_id).await?;
+        let mut report = SyncReport::default();
+        // Revisions written by a node are known there, or replaced by newer ones,
+        // so they are not sent back to it
+        loop {
+            let mut change_set = peer.pull(pulled_seq, batch_size).await?;
+            if change_set.changes.is_empty() {
+                break;
+            }
+            pulled_seq = change_set.last_seq;
+            change_set.changes.retain(|change| change.origin != node_id);
+            report.pulled.merge(&self.apply_change_set(change_set).await?);
+            self.store.set_peer_cursor(&peer_id, pulled_seq, pushed_seq).await?;
+        }
+        loop {
+            let mut change_set = self.changes_since(pushed_seq, batch_size).await?;
+            if change_set.changes.is_empty() {
+                break;
+            }
+            pushed_seq = change_set.last_seq;
+            change_set.changes.retain(|change| change.origin != peer_id);
+            if !change_set.changes.is_empty() {
+                report.pushed.merge(&peer.push(change_set).await?);
+            }
+            self.store.set_peer_cursor(&peer_id, pulled_seq, pushed_seq).await?;
+        }
+        Ok(report)
+    }
+
+    /// Starts the workers running the deferred hooks in the background,
+    /// jobs interrupted by a previous shutdown are run again.
+    pub async fn start_jobs(&self) -> Result<(), String> {
+        if self.jobs_running.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+        self.store.reset_running_jobs().await?;
+        let worker_store = self.get_worker_handle();
+        let mut job_workers = self.job_workers.lock().unwrap();
+        for _ in 0..self.job_settings.workers.max(1) {
+            let worker_store = worker_store.clone();
+            job_workers.push(tokio::spawn(async move {
+                while worker_store.jobs_running.load(Ordering::SeqCst) {
+                    if !worker_store.run_next_job().await {
+                        tokio::time::sleep(worker_store.job_settings.poll_interval).await;
+                    }
+                }
+            }));
+        }
+        Ok(())
+    }
+
+    /// Handle of the background workers, it shares the pool, the caches and
+    /// the running flags of this one
+    fn get_worker_handle(&self) -> Arc<RxStore> {
+        Arc::new(RxStore {
+            dispatcher: self.dispatcher.clone(),
+            store: self.store.clone(),
+            context: self.context.clone(),
+            tenant: self.tenant.clone(),
+            job_settings: self.job_settings,
+            job_workers: Mutex::new(vec![]),
+            jobs_running: self.jobs_running.clone(),
+            schedules: self.schedules.clone(),
+            scheduler_worker: Mutex::new(None),
+            scheduler_running: self.scheduler_running.clone(),
+            interceptors: self.interceptors.clone(),
+            changes: self.changes.clone(),
+            command_log: self.command_log.clone(),
+            action_log: self.action_log.clone(),
+        })
+    }
+
+    /// Waits for the running jobs then stops the workers
+    pub async fn stop_jobs(&self) {
+        self.jobs_running.store(false, Ordering::SeqCst);
+        let job_workers: Vec<_> = self.job_workers.lock().unwrap().drain(..).collect();
+        for job_worker in job_workers {
+            let _ = job_worker.await;
+        }
+    }
+
+    /// Runs the jobs due now in the current task, returns the number of jobs run.
+    pub async fn run_pending_jobs(&self) -> usize {
+        let mut count = 0;
+        while self.run_next_job().await {
+            count += 1;
+        }
+        count
+    }
+
+    async fn run_next_job(&self) -> bool {
+        let job = match self.store.claim_job().await {
+            Ok(Some(job)) => job,
+            Ok(None) => return false,
+            Err(error) => {
+                println!("Unable to claim job : {}", error);
+                return false;
+            }
+        };
+        let context = Arc::new(DispatchPayload::new(self));
+        let result = match self.dispatcher.get_data_hook(&job.action, &job.kind, &job.hook) {
+            Some(handler) => handler.handle_job(context, &job.payload).await,
+            None => Err(HookError::from(format!("Unknown hook {}", job.hook))),
+        };
+        let res = match result {
+            Ok(()) => self.store.complete_job(job.id).await,
+            Err(error) if job.attempts + 1 >= self.job_settings.max_attempts => {
+                println!("Job {} ({}) failed, moved to the dead jobs : {}", job.id, job.hook, error);
+                self.store.dead_letter_job(job.id, &error.message).await
+            }
+            Err(error) => {
+                let delay = self.job_settings.get_retry_delay(job.attempts + 1);
+                self.store.retry_job(job.id, &error.message, delay).await
+            }
+        };
+        if let Err(error) = res {
+            println!("Unable to update job {} : {}", job.id, error);
+        }
+        true
+    }
+
+    pub async fn get_jobs(&self) -> Result<Vec<Job>, String> {
+        self.store.get_jobs().await
+    }
+
+    pub async fn get_dead_jobs(&self) -> Result<Vec<Job>, String> {
+        self.store.get_dead_jobs().await
+    }
+
+    pub async fn requeue_dead_job(&self, id: i64) -> Result<(), String> {
+        self.store.requeue_dead_job(id).await
+    }
+
+    /// Sends `signal` on `schedule` once the scheduler is started. Runs missed
+    /// while the scheduler was stopped are caught up with a single run.
+    pub fn with_scheduled_signal<T: Entity>(mut self, name: &str, schedule: Schedule, signal: T) -> Result<Self, String> {
+        if let Schedule::Interval(interval) = &schedule {
+            Schedule::every(*interval)?;
+        }
+        let signal = serde_json::to_value(signal)
+            .map_err(|error| format!("Invalid signal for schedule {} : {}", name, error))?;
+        self.schedules.push(ScheduledSignal {
+            name: name.to_string(),
+            schedule,
+            signal,
+        });
+        Ok(self)
+    }
+
+    pub async fn start_scheduler(&self) -> Result<(), String> {
+        if self.scheduler_running.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+        let worker_store = self.get_worker_handle();
+        let scheduler_worker = tokio::spawn(async move {
+            while worker_store.scheduler_running.load(Ordering::SeqCst) {
+                let wait = match worker_store.run_schedules().await {
+                    Ok((_, Some(next_run))) => next_run - SQLiteEntityStore::now_millis(),
+                    Ok((_, None)) => SCHEDULER_MAX_WAIT_MS,
+                    Err(error) => {
+                        println!("Scheduler error : {}", error);
+                        SCHEDULER_MAX_WAIT_MS
+                    }
+                };
+                let wait = wait.clamp(1, SCHEDULER_MAX_WAIT_MS) as u64;
+                tokio::time::sleep(std::time::Duration::from_millis(wait)).await;
+            }
+        });
+        *self.scheduler_worker.lock().unwrap() = Some(scheduler_worker);
+        Ok(())
+    }
+
+    pub async fn stop_scheduler(&self) {
+        self.scheduler_running.store(false, Ordering::SeqCst);
+        let scheduler_worker = self.scheduler_worker.lock().unwrap().take();
+        if let Some(scheduler_worker) = scheduler_worker {
+            let _ = scheduler_worker.await;
+        }
+    }
+
+    /// Sends the scheduled signals which are due, in the current task.
+    pub async fn run_due_schedules(&self) -> Result<Vec<ScheduledRun>, String> {
+        self.run_schedules().await.map(|(runs, _)| runs)
+    }
+
+    /// Returns the runs done and the earliest next run
+    async fn run_schedules(&self) -> Result<(Vec<ScheduledRun>, Option<i64>), String> {
+        let mut runs = vec![];
+        let mut earliest_run: Option<i64> = None;
+        for scheduled in &self.schedules {
+            let now = SQLiteEntityStore::now_millis();
+            let next_run = match self.store.get_schedule_next_run(&scheduled.name).await? {
+                Some(next_run) if next_run <= now => {
+                    // The next run is saved first, a failing signal is not run again.
+                    // A schedule without further run is removed so it does not run again.
+                    let following_run = scheduled.schedule.next_after(now);
+                    match following_run {
+                        Some(following_run) => {
+                            self.store.set_schedule_next_run(&scheduled.name, following_run, Some(now)).await?
+                        }
+                        None => self.store.remove_schedule(&scheduled.name).await?,
+                    }
+                    runs.push(self.run_scheduled_signal(scheduled, next_run).await);
+                    following_run
+                }
+                Some(next_run) => Some(next_run),
+                None => {
+                    let next_run = scheduled.schedule.next_after(now);
+                    if let Some(next_run) = next_run {
+                        self.store.set_schedule_next_run(&scheduled.name, next_run, None).await?;
+                    }
+                    next_run
+                }
+            };
+            earliest_run = earliest_run.into_iter().chain(next_run).min();
+        }
+        Ok((runs, earliest_run))
+    }
+
+    async fn run_scheduled_signal(&self, scheduled: &ScheduledSignal, scheduled_at: i64) -> ScheduledRun {
+        let started_at = SQLiteEntityStore::now_millis();
+        let (success, message) = match self.context.signal(self, scheduled.signal.clone()).await {
+            RxResponse::Failure(message) | RxResponse::Timeout(message) | RxResponse::Overloaded(message) => {
+                (false, message)
+            }
+            response => (true, serde_json::to_string(&response).unwrap_or_default()),
+        };
+        let run = ScheduledRun::new(
+            scheduled.name.clone(),
+            scheduled_at,
+            started_at,
+            SQLiteEntityStore::now_millis(),
+            success,
+            message,
+        );
+        if let Err(error) = self.save_entities(&vec![run.clone()]).await {
+            println!("Unable to record the run of {} : {}", scheduled.name, error);
+        }
+        run
+    }
+
+    /// Recorded runs of a schedule, oldest first
+    pub async fn get_scheduled_runs(&self, name: &str) -> Vec<ScheduledRun> {
+        let predicate = ScheduledRun::fields().schedule.eq(name);
+        let mut runs: Vec<ScheduledRun> = self.store.query_predicate("ScheduledRun", &predicate).await;
+        runs.sort_by_key(|run| run.started_at);
+        runs
+    }
+
     pub async fn signal<T: Entity, R: Entity>(&self, signal_entity: T) -> Result<R, String> {
+        if self.interceptors.is_empty() {
+            return self.dispatch_signal(signal_entity).await.map_err(|error| error.to_string());
+        }
+        let response = self
+            .intercept(RxAction::new_signal(signal_entity), |action| async move {
+                match Self::get_signal_entity::<T>(action) {
+                    Ok(signal_entity) => match self.dispatch_signal::<T, R>(signal_entity).await {
+                        Ok(value) => RxResponse::SignalResponse(serde_json::to_value(value).unwrap()),
+                        Err(error) => error.into(),
+                    },
+                    Err(message) => RxResponse::Failure(message),
+                }
+            })
+            .await;
+        match response {
+            RxResponse::SignalResponse(value) => serde_json::from_value(value).map_err(|error| error.to_string()),
+            response => Err(Self::get_signal_error(response)),
+        }
+    }
+
+    async fn dispatch_signal<T: Entity, R: Entity>(&self, signal_entity: T) -> Result<R, RxError> {
         let context = Arc::new(DispatchPayload::new(self));
         self.dispatcher
             .dispatch_signal_hook(context, signal_entity)
@@ -111,23 +859,281 @@ impl RxStore {
     }
 
     pub async fn signal_action<T: Entity>(&self, signal_entity: T) -> Result<Value, String> {
+        match self.signal_response(signal_entity).await {
+            RxResponse::SignalResponse(value) => Ok(value),
+            response => Err(Self::get_signal_error(response)),
+        }
+    }
+
+    /// Same as `signal_action`, a signal timing out or over its concurrency
+    /// limit gets an `RxResponse::Timeout` or `RxResponse::Overloaded`.
+    pub async fn signal_response<T: Entity>(&self, signal_entity: T) -> RxResponse {
+        if self.interceptors.is_empty() {
+            return self.dispatch_signal_action(signal_entity).await;
+        }
+        self.intercept(RxAction::new_signal(signal_entity), |action| async move {
+            match Self::get_signal_entity::<T>(action) {
+                Ok(signal_entity) => self.dispatch_signal_action(signal_entity).await,
+                Err(message) => RxResponse::Failure(message),
+            }
+        })
+        .await
+    }
+
+    async fn dispatch_signal_action<T: Entity>(&self, signal_entity: T) -> RxResponse {
         let context = Arc::new(DispatchPayload::new(self));
-        self.dispatcher
-            .dispatch_signal_action(context, signal_entity)
-            .await
+        match self.dispatcher.dispatch_signal_action(context, signal_entity).await {
+            Ok(value) => RxResponse::SignalResponse(value),
+            Err(error) => error.into(),
+        }
+    }
+
+    fn get_signal_error(response: RxResponse) -> String {
+        match response {
+            RxResponse::Failure(message) | RxResponse::Timeout(message) | RxResponse::Overloaded(message) => message,
+            response => format!("Unexpected signal response {:?}", response),
+        }
+    }
+
+    /// Sends a signal handled by a stream handler, its items are returned as they
+    /// are produced. The `after` of the interceptors gets `RxResponse::Success`
+    /// once the stream is started, any other response returned by an
+    /// interceptor is the error.
+    pub async fn signal_stream<T: Entity>(&self, signal_entity: T) -> Result<SignalStream<'_>, RxResponse> {
+        let signal = serde_json::to_value(signal_entity)
+            .map_err(|error| RxResponse::Failure(format!("Invalid signal : {}", error)))?;
+        self.signal_stream_value(signal).await
     }
 
+    pub async fn signal_stream_value(&self, signal: Value) -> Result<SignalStream<'_>, RxResponse> {
+        let mut stream = None;
+        let response = self
+            .intercept(RxAction::Signal(signal), |action| {
+                let stream = &mut stream;
+                async move {
+                    let RxAction::Signal(signal) = action else {
+                        return RxResponse::Failure(format!("Expected a signal, got {:?}", action));
+                    };
+                    let context = Arc::new(DispatchPayload::new(self));
+                    match self.dispatcher.dispatch_stream_hook(context, signal) {
+                        Ok(signal_stream) => {
+                            *stream = Some(signal_stream);
+                            RxResponse::Success()
+                        }
+                        Err(error) => error.into(),
+                    }
+                }
+            })
+            .await;
+        match (response, stream) {
+            (RxResponse::Success(), Some(stream)) => Ok(stream),
+            (RxResponse::Success(), None) => Err(RxResponse::Failure("Stream was not started".to_string())),
+            (response, _) => Err(response),
+        }
+    }
+
+    // Interceptors may have replaced the signal
+    fn get_signal_entity<T: Entity>(action: RxAction) -> Result<T, String> {
+        match action {
+            RxAction::Signal(value) => serde_json::from_value(value).map_err(|error| format!("Invalid signal : {}", error)),
+            action => Err(format!("Expected a signal, got {:?}", action)),
+        }
+    }
+
+    /// With an action log, the action is not executed when it cannot be logged
     pub async fn execute_action(&self, action: RxAction) -> RxResponse {
+        if let Some(action_log) = &self.action_log {
+            if let Err(error) = action_log.append(&action).await {
+                println!("{}", error);
+                return RxResponse::Failure(error);
+            }
+        }
+        self.run_intercepted_action(action).await
+    }
+
+    async fn run_intercepted_action(&self, action: RxAction) -> RxResponse {
+        if self.interceptors.is_empty() {
+            return self.run_action(action).await;
+        }
+        self.intercept(action, |action| self.run_action(action)).await
+    }
+
+    /// Executes the actions of the log at `log_path` on `target`, usually a
+    /// fresh store, one after the other. The entities of `target` are then
+    /// compared with the ones of this store. Replayed actions are not added to
+    /// the log of `target`.
+    /// With `ReplayHooks::Enabled` the entity hooks run as for new actions, the
+    /// jobs of the deferred hooks are queued and run by the job workers of
+    /// `target`. With `ReplayHooks::Disabled` no entity hook runs, including the
+    /// before, change and deferred hooks, and no job is queued.
+    pub async fn replay(&self, log_path: &str, target: &RxStore, hooks: ReplayHooks) -> Result<ReplayReport, String> {
+        let logged_actions = ActionLog::read(log_path)?;
+        let mut report = ReplayReport::default();
+        for (position, logged_action) in logged_actions.into_iter().enumerate() {
+            let replayed_action = target.run_intercepted_action(logged_action.action);
+            let response = match hooks {
+                ReplayHooks::Enabled => replayed_action.await,
+                ReplayHooks::Disabled => Dispatcher::without_entity_hooks(replayed_action).await,
+            };
+            if response.is_failure() {
+                report.failures.push(ReplayFailure {
+                    position,
+                    timestamp: logged_action.timestamp,
+                    response,
+                });
+            }
+            report.actions += 1;
+        }
+        report.divergences = self.diff_entities(target).await?;
+        Ok(report)
+    }
+
+    /// Entities missing, added or different in `other`, compared by their
+    /// encoded data
+    pub async fn diff_entities(&self, other: &RxStore) -> Result<Vec<Divergence>, String> {
+        let mut other_rows: BTreeMap<(String, String), Vec<u8>> = other
+            .store
+            .get_entity_rows()
+            .await?
+            .into_iter()
+            .map(|(kind, id, data)| ((kind, id), data))
+            .collect();
+        let mut divergences = vec![];
+        for (kind, id, data) in self.store.get_entity_rows().await? {
+            let reason = match other_rows.remove(&(kind.clone(), id.clone())) {
+                None => Some(DivergenceReason::Missing),
+                Some(other_data) if other_data != data => Some(DivergenceReason::Different),
+                Some(_) => None,
+            };
+            if let Some(reason) = reason {
+                divergences.push(Divergence { kind, id, reason });
+            }
+        }
+        divergences.extend(other_rows.into_keys().map(|(kind, id)| Divergence {
+            kind,
+            id,
+            reason: DivergenceReason::Unexpected,
+        }));
+        Ok(divergences)
+    }
+
+    /// Same as `execute_action`, the updates and deletions are recorded in the
+    /// command log of `session` so they can be undone.
+    pub async fn execute_session_action(&self, session: &str, action: RxAction) -> RxResponse {
+        let Some(command_log) = &self.command_log else {
+            return self.execute_action(action).await;
+        };
+        if !matches!(action, RxAction::UpdateEntities(..) | RxAction::DeleteEntities(..)) {
+            return self.execute_action(action).await;
+        }
+        let (response, command) = self.run_command(action).await;
+        // Written values are recorded even when a hook failed afterwards
+        if let Some(command) = command {
+            command_log.record(session, command);
+        }
+        response
+    }
+
+    /// Reverts the last `count` commands of `session`, the entity hooks run for
+    /// the restored values. Returns the number of commands undone, fewer when
+    /// the log is shorter. The previous values are restored even when another
+    /// session changed the entities since.
+    pub async fn undo(&self, session: &str, count: usize) -> Result<usize, RxError> {
+        let command_log = self.get_enabled_command_log()?;
+        for undone in 0..count {
+            let Some(command) = command_log.pop_undo(session) else {
+                return Ok(undone);
+            };
+            let response = self.execute_action(command.inverse.clone()).await;
+            if let Some(message) = Self::get_command_error(&response) {
+                command_log.push_undo(session, command);
+                return Err(RxError::Failure(format!("Unable to undo : {}", message)));
+            }
+            command_log.push_redo(session, command);
+        }
+        Ok(count)
+    }
+
+    /// Applies again the last `count` commands undone in `session`, a new
+    /// command of the session drops them.
+    pub async fn redo(&self, session: &str, count: usize) -> Result<usize, RxError> {
+        let command_log = self.get_enabled_command_log()?;
+        for redone in 0..count {
+            let Some(command) = command_log.pop_redo(session) else {
+                return Ok(redone);
+            };
+            let (response, redone_command) = self.run_command(command.action.clone()).await;
+            let written = redone_command.is_some();
+            if let Some(redone_command) = redone_command {
+                command_log.push_undo(session, redone_command);
+            }
+            if let Some(message) = Self::get_command_error(&response) {
+                if !written {
+                    command_log.push_redo(session, command);
+                }
+                return Err(RxError::Failure(format!("Unable to redo : {}", message)));
+            }
+        }
+        Ok(count)
+    }
+
+    fn get_enabled_command_log(&self) -> Result<&CommandLog, RxError> {
+        self.command_log
+            .as_deref()
+            .ok_or(RxError::Failure("Command log is not enabled".to_string()))
+    }
+
+    // The inverse is captured from the previous values loaded by the write,
+    // there is no command when nothing was written
+    async fn run_command(&self, action: RxAction) -> (RxResponse, Option<Command>) {
+        let capture = CommandCapture::default();
+        let response = capture.clone().scope(self.execute_action(action.clone())).await;
+        let command = capture.take_inverse().map(|inverse| Command::new(action, inverse));
+        (response, command)
+    }
+
+    fn get_command_error(response: &RxResponse) -> Option<String> {
+        match response {
+            RxResponse::Success() => None,
+            RxResponse::BatchResponse(responses) => responses.iter().find_map(Self::get_command_error),
+            RxResponse::Failure(message) => Some(message.clone()),
+            response => Some(format!("{:?}", response)),
+        }
+    }
+
+    async fn intercept<F, R>(&self, mut action: RxAction, run: F) -> RxResponse
+    where
+        F: FnOnce(RxAction) -> R,
+        R: Future<Output = RxResponse>,
+    {
+        let mut short_circuit = None;
+        let mut called = 0;
+        for interceptor in &self.interceptors {
+            called += 1;
+            if let Some(response) = interceptor.before(self, &mut action).await {
+                short_circuit = Some(response);
+                break;
+            }
+        }
+        let mut response = match short_circuit {
+            Some(response) => response,
+            None => run(action.clone()).await,
+        };
+        for interceptor in self.interceptors[..called].iter().rev() {
+            response = interceptor.after(self, &action, response).await;
+        }
+        response
+    }
+
+    async fn run_action(&self, action: RxAction) -> RxResponse {
         let rx_context = &self.context;
         match action {
             RxAction::UpdateEntities(kind, values) => {
-                rx_context.update_entities(&self, &kind, values).await;
-                RxResponse::Success()
+                rx_context.update_entities(&self, &kind, values).await
             }
             RxAction::DeleteEntities(kind, ids) => {
                 let ids_ref = ids.iter().map(|id| id.as_str()).collect();
-                rx_context.delete_entities(&self, &kind, &ids_ref).await;
-                RxResponse::Success()
+                rx_context.delete_entities(&self, &kind, &ids_ref).await
             }
             RxAction::QueryIds(kind, ids) => {
                 let ids_ref = ids.iter().map(|id| id.as_str()).collect();
@@ -138,7 +1144,81 @@ impl RxStore {
                     .query_property(&self, &kind, &property_name, &expression)
                     .await
             }
+            RxAction::QueryGeo(kind, query) => rx_context.query_geo(&self, &kind, &query).await,
             RxAction::Signal(signal) => rx_context.signal(&self, signal).await,
+            RxAction::Batch(actions) => self.run_batch(actions).await,
         }
     }
+
+    /// Nothing is written when one of the operations fails, the failing one
+    /// gets its error and the others a rollback failure.
+    async fn run_batch(&self, actions: Vec<RxAction>) -> RxResponse {
+        let count = actions.len();
+        let mut batch = RxBatch::new();
+        for (index, action) in actions.into_iter().enumerate() {
+            batch.set_position(index);
+            let response = match action {
+                RxAction::UpdateEntities(kind, values) => {
+                    self.context.batch_update_entities(self, &mut batch, &kind, values).await
+                }
+                RxAction::DeleteEntities(kind, ids) => {
+                    let ids_ref = ids.iter().map(|id| id.as_str()).collect();
+                    self.context.batch_delete_entities(self, &mut batch, &kind, &ids_ref).await
+                }
+                _ => RxResponse::Failure("Only updates and deletions can be batched".to_string()),
+            };
+            if !matches!(response, RxResponse::Success()) {
+                return Self::get_rollback_response(count, index, response);
+            }
+        }
+
+        let mut operations = batch.take_operations();
+        let mut store_batch = match self.store.begin_batch().await {
+            Ok(store_batch) => store_batch,
+            Err(message) => return RxResponse::BatchResponse(vec![RxResponse::Failure(message); count]),
+        };
+        for (index, operation) in operations.iter_mut() {
+            if let Err(error) = operation.write(&mut store_batch).await {
+                return Self::get_rollback_response(count, *index, error.into());
+            }
+        }
+        if let Err(message) = self.store.commit_batch(store_batch).await {
+            return RxResponse::BatchResponse(vec![RxResponse::Failure(message); count]);
+        }
+
+        for (_, operation) in &operations {
+            self.changes.send(operation.get_change()).ok();
+        }
+        let mut responses = vec![RxResponse::Success(); count];
+        for (index, operation) in operations {
+            if let Err(error) = operation.dispatch_hooks(self).await {
+                responses[index] = error.into();
+            }
+        }
+        RxResponse::BatchResponse(responses)
+    }
+
+    fn get_rollback_response(count: usize, failed_index: usize, failure: RxResponse) -> RxResponse {
+        let mut responses = vec![RxResponse::Failure(format!("Rolled back, operation {} failed", failed_index)); count];
+        responses[failed_index] = failure;
+        RxResponse::BatchResponse(responses)
+    }
+}
+
+#[async_trait]
+impl ReplicationPeer for RxStore {
+    async fn get_node_id(&self) -> Result<String, String> {
+        self.store
+            .get_node_id()
+            .map(|node_id| node_id.to_string())
+            .ok_or("Replication is not enabled".to_string())
+    }
+
+    async fn pull(&self, since: i64, limit: usize) -> Result<ChangeSet, String> {
+        self.changes_since(since, limit).await
+    }
+
+    async fn push(&self, change_set: ChangeSet) -> Result<ApplyReport, String> {
+        self.apply_change_set(change_set).await
+    }
 }
@@ -0,0 +1,60 @@
+use std::{str::FromStr, time::Duration};
+
+use chrono::{TimeZone, Utc};
+
+use crate::prelude::*;
+
+/// When a scheduled signal runs, cron expressions include the seconds
+/// (`0 0 3 * * *` runs every day at 3:00 UTC).
+#[derive(Debug, Clone)]
+pub enum Schedule {
+    Cron(Box<cron::Schedule>),
+    Interval(Duration),
+}
+
+impl Schedule {
+    pub fn cron(expression: &str) -> Result<Schedule, String> {
+        cron::Schedule::from_str(expression)
+            .map(|schedule| Schedule::Cron(Box::new(schedule)))
+            .map_err(|error| format!("Invalid cron expression {} : {}", expression, error))
+    }
+
+    /// The interval is at least one millisecond
+    pub fn every(interval: Duration) -> Result<Schedule, String> {
+        if interval.as_millis() == 0 {
+            return Err(format!("Invalid interval {:?} : must be at least 1ms", interval));
+        }
+        Ok(Schedule::Interval(interval))
+    }
+
+    /// Next run following `time`, in milliseconds since the epoch
+    pub fn next_after(&self, time: i64) -> Option<i64> {
+        match self {
+            Schedule::Cron(schedule) => {
+                let time = Utc.timestamp_millis_opt(time).single()?;
+                schedule.after(&time).next().map(|next| next.timestamp_millis())
+            }
+            Schedule::Interval(interval) => Some(time + interval.as_millis() as i64),
+        }
+    }
+}
+
+/// A signal sent by the scheduler of a store
+#[derive(Debug, Clone)]
+pub struct ScheduledSignal {
+    pub name: String,
+    pub schedule: Schedule,
+    pub signal: Value,
+}
+
+/// Recorded for every run of a scheduled signal, `message` is the signal
+/// response or the error, `schedule` is the name given to `with_scheduled_signal`.
+#[entity(index(schedule))]
+pub struct ScheduledRun {
+    pub schedule: String,
+    pub scheduled_at: i64,
+    pub started_at: i64,
+    pub finished_at: i64,
+    pub success: bool,
+    pub message: String,
+}
@@ -44,7 +44,7 @@ async fn long_add_wrapper(context: Arc<DispatchPayload<'_>>, value: Arc<User>) {
 }
 
 async fn sub<'a>(context: Arc<DispatchPayload<'a>>, _value: Arc<Payload>) {
-    context.store.save_entities(&vec![TestEntity::new(12)]).await;
+    context.store.save_entities(&vec![TestEntity::new(12)]).await.unwrap();
 }
 
 pub struct MyAddHandler;
@@ -52,8 +52,9 @@ pub struct MyAddHandler;
 #[async_trait]
 impl DataHookHandler for MyAddHandler {
 
-    async fn handle(&self, context: Arc<DispatchPayload<'_>>, value: Arc<Payload>) {
+    async fn handle(&self, context: Arc<DispatchPayload<'_>>, value: Arc<Payload>) -> Result<(), HookError> {
         add(context, value).await;
+        Ok(())
     }
 
     fn get_action(&self) -> EntityAction {
@@ -70,10 +71,11 @@ pub struct MyLongAddHandler;
 #[async_trait]
 impl DataHookHandler for MyLongAddHandler {
 
-    async fn handle(&self, context: Arc<DispatchPayload<'_>>, value: Arc<Payload>){
+    async fn handle(&self, context: Arc<DispatchPayload<'_>>, value: Arc<Payload>) -> Result<(), HookError> {
         if let Ok(data) = value.downcast::<User>() {
             long_add_wrapper(context, data).await;
         }
+        Ok(())
     }
 
     fn get_action(&self) -> EntityAction {
@@ -89,9 +91,10 @@ pub struct MySubtractHandler;
 
 #[async_trait]
 impl DataHookHandler for MySubtractHandler {
-    async fn handle(&self, context: Arc<DispatchPayload<'_>>, value: Arc<Payload>){
+    async fn handle(&self, context: Arc<DispatchPayload<'_>>, value: Arc<Payload>) -> Result<(), HookError> {
         println!("sub");
         sub(context.clone(), value.clone()).await;
+        Ok(())
     }
     fn get_action(&self) -> EntityAction {
         EntityAction::Update
@@ -105,7 +108,7 @@ impl DataHookHandler for MySubtractHandler {
 #[tokio::test]
 pub async fn test_dispatcher() {
     // Create a new Dispatcher instance
-    let mut dispatcher = Dispatcher::new();
+    let dispatcher = Dispatcher::new();
 
     // Create and register action handlers
     let add_handler = MyAddHandler;
@@ -0,0 +1,110 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use alchemix_rx::prelude::*;
+use alchemix_utils::file_io;
+
+#[entity]
+pub struct Post {
+    title: String,
+}
+
+#[entity]
+pub struct PostCount {
+    count: usize,
+}
+
+#[rx_context(Post, PostCount)]
+pub struct BlogContext {
+    // Count added for each saved post
+    increment: usize,
+    hook_runs: AtomicUsize,
+}
+
+#[rx_entity_update(Post)]
+async fn count_posts(value: &[Post], store: &RxStore, context: &BlogContext) -> Result<(), String> {
+    context.hook_runs.fetch_add(1, Ordering::SeqCst);
+    let counts = store.get_entities(BlogContext::POST_COUNT, &vec!["all"]).await;
+    let count = counts.first().map(|count| count.count).unwrap_or(0) + value.len() * context.increment;
+    store
+        .save_entities(&vec![PostCount::new_with_id("all", count)])
+        .await
+        .map(|_| ())
+        .map_err(|error| error.to_string())
+}
+
+async fn create_store(name: &str, increment: usize) -> RxStore {
+    let context = BlogContext {
+        increment,
+        hook_runs: AtomicUsize::new(0),
+    };
+    let mut rx_store = RxStore::new(context, &format!("test-data/out/entity-action-log-{}.db", name))
+        .with_entity_hooks(entity_hooks!(count_posts));
+    rx_store.open().await;
+    rx_store.clear().await;
+    rx_store
+}
+
+#[tokio::test]
+pub async fn test_action_log_replay() {
+    let log_path = "test-data/out/entity-action-log.ndjson";
+    file_io::remove_file(log_path);
+    // Each post is counted twice
+    let mut original = create_store("original", 2).await;
+    original = original.with_action_log(log_path);
+
+    let first_post = Post::new("First".to_string());
+    let second_post = Post::new("Second".to_string());
+    original
+        .execute_action(RxAction::new_update_action("Post", &vec![first_post.clone(), second_post.clone()]))
+        .await;
+    original
+        .execute_action(RxAction::new_delete_action("Post", vec![first_post.get_id().to_string()]))
+        .await;
+    original.execute_action(RxAction::new_query_ids("Post", vec![])).await;
+    original.execute_action(RxAction::new_update_action("Unknown", &vec![Post::new("Lost".to_string())])).await;
+
+    let logged_actions = ActionLog::read(log_path).unwrap();
+    assert_eq!(logged_actions.len(), 4);
+    assert!(logged_actions.windows(2).all(|pair| pair[0].timestamp <= pair[1].timestamp));
+
+    // The fixed hook rebuilds the count, the only divergence with the original store
+    let replayed = create_store("fixed", 1).await;
+    let report = original.replay(log_path, &replayed, ReplayHooks::Enabled).await.unwrap();
+    assert_eq!(report.actions, 4);
+    assert_eq!(report.failures.len(), 1);
+    assert_eq!(report.failures[0].position, 3);
+    assert_eq!(
+        report.divergences,
+        vec![Divergence {
+            kind: "PostCount".to_string(),
+            id: "all".to_string(),
+            reason: DivergenceReason::Different,
+        }]
+    );
+    let counts = replayed.get_entities(BlogContext::POST_COUNT, &vec!["all"]).await;
+    assert_eq!(counts[0].count, 2);
+
+    // Without the hooks the count is not written
+    let replayed = create_store("no-hooks", 2).await;
+    let report = original.replay(log_path, &replayed, ReplayHooks::Disabled).await.unwrap();
+    assert_eq!(replayed.get_context::<BlogContext>().hook_runs.load(Ordering::SeqCst), 0);
+    assert_eq!(report.divergences.len(), 1);
+    assert_eq!(report.divergences[0].reason, DivergenceReason::Missing);
+
+    let replayed = create_store("identical", 2).await;
+    assert!(original.replay(log_path, &replayed, ReplayHooks::Enabled).await.unwrap().is_identical());
+    assert_eq!(ActionLog::read(log_path).unwrap().len(), 4);
+    original.close().await;
+}
+
+#[tokio::test]
+pub async fn test_action_log_failure() {
+    // A directory cannot be opened as the log
+    let rx_store = create_store("failure", 1).await.with_action_log("test-data/out");
+    let post = Post::new("Unlogged".to_string());
+    let response = rx_store.execute_action(RxAction::new_update_action("Post", &vec![post.clone()])).await;
+    assert!(matches!(response, RxResponse::Failure(_)), "{:?}", response);
+    assert!(rx_store.get_entities(BlogContext::POST, &vec![post.get_id()]).await.is_empty());
+    assert_eq!(rx_store.get_context::<BlogContext>().hook_runs.load(Ordering::SeqCst), 0);
+    rx_store.close().await;
+}
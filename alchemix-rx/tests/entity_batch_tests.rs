@@ -0,0 +1,192 @@
+use std::sync::Mutex;
+
+use alchemix_rx::prelude::*;
+
+#[entity]
+pub struct Order {
+    customer: String,
+}
+
+#[entity]
+pub struct OrderLine {
+    order_id: String,
+    #[validate(range(min = 1, max = 100))]
+    quantity: i32,
+}
+
+#[rx_context(Order, OrderLine)]
+pub struct OrderContext {
+    events: Mutex<Vec<String>>,
+}
+
+#[rx_entity_update(Order)]
+async fn order_updated(value: &[Order], store: &RxStore, context: &OrderContext) {
+    // Hooks run after the commit, the lines of the batch are visible
+    let ids = value.iter().map(|order| format!("{}-1", order.id)).collect::<Vec<_>>();
+    let ids_ref = ids.iter().map(|id| id.as_str()).collect();
+    let lines = store.get_entities(OrderContext::ORDER_LINE, &ids_ref).await;
+    let event = format!("update Order {} lines", lines.len());
+    context.events.lock().unwrap().push(event);
+}
+
+#[rx_entity_update(OrderLine)]
+async fn lines_updated(value: &[OrderLine], _store: &RxStore, context: &OrderContext) {
+    let event = format!("update OrderLine {}", value.len());
+    context.events.lock().unwrap().push(event);
+}
+
+#[rx_entity_delete(OrderLine)]
+async fn lines_deleted(value: &[OrderLine], _store: &RxStore, context: &OrderContext) {
+    let event = format!("delete OrderLine {}", value.len());
+    context.events.lock().unwrap().push(event);
+}
+
+#[rx_entity_before_update(Order)]
+async fn check_customer(value: &mut [Order], _store: &RxStore) -> Result<(), String> {
+    if value.iter().any(|order| order.customer.is_empty()) {
+        return Err("Missing customer".to_string());
+    }
+    Ok(())
+}
+
+async fn create_store(name: &str) -> RxStore {
+    let context = OrderContext {
+        events: Mutex::new(vec![]),
+    };
+    let db_path = format!("test-data/out/entity-batch-{}.db", name);
+    let mut rx_store = RxStore::new(context, &db_path)
+        .with_entity_hooks(entity_hooks!(order_updated, lines_updated, lines_deleted, check_customer));
+    rx_store.open().await;
+    rx_store.clear().await;
+    rx_store
+}
+
+fn create_order(id: &str, customer: &str, quantities: &[i32]) -> Vec<RxAction> {
+    let order = Order::new_with_id(id, customer.to_string());
+    let lines: Vec<OrderLine> = quantities
+        .iter()
+        .enumerate()
+        .map(|(index, quantity)| OrderLine::new_with_id(&format!("{}-{}", id, index + 1), id.to_string(), *quantity))
+        .collect();
+    vec![
+        RxAction::new_update_action("Order", &vec![order]),
+        RxAction::new_update_action("OrderLine", &lines),
+    ]
+}
+
+fn events(rx_store: &RxStore) -> Vec<String> {
+    std::mem::take(&mut *rx_store.get_context::<OrderContext>().events.lock().unwrap())
+}
+
+fn get_responses(response: RxResponse) -> Vec<RxResponse> {
+    match response {
+        RxResponse::BatchResponse(responses) => responses,
+        response => panic!("Unexpected response {:?}", response),
+    }
+}
+
+#[tokio::test]
+pub async fn test_batch() {
+    let rx_store = create_store("commit").await;
+
+    let action = RxAction::new_batch(create_order("o1", "acme", &[2, 3]));
+    let responses = get_responses(rx_store.execute_action(action).await);
+    assert_eq!(responses.len(), 2);
+    assert!(responses.iter().all(|response| matches!(response, RxResponse::Success())));
+    assert_eq!(events(&rx_store), vec!["update Order 1 lines", "update OrderLine 2"]);
+    assert_eq!(rx_store.get_entities(OrderContext::ORDER_LINE, &vec!["o1-1", "o1-2"]).await.len(), 2);
+
+    let mut actions = create_order("o2", "acme", &[1]);
+    actions.push(RxAction::new_delete_action("OrderLine", vec!["o1-2".to_string()]));
+    let responses = get_responses(rx_store.execute_action(RxAction::new_batch(actions)).await);
+    assert_eq!(responses.len(), 3);
+    assert!(responses.iter().all(|response| matches!(response, RxResponse::Success())));
+    assert_eq!(events(&rx_store), vec!["update Order 1 lines", "update OrderLine 1", "delete OrderLine 1"]);
+    assert!(rx_store.get_entities(OrderContext::ORDER_LINE, &vec!["o1-2"]).await.is_empty());
+
+    // The batch goes through the JSON actions as well
+    let json = serde_json::to_string(&RxAction::new_batch(create_order("o3", "acme", &[1]))).unwrap();
+    let action: RxAction = serde_json::from_str(&json).unwrap();
+    assert_eq!(get_responses(rx_store.execute_action(action).await).len(), 2);
+    rx_store.close().await;
+}
+
+#[tokio::test]
+pub async fn test_batch_rollback() {
+    let rx_store = create_store("rollback").await;
+
+    // An invalid line rolls back the order
+    let action = RxAction::new_batch(create_order("o1", "acme", &[2, 500]));
+    let responses = get_responses(rx_store.execute_action(action).await);
+    assert!(matches!(&responses[0], RxResponse::Failure(message) if message == "Rolled back, operation 1 failed"));
+    assert!(matches!(responses[1], RxResponse::ValidationFailure(_)));
+    assert!(rx_store.get_entities(OrderContext::ORDER, &vec!["o1"]).await.is_empty());
+    assert!(events(&rx_store).is_empty());
+
+    // So does a before hook rejecting the order
+    let action = RxAction::new_batch(create_order("o2", "", &[2]));
+    let responses = get_responses(rx_store.execute_action(action).await);
+    assert!(matches!(&responses[0], RxResponse::Failure(message) if message == "Missing customer"));
+    assert!(matches!(responses[1], RxResponse::Failure(_)));
+    assert!(rx_store.get_entities(OrderContext::ORDER_LINE, &vec!["o2-1"]).await.is_empty());
+
+    // Queries are not batched
+    let mut actions = create_order("o3", "acme", &[2]);
+    actions.push(RxAction::new_query_ids("Order", vec!["o3".to_string()]));
+    let responses = get_responses(rx_store.execute_action(RxAction::new_batch(actions)).await);
+    assert!(matches!(responses[2], RxResponse::Failure(_)));
+    assert!(rx_store.get_entities(OrderContext::ORDER, &vec!["o3"]).await.is_empty());
+    assert!(events(&rx_store).is_empty());
+    rx_store.close().await;
+}
+
+#[tokio::test]
+pub async fn test_batch_statement_failure() {
+    let db_path = "test-data/out/entity-batch-statement.db";
+    let rx_store = create_store("statement").await;
+    // A trigger makes the INSERT of one line fail inside the transaction
+    let pool = sqlx::SqlitePool::connect(&format!("sqlite://{}", db_path)).await.unwrap();
+    sqlx::query("DROP TRIGGER IF EXISTS reject_line").execute(&pool).await.unwrap();
+    let trigger = "CREATE TRIGGER reject_line BEFORE INSERT ON entity WHEN NEW.id = 'o1-2'
+        BEGIN SELECT RAISE(ABORT, 'line rejected'); END";
+    sqlx::query(trigger).execute(&pool).await.unwrap();
+
+    let action = RxAction::new_batch(create_order("o1", "acme", &[2, 3]));
+    let responses = get_responses(rx_store.execute_action(action).await);
+    assert!(matches!(&responses[0], RxResponse::Failure(message) if message == "Rolled back, operation 1 failed"));
+    assert!(matches!(&responses[1], RxResponse::Failure(message) if message.contains("line rejected")));
+    assert!(rx_store.get_entities(OrderContext::ORDER, &vec!["o1"]).await.is_empty());
+    assert!(rx_store.get_entities(OrderContext::ORDER_LINE, &vec!["o1-1"]).await.is_empty());
+    assert!(events(&rx_store).is_empty());
+
+    sqlx::query("DROP TRIGGER reject_line").execute(&pool).await.unwrap();
+    pool.close().await;
+    rx_store.close().await;
+}
+
+#[tokio::test]
+pub async fn test_batch_delete_undecodable() {
+    let db_path = "test-data/out/entity-batch-undecodable.db";
+    let rx_store = create_store("undecodable").await;
+    let action = RxAction::new_batch(create_order("o1", "acme", &[2, 3]));
+    get_responses(rx_store.execute_action(action).await);
+    events(&rx_store);
+
+    let pool = sqlx::SqlitePool::connect(&format!("sqlite://{}", db_path)).await.unwrap();
+    sqlx::query("UPDATE entity SET data = x'00' WHERE key = 'OrderLine#o1-1'").execute(&pool).await.unwrap();
+
+    // The row is deleted although it cannot be decoded, only the decoded line reaches the hooks
+    let ids = vec!["o1-1".to_string(), "o1-2".to_string()];
+    let action = RxAction::new_batch(vec![RxAction::new_delete_action("OrderLine", ids)]);
+    let responses = get_responses(rx_store.execute_action(action).await);
+    assert!(matches!(responses[0], RxResponse::Success()));
+    assert_eq!(events(&rx_store), vec!["delete OrderLine 1"]);
+    let rows: Vec<(String,)> = sqlx::query_as("SELECT key FROM entity WHERE kind = 'OrderLine'")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    assert!(rows.is_empty());
+
+    pool.close().await;
+    rx_store.close().await;
+}
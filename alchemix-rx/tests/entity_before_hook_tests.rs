@@ -0,0 +1,122 @@
+use alchemix_rx::prelude::*;
+
+#[entity(index(email))]
+pub struct Member {
+    email: String,
+    locked: bool,
+}
+
+#[entity]
+pub struct MemberLog {
+    message: String,
+}
+
+#[rx_context(Member, MemberLog)]
+pub struct MemberContext {
+    banned_domain: String,
+}
+
+#[rx_entity_before_update(Member)]
+async fn normalize_email(value: &mut [Member], _store: &RxStore) -> Result<(), String> {
+    for member in value.iter_mut() {
+        member.email = member.email.trim().to_lowercase();
+    }
+    Ok(())
+}
+
+#[rx_entity_before_update(Member)]
+async fn reject_banned(
+    value: &mut [Member],
+    _store: &RxStore,
+    context: &MemberContext,
+) -> Result<(), String> {
+    match value.iter().find(|member| member.email.ends_with(&context.banned_domain)) {
+        Some(member) => Err(format!("{} is banned", member.email)),
+        None => Ok(()),
+    }
+}
+
+#[rx_entity_before_delete(Member)]
+async fn keep_locked(value: &mut Vec<Member>, _store: &RxStore) -> Result<(), String> {
+    value.retain(|member| !member.locked);
+    Ok(())
+}
+
+#[rx_entity_update(Member)]
+async fn log_members(value: &[Member], store: &RxStore) {
+    let logs = value
+        .iter()
+        .map(|member| MemberLog::new_with_id(&member.id, member.email.clone()))
+        .collect();
+    store.save_entities(&logs).await.unwrap();
+}
+
+#[rx_entity_before_delete(MemberLog)]
+async fn protect_logs(_value: &Vec<MemberLog>, _store: &RxStore) -> Result<(), String> {
+    Err("Logs are read only".to_string())
+}
+
+fn create_member(id: &str, email: &str, locked: bool) -> Member {
+    Member::new_with_id(id, email.to_string(), locked)
+}
+
+#[tokio::test]
+pub async fn test_before_hooks() {
+    let context = MemberContext {
+        banned_domain: "@spam.com".to_string(),
+    };
+    let mut rx_store = RxStore::new(context, "test-data/out/entity-before-hooks.db")
+        .with_entity_hooks(entity_hooks!(normalize_email, reject_banned, keep_locked, log_members, protect_logs));
+    rx_store.open().await;
+    rx_store.clear().await;
+
+    // Entities are saved, and dispatched to the after hooks, as modified
+    rx_store
+        .save_entities(&vec![create_member("m1", " Wile@ACME.com", false)])
+        .await
+        .unwrap();
+    let member = rx_store.get_entities(MemberContext::MEMBER, &vec!["m1"]).await;
+    assert_eq!(member[0].email, "wile@acme.com");
+    let log = rx_store.get_entities(MemberContext::MEMBER_LOG, &vec!["m1"]).await;
+    assert_eq!(log[0].message, "wile@acme.com");
+
+    // A rejection cancels the whole batch, the after hooks are not called
+    let res = rx_store
+        .save_entities(&vec![
+            create_member("m2", "road@acme.com", false),
+            create_member("m3", "SALE@spam.com", false),
+        ])
+        .await;
+    match res {
+        Err(RxError::Rejected(message)) => assert_eq!(message, "sale@spam.com is banned"),
+        _ => panic!("Expected a rejection"),
+    }
+    assert!(rx_store.get_entities(MemberContext::MEMBER, &vec!["m2"]).await.is_empty());
+    assert!(rx_store.get_entities(MemberContext::MEMBER_LOG, &vec!["m2"]).await.is_empty());
+
+    let action = RxAction::new_update_action("Member", &vec![create_member("m4", "spam@spam.com", false)]);
+    assert!(matches!(rx_store.execute_action(action).await, RxResponse::Failure(_)));
+
+    // Before delete hooks filter the entities to remove
+    rx_store
+        .save_entities(&vec![create_member("m5", "locked@acme.com", true)])
+        .await
+        .unwrap();
+    rx_store
+        .delete_entities(MemberContext::MEMBER, &vec!["m1", "m5"])
+        .await
+        .unwrap();
+    let members = rx_store.get_entities(MemberContext::MEMBER, &vec!["m1", "m5"]).await;
+    assert_eq!(members.len(), 1);
+    assert_eq!(members[0].id, "m5");
+
+    let res = rx_store
+        .delete_entities(MemberContext::MEMBER_LOG, &vec!["m1"])
+        .await;
+    assert!(matches!(res, Err(RxError::Rejected(_))));
+    let action = RxAction::new_delete_action("MemberLog", vec!["m1".to_string()]);
+    assert!(matches!(rx_store.execute_action(action).await, RxResponse::Failure(_)));
+    assert_eq!(rx_store.get_entities(MemberContext::MEMBER_LOG, &vec!["m1"]).await.len(), 1);
+
+    rx_store.close().await;
+}
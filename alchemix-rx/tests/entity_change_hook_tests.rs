@@ -0,0 +1,96 @@
+use std::sync::Mutex;
+
+use alchemix_rx::prelude::*;
+
+#[entity_part]
+pub struct Stock {
+    quantity: u32,
+    location: String,
+}
+
+#[entity(index(name))]
+pub struct Product {
+    name: String,
+    price: f64,
+    stock: Stock,
+}
+
+// Id of the product and its changed fields, None when it was created
+type ProductChange = (String, Option<Vec<&'static str>>);
+
+#[rx_context(Product)]
+pub struct ProductContext {
+    changes: Mutex<Vec<ProductChange>>,
+}
+
+#[rx_entity_change(Product)]
+async fn on_product_change(
+    value: &Vec<(Option<Product>, Product)>,
+    _store: &RxStore,
+    context: &ProductContext,
+) {
+    let mut changes = context.changes.lock().unwrap();
+    for (previous, product) in value {
+        let changed_fields = previous.as_ref().map(|previous| previous.diff(product));
+        changes.push((product.id.clone(), changed_fields));
+    }
+}
+
+fn create_product(id: &str, name: &str, price: f64, quantity: u32) -> Product {
+    Product::new_with_id(
+        id,
+        name.to_string(),
+        price,
+        Stock {
+            quantity,
+            location: "A1".to_string(),
+        },
+    )
+}
+
+#[test]
+pub fn test_entity_diff() {
+    let product = create_product("p1", "Anvil", 10.0, 5);
+    assert!(product.diff(&product.clone()).is_empty());
+
+    let mut changed = product.clone();
+    changed.price = 12.5;
+    changed.stock.location = "B2".to_string();
+    assert_eq!(product.diff(&changed), vec!["price", "stock"]);
+}
+
+#[tokio::test]
+pub async fn test_change_hooks() {
+    let context = ProductContext {
+        changes: Mutex::new(vec![]),
+    };
+    let mut rx_store = RxStore::new(context, "test-data/out/entity-change-hooks.db")
+        .with_entity_hooks(entity_hooks!(on_product_change));
+    rx_store.open().await;
+    rx_store.clear().await;
+
+    rx_store
+        .save_entities(&vec![create_product("p1", "Anvil", 10.0, 5)])
+        .await
+        .unwrap();
+    rx_store
+        .save_entities(&vec![
+            create_product("p1", "Anvil", 10.0, 4),
+            create_product("p2", "Rocket", 99.0, 1),
+        ])
+        .await
+        .unwrap();
+
+    let context = rx_store.get_context::<ProductContext>();
+    let changes = context.changes.lock().unwrap().clone();
+    assert_eq!(
+        changes,
+        vec![
+            ("p1".to_string(), None),
+            ("p1".to_string(), Some(vec!["stock"])),
+            ("p2".to_string(), None),
+        ]
+    );
+
+    rx_store.close().await;
+}
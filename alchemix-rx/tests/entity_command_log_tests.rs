@@ -0,0 +1,138 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use alchemix_rx::prelude::*;
+
+#[entity]
+pub struct Shape {
+    color: String,
+}
+
+#[rx_context(Shape)]
+pub struct EditorContext {
+    updated: AtomicUsize,
+    deleted: AtomicUsize,
+}
+
+#[rx_entity_update(Shape)]
+async fn on_shape_update(value: &[Shape], _store: &RxStore, context: &EditorContext) {
+    context.updated.fetch_add(value.len(), Ordering::SeqCst);
+}
+
+#[rx_entity_delete(Shape)]
+async fn on_shape_delete(value: &[Shape], _store: &RxStore, context: &EditorContext) {
+    context.deleted.fetch_add(value.len(), Ordering::SeqCst);
+}
+
+async fn create_store(name: &str, capacity: usize) -> RxStore {
+    let context = EditorContext {
+        updated: AtomicUsize::new(0),
+        deleted: AtomicUsize::new(0),
+    };
+    let db_path = format!("test-data/out/entity-command-log-{}.db", name);
+    let mut rx_store = RxStore::new(context, &db_path)
+        .with_entity_hooks(entity_hooks!(on_shape_update, on_shape_delete))
+        .with_command_log(capacity);
+    rx_store.open().await;
+    rx_store.clear().await;
+    rx_store
+}
+
+async fn save_shape(rx_store: &RxStore, session: &str, shape: &Shape) {
+    let action = RxAction::new_update_action("Shape", &vec![shape.clone()]);
+    let response = rx_store.execute_session_action(session, action).await;
+    assert!(matches!(response, RxResponse::Success()), "{:?}", response);
+}
+
+async fn get_color(rx_store: &RxStore, shape: &Shape) -> Option<String> {
+    let shapes = rx_store.get_entities(EditorContext::SHAPE, &vec![shape.get_id()]).await;
+    shapes.first().map(|shape| shape.color.clone())
+}
+
+#[tokio::test]
+pub async fn test_undo_redo() {
+    let rx_store = create_store("undo", 10).await;
+    let context = rx_store.get_context::<EditorContext>();
+
+    let mut shape = Shape::new("red".to_string());
+    save_shape(&rx_store, "alice", &shape).await;
+    shape.color = "blue".to_string();
+    save_shape(&rx_store, "alice", &shape).await;
+    assert_eq!(rx_store.get_command_log().unwrap().get_commands("alice").len(), 2);
+
+    // The update hooks run for the restored values
+    assert_eq!(rx_store.undo("alice", 1).await, Ok(1));
+    assert_eq!(get_color(&rx_store, &shape).await.as_deref(), Some("red"));
+    assert_eq!(context.updated.load(Ordering::SeqCst), 3);
+
+    // Undoing the creation deletes the shape
+    assert_eq!(rx_store.undo("alice", 5).await, Ok(1));
+    assert_eq!(get_color(&rx_store, &shape).await, None);
+    assert_eq!(context.deleted.load(Ordering::SeqCst), 1);
+
+    assert_eq!(rx_store.redo("alice", 2).await, Ok(2));
+    assert_eq!(get_color(&rx_store, &shape).await.as_deref(), Some("blue"));
+    assert_eq!(rx_store.redo("alice", 1).await, Ok(0));
+
+    // Deletions are restored, a new command drops the undone ones
+    let action = RxAction::new_delete_action("Shape", vec![shape.get_id().to_string()]);
+    assert!(matches!(rx_store.execute_session_action("alice", action).await, RxResponse::Success()));
+    assert_eq!(rx_store.undo("alice", 1).await, Ok(1));
+    assert_eq!(get_color(&rx_store, &shape).await.as_deref(), Some("blue"));
+    save_shape(&rx_store, "alice", &Shape::new("green".to_string())).await;
+    assert_eq!(rx_store.redo("alice", 1).await, Ok(0));
+    rx_store.close().await;
+}
+
+#[tokio::test]
+pub async fn test_command_log_sessions() {
+    let rx_store = create_store("sessions", 2).await;
+
+    let shapes: Vec<Shape> = (0..3).map(|index| Shape::new(format!("color-{}", index))).collect();
+    for shape in &shapes {
+        save_shape(&rx_store, "alice", shape).await;
+    }
+    let bob_shape = Shape::new("black".to_string());
+    save_shape(&rx_store, "bob", &bob_shape).await;
+
+    // The oldest command is dropped past the capacity
+    assert_eq!(rx_store.undo("alice", 10).await, Ok(2));
+    assert!(get_color(&rx_store, &shapes[0]).await.is_some());
+    assert!(get_color(&rx_store, &shapes[1]).await.is_none());
+    assert_eq!(get_color(&rx_store, &bob_shape).await.as_deref(), Some("black"));
+    assert_eq!(rx_store.get_command_log().unwrap().get_undone_commands("alice").len(), 2);
+    assert_eq!(rx_store.get_command_log().unwrap().get_commands("bob").len(), 1);
+
+    // Actions sent without a session are not recorded
+    let action = RxAction::new_update_action("Shape", &vec![Shape::new("white".to_string())]);
+    rx_store.execute_action(action).await;
+    assert_eq!(rx_store.undo("carol", 1).await, Ok(0));
+    rx_store.close().await;
+}
+
+#[tokio::test]
+pub async fn test_command_log_max_sessions() {
+    let context = EditorContext {
+        updated: AtomicUsize::new(0),
+        deleted: AtomicUsize::new(0),
+    };
+    let mut rx_store = RxStore::new(context, "test-data/out/entity-command-log-max-sessions.db")
+        .with_entity_hooks(entity_hooks!(on_shape_update, on_shape_delete))
+        .with_command_log_sessions(10, 2);
+    rx_store.open().await;
+    rx_store.clear().await;
+    let command_log = rx_store.get_command_log().unwrap();
+    assert_eq!(command_log.get_max_sessions(), 2);
+
+    save_shape(&rx_store, "alice", &Shape::new("red".to_string())).await;
+    save_shape(&rx_store, "bob", &Shape::new("blue".to_string())).await;
+    save_shape(&rx_store, "alice", &Shape::new("green".to_string())).await;
+
+    // The least recently used session is dropped past the limit
+    save_shape(&rx_store, "carol", &Shape::new("black".to_string())).await;
+    assert_eq!(command_log.get_session_count(), 2);
+    assert_eq!(command_log.get_commands("alice").len(), 2);
+    assert!(command_log.get_commands("bob").is_empty());
+    assert_eq!(rx_store.undo("bob", 1).await, Ok(0));
+    assert_eq!(command_log.get_commands("carol").len(), 1);
+    rx_store.close().await;
+}
@@ -0,0 +1,122 @@
+use alchemix_rx::prelude::*;
+
+#[entity(index(name), geo(lat, lon))]
+pub struct Site {
+    name: String,
+    lat: f64,
+    lon: f64,
+}
+
+#[rx_context(Site)]
+pub struct GeoContext {}
+
+fn create_sites() -> Vec<Site> {
+    vec![
+        Site::new_with_id("louvre", "Louvre".to_string(), 48.8606, 2.3376),
+        Site::new_with_id("orsay", "Orsay".to_string(), 48.8600, 2.3266),
+        Site::new_with_id("eiffel", "Eiffel Tower".to_string(), 48.8584, 2.2945),
+        Site::new_with_id("fourviere", "Fourviere".to_string(), 45.7623, 4.8223),
+    ]
+}
+
+#[tokio::test]
+pub async fn test_geo_queries() {
+    let mut rx_store = RxStore::new(GeoContext {}, "test-data/out/entity-geo.db");
+    rx_store.open().await;
+    rx_store.clear().await;
+
+    rx_store.save_entities(&create_sites()).await.unwrap();
+
+    let paris = GeoBounds::new(48.8, 2.2, 48.9, 2.4);
+    let sites = rx_store
+        .query_geo(GeoContext::SITE, &GeoQuery::BoundingBox(paris))
+        .await;
+    assert_eq!(sites.len(), 3);
+
+    let louvre = GeoPoint::new(48.8606, 2.3376);
+    let sites = rx_store
+        .query_geo(GeoContext::SITE, &GeoQuery::Radius(louvre, 1_500.0))
+        .await;
+    let names: Vec<&str> = sites.iter().map(|site| site.name.as_str()).collect();
+    assert_eq!(names, vec!["Louvre", "Orsay"]);
+
+    // Moving an entity updates its position in the index
+    let moved = vec![Site::new_with_id("fourviere", "Fourviere".to_string(), 48.8610, 2.3380)];
+    rx_store.save_entities(&moved).await.unwrap();
+    let sites = rx_store
+        .query_geo(GeoContext::SITE, &GeoQuery::Radius(louvre, 100.0))
+        .await;
+    assert_eq!(sites.len(), 2);
+
+    rx_store
+        .delete_entities(GeoContext::SITE, &vec!["fourviere"])
+        .await
+        .unwrap();
+    let res = rx_store
+        .execute_action(RxAction::new_query_geo(
+            "Site",
+            GeoQuery::Radius(louvre, 100.0),
+        ))
+        .await;
+    match res {
+        RxResponse::QueryResponse(values) => assert_eq!(values.as_array().unwrap().len(), 1),
+        _ => panic!("Unexpected response {:?}", res),
+    }
+    rx_store.close().await;
+}
+
+#[tokio::test]
+pub async fn test_geo_antimeridian() {
+    let mut rx_store = RxStore::new(GeoContext {}, "test-data/out/entity-geo-antimeridian.db");
+    rx_store.open().await;
+    rx_store.clear().await;
+
+    let sites = vec![
+        Site::new_with_id("east", "East".to_string(), -16.5, 179.9),
+        Site::new_with_id("west", "West".to_string(), -16.5, -179.9),
+        Site::new_with_id("far", "Far".to_string(), -16.5, 170.0),
+    ];
+    rx_store.save_entities(&sites).await.unwrap();
+
+    // About 21 km apart across the antimeridian
+    let center = GeoPoint::new(-16.5, 179.9);
+    let sites = rx_store
+        .query_geo(GeoContext::SITE, &GeoQuery::Radius(center, 30_000.0))
+        .await;
+    let names: Vec<&str> = sites.iter().map(|site| site.name.as_str()).collect();
+    assert_eq!(names, vec!["East", "West"]);
+
+    let bounds = GeoBounds::new(-17.0, 179.0, -16.0, -179.0);
+    assert!(bounds.contains(&GeoPoint::new(-16.5, -179.9)));
+    assert!(!bounds.contains(&GeoPoint::new(-16.5, 170.0)));
+    let sites = rx_store
+        .query_geo(GeoContext::SITE, &GeoQuery::BoundingBox(bounds))
+        .await;
+    assert_eq!(sites.len(), 2);
+    rx_store.close().await;
+}
+
+#[tokio::test]
+pub async fn test_geo_delete_quoted_ids() {
+    let mut rx_store = RxStore::new(GeoContext {}, "test-data/out/entity-geo-quoted.db");
+    rx_store.open().await;
+    rx_store.clear().await;
+
+    let quoted_id = "x'); DELETE FROM entity; --";
+    let sites = vec![
+        Site::new_with_id(quoted_id, "Quoted".to_string(), 48.8606, 2.3376),
+        Site::new_with_id("orsay", "Orsay".to_string(), 48.8600, 2.3266),
+    ];
+    rx_store.save_entities(&sites).await.unwrap();
+    rx_store.delete_entities(GeoContext::SITE, &vec![quoted_id]).await.unwrap();
+
+    // The id is bound, the other entities and their geo index are kept
+    assert!(rx_store.get_entities(GeoContext::SITE, &vec![quoted_id]).await.is_empty());
+    let louvre = GeoPoint::new(48.8606, 2.3376);
+    let sites = rx_store
+        .query_geo(GeoContext::SITE, &GeoQuery::Radius(louvre, 1_500.0))
+        .await;
+    let names: Vec<&str> = sites.iter().map(|site| site.name.as_str()).collect();
+    assert_eq!(names, vec!["Orsay"]);
+    rx_store.close().await;
+}
@@ -0,0 +1,103 @@
+use std::sync::Mutex;
+
+use alchemix_rx::prelude::*;
+
+#[entity]
+pub struct Order {
+    quantity: u32,
+}
+
+#[entity]
+pub struct Stock {
+    quantity: u32,
+}
+
+#[entity]
+pub struct Audit {
+    message: String,
+}
+
+#[entity]
+pub struct Counter {
+    value: u32,
+}
+
+#[rx_context(Order, Stock, Audit, Counter)]
+pub struct ShopContext {
+    chains: Mutex<Vec<String>>,
+}
+
+#[rx_entity_update(Order)]
+async fn reserve_stock(value: &[Order], store: &RxStore) -> Result<(), String> {
+    let stock = value.iter().map(|order| Stock::new_with_id(&order.id, order.quantity)).collect();
+    store.save_entities(&stock).await.map_err(|error| error.to_string())?;
+    Ok(())
+}
+
+#[rx_entity_update(Stock)]
+async fn audit_stock(value: &[Stock], store: &RxStore, context: &ShopContext) -> Result<(), String> {
+    context.chains.lock().unwrap().push(store.get_hook_chain().to_string());
+    let audits = value.iter().map(|stock| Audit::new(format!("stock {}", stock.id))).collect();
+    store.save_entities(&audits).await.map_err(|error| error.to_string())?;
+    Ok(())
+}
+
+#[rx_entity_update(Counter)]
+async fn increment_counter(value: &[Counter], store: &RxStore) -> Result<(), String> {
+    let counters = value.iter().map(|counter| Counter::new_with_id(&counter.id, counter.value + 1)).collect();
+    store.save_entities(&counters).await.map_err(|error| error.to_string())?;
+    Ok(())
+}
+
+async fn create_store(name: &str, max_depth: usize) -> RxStore {
+    let context = ShopContext {
+        chains: Mutex::new(vec![]),
+    };
+    let db_path = format!("test-data/out/entity-hook-chain-{}.db", name);
+    let mut rx_store = RxStore::new(context, &db_path)
+        .with_entity_hooks(entity_hooks!(reserve_stock, audit_stock, increment_counter))
+        .with_max_hook_depth(max_depth);
+    rx_store.open().await;
+    rx_store.clear().await;
+    rx_store
+}
+
+#[tokio::test]
+pub async fn test_hook_chain() {
+    let rx_store = create_store("default", DEFAULT_MAX_HOOK_DEPTH).await;
+
+    let report = rx_store.save_entities(&vec![Order::new_with_id("o1", 3)]).await.unwrap();
+    assert!(report.is_success());
+    assert!(rx_store.get_hook_chain().causes.is_empty());
+    let chains = rx_store.get_context::<ShopContext>().chains.lock().unwrap().clone();
+    assert_eq!(chains, vec!["update Order -> update Stock"]);
+    let audits = rx_store.get_entities(ShopContext::AUDIT, &vec![]).await;
+    assert_eq!(audits.len(), 1);
+
+    // A hook writing its own kind is stopped at the first nested write
+    let report = rx_store.save_entities(&vec![Counter::new_with_id("c1", 0)]).await.unwrap();
+    assert_eq!(
+        report.failures[0].error.message,
+        "Hook cycle detected : update Counter -> update Counter"
+    );
+    let counters = rx_store.get_entities(ShopContext::COUNTER, &vec!["c1"]).await;
+    assert_eq!(counters[0].value, 0);
+    rx_store.close().await;
+
+    let rx_store = create_store("depth", 2).await;
+    let report = rx_store.save_entities(&vec![Order::new_with_id("o1", 3)]).await.unwrap();
+    assert!(report.is_success());
+    // The failure is reported to the caller of the nested write
+    let chains = rx_store.get_context::<ShopContext>().chains.lock().unwrap().clone();
+    assert_eq!(chains.len(), 1);
+    assert!(rx_store.get_entities(ShopContext::AUDIT, &vec![]).await.is_empty());
+    rx_store.close().await;
+
+    let rx_store = create_store("depth-direct", 1).await;
+    let report = rx_store.save_entities(&vec![Stock::new_with_id("s1", 3)]).await.unwrap();
+    assert_eq!(
+        report.failures[0].error.message,
+        "Maximum hook depth exceeded : update Stock -> update Audit"
+    );
+    rx_store.close().await;
+}
@@ -0,0 +1,124 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use alchemix_rx::prelude::*;
+
+#[entity]
+pub struct Invoice {
+    amount: i64,
+}
+
+#[rx_context(Invoice)]
+pub struct InvoiceContext {
+    archived: AtomicUsize,
+}
+
+#[rx_entity_update(Invoice, priority = 10)]
+async fn send_invoice(value: &[Invoice], _store: &RxStore) -> Result<(), String> {
+    match value.iter().find(|invoice| invoice.amount < 0) {
+        Some(invoice) => Err(format!("Unable to send {}", invoice.id)),
+        None => Ok(()),
+    }
+}
+
+#[rx_entity_update(Invoice)]
+async fn archive_invoice(
+    _value: &[Invoice],
+    _store: &RxStore,
+    context: &InvoiceContext,
+) -> Result<(), HookError> {
+    context.archived.fetch_add(1, Ordering::SeqCst);
+    Ok(())
+}
+
+// Paid invoices can not be cancelled
+#[rx_entity_change(Invoice)]
+async fn check_invoice(value: &[(Option<Invoice>, Invoice)], _store: &RxStore) -> Result<(), String> {
+    match value.iter().find(|(previous, invoice)| invoice.amount == 0 && previous.is_some()) {
+        Some((_, invoice)) => Err(format!("Unable to cancel {}", invoice.id)),
+        None => Ok(()),
+    }
+}
+
+async fn create_store(name: &str, policy: HookFailurePolicy) -> RxStore {
+    let context = InvoiceContext {
+        archived: AtomicUsize::new(0),
+    };
+    let db_path = format!("test-data/out/entity-hook-failure-{}.db", name);
+    let mut rx_store = RxStore::new(context, &db_path)
+        .with_entity_hooks(entity_hooks!(send_invoice, archive_invoice, check_invoice))
+        .with_hook_failure_policy(policy);
+    rx_store.open().await;
+    rx_store.clear().await;
+    rx_store
+}
+
+fn archived(rx_store: &RxStore) -> usize {
+    rx_store.get_context::<InvoiceContext>().archived.load(Ordering::SeqCst)
+}
+
+#[tokio::test]
+pub async fn test_hook_failures() {
+    let rx_store = create_store("log", HookFailurePolicy::Log).await;
+    let report = rx_store
+        .save_entities(&vec![Invoice::new_with_id("i1", 100)])
+        .await
+        .unwrap();
+    assert!(report.is_success());
+
+    // Failures are reported, the following hooks still run
+    let report = rx_store
+        .save_entities(&vec![Invoice::new_with_id("i2", -5)])
+        .await
+        .unwrap();
+    assert_eq!(
+        report.failures,
+        vec![HookFailure {
+            hook: "send_invoice".to_string(),
+            action: "update".to_string(),
+            kind: "Invoice".to_string(),
+            error: HookError::new("Unable to send i2"),
+        }]
+    );
+    assert_eq!(archived(&rx_store), 2);
+    rx_store.close().await;
+
+    let rx_store = create_store("ignore", HookFailurePolicy::Ignore).await;
+    let report = rx_store
+        .save_entities(&vec![Invoice::new_with_id("i2", -5)])
+        .await
+        .unwrap();
+    assert_eq!(report.failures.len(), 1);
+    rx_store.close().await;
+
+    // Fail fast skips the following stages, the entities are written
+    let rx_store = create_store("fail-fast", HookFailurePolicy::FailFast).await;
+    let res = rx_store
+        .save_entities(&vec![Invoice::new_with_id("i2", -5)])
+        .await;
+    match res {
+        Err(RxError::Hooks(report)) => assert_eq!(report.failures[0].hook, "send_invoice"),
+        _ => panic!("Expected a hook failure"),
+    }
+    assert_eq!(archived(&rx_store), 0);
+    let invoices = rx_store.get_entities(InvoiceContext::INVOICE, &vec!["i2"]).await;
+    assert_eq!(invoices.len(), 1);
+
+    let action = RxAction::new_update_action("Invoice", &vec![Invoice::new_with_id("i3", -1)]);
+    match rx_store.execute_action(action).await {
+        RxResponse::Failure(message) => assert!(message.contains("Unable to send i3")),
+        response => panic!("Unexpected response {:?}", response),
+    }
+
+    // The change hooks run first, their failure skips the update hooks
+    rx_store.save_entities(&vec![Invoice::new_with_id("i4", 10)]).await.unwrap();
+    assert_eq!(archived(&rx_store), 1);
+    let res = rx_store.save_entities(&vec![Invoice::new_with_id("i4", 0)]).await;
+    match res {
+        Err(RxError::Hooks(report)) => assert_eq!(report.failures[0].hook, "check_invoice"),
+        _ => panic!("Expected a hook failure"),
+    }
+    assert_eq!(archived(&rx_store), 1);
+    let invoices = rx_store.get_entities(InvoiceContext::INVOICE, &vec!["i4"]).await;
+    assert_eq!(invoices[0].amount, 0);
+    rx_store.close().await;
+}
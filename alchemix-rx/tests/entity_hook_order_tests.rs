@@ -0,0 +1,65 @@
+use std::{sync::Mutex, time::Duration};
+
+use alchemix_rx::prelude::*;
+
+#[entity]
+pub struct Order {
+    amount: u32,
+}
+
+#[rx_context(Order)]
+pub struct OrderContext {
+    events: Mutex<Vec<String>>,
+}
+
+impl OrderContext {
+    async fn record(&self, hook: &str, delay: u64) {
+        self.events.lock().unwrap().push(format!("{} start", hook));
+        tokio::time::sleep(Duration::from_millis(delay)).await;
+        self.events.lock().unwrap().push(format!("{} end", hook));
+    }
+}
+
+#[rx_entity_update(Order, priority = 10, mode = sequential)]
+async fn update_counter(_value: &Vec<Order>, _store: &RxStore, context: &OrderContext) {
+    context.record("counter", 30).await;
+}
+
+#[rx_entity_update(Order)]
+async fn notify_email(_value: &Vec<Order>, _store: &RxStore, context: &OrderContext) {
+    context.record("email", 20).await;
+}
+
+#[rx_entity_update(Order, mode = parallel)]
+async fn notify_push(_value: &Vec<Order>, _store: &RxStore, context: &OrderContext) {
+    context.record("push", 20).await;
+}
+
+#[rx_entity_update(Order, priority = -5, mode = sequential)]
+async fn audit_order(_value: &Vec<Order>, _store: &RxStore, context: &OrderContext) {
+    context.record("audit", 0).await;
+}
+
+#[tokio::test]
+pub async fn test_hook_stages() {
+    let context = OrderContext {
+        events: Mutex::new(vec![]),
+    };
+    let mut rx_store = RxStore::new(context, "test-data/out/entity-hook-order.db")
+        .with_entity_hooks(entity_hooks!(notify_email, audit_order, update_counter, notify_push));
+    rx_store.open().await;
+    rx_store.clear().await;
+
+    rx_store.save_entities(&vec![Order::new(42)]).await.unwrap();
+
+    let events = rx_store.get_context::<OrderContext>().events.lock().unwrap().clone();
+    assert_eq!(events.len(), 8);
+    assert_eq!(events[..2], ["counter start", "counter end"]);
+    // Parallel hooks of the same priority start together
+    assert_eq!(events[2..4], ["email start", "push start"]);
+    assert!(events[4..6].contains(&"email end".to_string()));
+    assert!(events[4..6].contains(&"push end".to_string()));
+    assert_eq!(events[6..], ["audit start", "audit end"]);
+
+    rx_store.close().await;
+}
@@ -0,0 +1,139 @@
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use alchemix_rx::prelude::*;
+
+#[entity]
+pub struct Order {
+    delay_ms: u64,
+}
+
+#[entity]
+pub struct CountOrders {
+    _all: usize,
+}
+
+#[entity]
+pub struct OrderCount {
+    count: usize,
+}
+
+#[rx_context(Order, CountOrders, OrderCount)]
+pub struct OrderContext {
+    audited: AtomicUsize,
+}
+
+#[rx_entity_update(Order)]
+async fn audit_order(value: &Vec<Order>, _store: &RxStore, context: &OrderContext) -> Result<(), String> {
+    for order in value {
+        tokio::time::sleep(Duration::from_millis(order.delay_ms)).await;
+    }
+    context.audited.fetch_add(value.len(), Ordering::SeqCst);
+    Ok(())
+}
+
+#[rx_signal_handler]
+async fn count_orders(_value: &CountOrders, store: &RxStore) -> Result<OrderCount, String> {
+    let orders = store.get_entities(OrderContext::ORDER, &vec![]).await;
+    Ok(OrderCount::new(orders.len()))
+}
+
+#[rx_signal_handler]
+async fn count_no_orders(_value: &CountOrders, _store: &RxStore) -> Result<OrderCount, String> {
+    Ok(OrderCount::new(0))
+}
+
+async fn create_store(name: &str) -> RxStore {
+    let context = OrderContext {
+        audited: AtomicUsize::new(0),
+    };
+    let db_path = format!("test-data/out/entity-hook-registry-{}.db", name);
+    let mut rx_store = RxStore::new(context, &db_path);
+    rx_store.open().await;
+    rx_store.clear().await;
+    rx_store
+}
+
+fn audited(rx_store: &RxStore) -> usize {
+    rx_store.get_context::<OrderContext>().audited.load(Ordering::SeqCst)
+}
+
+#[tokio::test]
+pub async fn test_register_entity_hooks() {
+    let rx_store = create_store("entity").await;
+
+    rx_store.save_entities(&vec![Order::new(0)]).await.unwrap();
+    assert_eq!(audited(&rx_store), 0);
+
+    let handle = rx_store.register_entity_hooks(entity_hooks!(audit_order));
+    rx_store.save_entities(&vec![Order::new(0), Order::new(0)]).await.unwrap();
+    assert_eq!(audited(&rx_store), 2);
+
+    assert!(rx_store.unregister_hooks(handle));
+    assert!(!rx_store.unregister_hooks(handle));
+    rx_store.save_entities(&vec![Order::new(0)]).await.unwrap();
+    assert_eq!(audited(&rx_store), 2);
+    rx_store.close().await;
+}
+
+#[tokio::test]
+pub async fn test_unregister_during_dispatch() {
+    let rx_store = create_store("in-flight").await;
+    let handle = rx_store.register_entity_hooks(entity_hooks!(audit_order));
+
+    // The save started before the removal still runs the hook
+    let orders = vec![Order::new(100)];
+    let (saved, removed) = tokio::join!(rx_store.save_entities(&orders), async {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        rx_store.unregister_hooks(handle)
+    });
+    saved.unwrap();
+    assert!(removed);
+    assert_eq!(audited(&rx_store), 1);
+
+    rx_store.save_entities(&vec![Order::new(0)]).await.unwrap();
+    assert_eq!(audited(&rx_store), 1);
+    rx_store.close().await;
+}
+
+#[tokio::test]
+pub async fn test_register_signal_hooks() {
+    let rx_store = create_store("signal").await;
+    rx_store.save_entities(&vec![Order::new(0), Order::new(0)]).await.unwrap();
+
+    let result: Result<OrderCount, String> = rx_store.signal(CountOrders::new(0)).await;
+    assert!(result.is_err());
+
+    let handle = rx_store.register_signal_hooks(signal_hooks!(count_orders));
+    let result: OrderCount = rx_store.signal(CountOrders::new(0)).await.unwrap();
+    assert_eq!(result.count, 2);
+
+    assert!(rx_store.unregister_hooks(handle));
+    let result: Result<OrderCount, String> = rx_store.signal(CountOrders::new(0)).await;
+    assert!(result.is_err());
+    rx_store.close().await;
+}
+
+#[tokio::test]
+pub async fn test_replace_signal_hooks() {
+    let rx_store = create_store("signal-replace").await;
+    rx_store.save_entities(&vec![Order::new(0), Order::new(0)]).await.unwrap();
+
+    let first = rx_store.register_signal_hooks(signal_hooks!(count_orders));
+    let second = rx_store.register_signal_hooks(signal_hooks!(count_no_orders));
+    let result: OrderCount = rx_store.signal(CountOrders::new(0)).await.unwrap();
+    assert_eq!(result.count, 0);
+
+    // Removing the replacement restores the previous handler
+    assert!(rx_store.unregister_hooks(second));
+    let result: OrderCount = rx_store.signal(CountOrders::new(0)).await.unwrap();
+    assert_eq!(result.count, 2);
+    assert!(!rx_store.unregister_hooks(second));
+
+    assert!(rx_store.unregister_hooks(first));
+    let result: Result<OrderCount, String> = rx_store.signal(CountOrders::new(0)).await;
+    assert!(result.is_err());
+    rx_store.close().await;
+}
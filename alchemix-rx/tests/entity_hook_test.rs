@@ -46,7 +46,8 @@ async fn long_save(value: &Vec<User>, store: &RxStore) {
     // context.hello();
     store
         .save_entities(&vec![TestEntity::new(value.len())])
-        .await;
+        .await
+        .unwrap();
     println!("long add Complete");
 }
 
@@ -85,14 +86,14 @@ async fn add_users(
     store: &RxStore,
     _context: &AppContext,
 ) -> Result<AddUsers, String> {
-    store.save_entities(&value.users).await;
+    store.save_entities(&value.users).await.unwrap();
     Ok(value.clone())
 }
 
 #[tokio::test]
 pub async fn test_hooks() {
     println!("Start");
-    let mut dispatcher = Dispatcher::new();
+    let dispatcher = Dispatcher::new();
     dispatcher.register_entity_hooks(entity_hooks!(on_save, long_save, on_delete, on_derive_data));
 
     let context = AppContext {
@@ -155,7 +156,7 @@ pub async fn test_reactive_store() {
 
     let user = User::new("user_1".to_string(), 1, vec![]);
 
-    rx_store.save_entities(&vec![user.clone()]).await;
+    rx_store.save_entities(&vec![user.clone()]).await.unwrap();
 
     let users = rx_store.get_entities(AppContext::USER, &vec![]).await;
     assert_eq!(users.len(), 1);
@@ -186,7 +187,8 @@ pub async fn test_reactive_store() {
 
     rx_store
         .delete_entities(AppContext::USER, &vec![user.id.as_str()])
-        .await;
+        .await
+        .unwrap();
 
     let res = rx_store
         .execute_action(RxAction::new_query_property(
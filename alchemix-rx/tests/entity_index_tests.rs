@@ -0,0 +1,69 @@
+use alchemix_rx::prelude::*;
+
+#[entity_part]
+pub struct Address {
+    city: String,
+    zip_code: u32,
+}
+
+#[entity(index(name), index(address.city), index(tags))]
+pub struct Site {
+    name: String,
+    address: Address,
+    tags: Vec<String>,
+}
+
+fn create_site(name: &str, city: &str, tags: Vec<&str>) -> Site {
+    Site::new_with_id(
+        name,
+        name.to_string(),
+        Address {
+            city: city.to_string(),
+            zip_code: 75000,
+        },
+        tags.iter().map(|tag| tag.to_string()).collect(),
+    )
+}
+
+#[tokio::test]
+pub async fn test_nested_and_multi_valued_index() {
+    let datastore = SQLiteEntityStore::new("./test-data/out/entity-index.db");
+    datastore.clear().await;
+
+    let sites = vec![
+        create_site("Louvre", "Paris", vec!["museum", "art"]),
+        create_site("Orsay", "Paris", vec!["museum"]),
+        create_site("Confluences", "Lyon", vec!["museum", "science"]),
+    ];
+    datastore.update_entities(&sites).await.unwrap();
+
+    let site_index = sites[0].get_fields_index();
+    assert_eq!(site_index.len(), 4);
+
+    let paris_sites: Vec<Site> = datastore
+        .query_entities("Site", "address.city", "value = 'Paris'")
+        .await;
+    assert_eq!(paris_sites.len(), 2);
+
+    let museums: Vec<Site> = datastore
+        .query_entities("Site", "tags", "value = 'museum'")
+        .await;
+    assert_eq!(museums.len(), 3);
+
+    let by_name: Vec<Site> = datastore
+        .query_entities("Site", "name", "value = 'Orsay'")
+        .await;
+    assert_eq!(by_name.len(), 1);
+
+    // Index rows of removed tags are dropped when the entity is saved again
+    datastore
+        .update_entities(&vec![create_site("Louvre", "Paris", vec!["art"])])
+        .await
+        .unwrap();
+    let museums: Vec<Site> = datastore
+        .query_entities("Site", "tags", "value = 'museum'")
+        .await;
+    assert_eq!(museums.len(), 2);
+
+    datastore.close().await;
+}
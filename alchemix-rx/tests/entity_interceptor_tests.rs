@@ -0,0 +1,232 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use alchemix_rx::prelude::*;
+
+#[entity]
+pub struct Note {
+    text: String,
+}
+
+#[entity]
+pub struct CountNotes {
+    offset: usize,
+}
+
+#[entity]
+pub struct NoteCount {
+    count: usize,
+}
+
+#[rx_context(Note, CountNotes, NoteCount)]
+pub struct NoteContext {}
+
+#[rx_signal_handler]
+async fn count_notes(value: &CountNotes, store: &RxStore) -> Result<NoteCount, String> {
+    let notes = store.get_entities(NoteContext::NOTE, &vec!["n1", "n2"]).await;
+    Ok(NoteCount::new(value.offset + notes.len()))
+}
+
+/// Records the actions seen by `before` and `after`
+#[derive(Default)]
+struct ActionRecorder {
+    entries: Mutex<Vec<String>>,
+}
+
+#[async_trait]
+impl RxInterceptor for ActionRecorder {
+    async fn before(&self, _store: &RxStore, action: &mut RxAction) -> Option<RxResponse> {
+        self.entries.lock().unwrap().push(format!("before {}", action_name(action)));
+        None
+    }
+
+    async fn after(&self, _store: &RxStore, action: &RxAction, response: RxResponse) -> RxResponse {
+        self.entries.lock().unwrap().push(format!("after {}", action_name(action)));
+        response
+    }
+}
+
+fn action_name(action: &RxAction) -> &'static str {
+    match action {
+        RxAction::UpdateEntities(..) => "update",
+        RxAction::DeleteEntities(..) => "delete",
+        RxAction::QueryIds(..) => "query",
+        RxAction::Signal(..) => "signal",
+        _ => "other",
+    }
+}
+
+struct ReadOnly;
+
+#[async_trait]
+impl RxInterceptor for ReadOnly {
+    async fn before(&self, _store: &RxStore, action: &mut RxAction) -> Option<RxResponse> {
+        match action {
+            RxAction::DeleteEntities(..) => Some(RxResponse::Failure("Not allowed".to_string())),
+            _ => None,
+        }
+    }
+}
+
+/// Trims the text of the notes being updated
+struct TrimNotes;
+
+#[async_trait]
+impl RxInterceptor for TrimNotes {
+    async fn before(&self, _store: &RxStore, action: &mut RxAction) -> Option<RxResponse> {
+        if let RxAction::UpdateEntities(kind, Value::Array(values)) = action {
+            if kind == "Note" {
+                for value in values {
+                    if let Some(Value::String(text)) = value.get_mut("text") {
+                        *text = text.trim().to_string();
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+#[derive(Default)]
+struct QueryCache {
+    responses: Mutex<HashMap<String, RxResponse>>,
+    hits: AtomicUsize,
+}
+
+#[async_trait]
+impl RxInterceptor for QueryCache {
+    async fn before(&self, _store: &RxStore, action: &mut RxAction) -> Option<RxResponse> {
+        if !matches!(action, RxAction::QueryIds(..)) {
+            return None;
+        }
+        let key = serde_json::to_string(action).unwrap();
+        let response = self.responses.lock().unwrap().get(&key).cloned();
+        if response.is_some() {
+            self.hits.fetch_add(1, Ordering::SeqCst);
+        }
+        response
+    }
+
+    async fn after(&self, _store: &RxStore, action: &RxAction, response: RxResponse) -> RxResponse {
+        if matches!(action, RxAction::QueryIds(..)) {
+            let key = serde_json::to_string(action).unwrap();
+            self.responses.lock().unwrap().insert(key, response.clone());
+        }
+        response
+    }
+}
+
+/// Adds one to the offset of the note count signals
+struct ShiftCount;
+
+#[async_trait]
+impl RxInterceptor for ShiftCount {
+    async fn before(&self, _store: &RxStore, action: &mut RxAction) -> Option<RxResponse> {
+        if let RxAction::Signal(value) = action {
+            if value.get("kind") == Some(&Value::String("CountNotes".to_string())) {
+                value["offset"] = Value::from(value["offset"].as_u64().unwrap() + 1);
+            }
+        }
+        None
+    }
+}
+
+async fn create_store<I: RxInterceptor + 'static>(name: &str, interceptor: I) -> RxStore {
+    let db_path = format!("test-data/out/entity-interceptors-{}.db", name);
+    let mut rx_store = RxStore::new(NoteContext {}, &db_path)
+        .with_signal_hooks(signal_hooks!(count_notes))
+        .with_interceptor(interceptor);
+    rx_store.open().await;
+    rx_store.clear().await;
+    rx_store
+}
+
+#[tokio::test]
+pub async fn test_interceptor_order() {
+    let recorder = Arc::new(ActionRecorder::default());
+    let rx_store = create_store("order", recorder.clone()).await.with_interceptor(ReadOnly);
+
+    let note = Note::new("first".to_string());
+    let action = RxAction::new_update_action("Note", &vec![note.clone()]);
+    assert!(matches!(rx_store.execute_action(action).await, RxResponse::Success()));
+
+    // The delete is rejected before reaching the store
+    let action = RxAction::new_delete_action("Note", vec![note.id.clone()]);
+    match rx_store.execute_action(action).await {
+        RxResponse::Failure(message) => assert_eq!(message, "Not allowed"),
+        response => panic!("Unexpected response {:?}", response),
+    }
+    assert_eq!(rx_store.get_entities(NoteContext::NOTE, &vec![note.id.as_str()]).await.len(), 1);
+    assert_eq!(
+        *recorder.entries.lock().unwrap(),
+        vec!["before update", "after update", "before delete", "after delete"]
+    );
+    rx_store.close().await;
+}
+
+#[tokio::test]
+pub async fn test_interceptor_log() {
+    let recorder = Arc::new(ActionRecorder::default());
+    let rx_store = create_store("log", recorder.clone()).await.with_interceptor(ReadOnly);
+    rx_store.execute_action(RxAction::new_query_ids("Note", vec![])).await;
+    rx_store.execute_action(RxAction::new_delete_action("Note", vec![])).await;
+    let count: NoteCount = rx_store.signal(CountNotes::new(0)).await.unwrap();
+    assert_eq!(count.count, 0);
+
+    // Interceptors after a short circuit are skipped, the earlier ones still see the response
+    assert_eq!(
+        *recorder.entries.lock().unwrap(),
+        vec!["before query", "after query", "before delete", "after delete", "before signal", "after signal"]
+    );
+    rx_store.close().await;
+}
+
+#[tokio::test]
+pub async fn test_interceptor_sanitize() {
+    let rx_store = create_store("sanitize", TrimNotes).await;
+    let note = Note::new("  padded  ".to_string());
+    let action = RxAction::new_update_action("Note", &vec![note.clone()]);
+    assert!(matches!(rx_store.execute_action(action).await, RxResponse::Success()));
+
+    let notes = rx_store.get_entities(NoteContext::NOTE, &vec![note.id.as_str()]).await;
+    assert_eq!(notes[0].text, "padded");
+    rx_store.close().await;
+}
+
+#[tokio::test]
+pub async fn test_interceptor_cache() {
+    let cache = Arc::new(QueryCache::default());
+    let rx_store = create_store("cache", cache.clone()).await;
+    let note = Note::new("cached".to_string());
+    rx_store.save_entities(&vec![note.clone()]).await.unwrap();
+
+    for _ in 0..3 {
+        let action = RxAction::new_query_ids("Note", vec![note.id.clone()]);
+        match rx_store.execute_action(action).await {
+            RxResponse::QueryResponse(Value::Array(values)) => assert_eq!(values.len(), 1),
+            response => panic!("Unexpected response {:?}", response),
+        }
+    }
+    assert_eq!(cache.hits.load(Ordering::SeqCst), 2);
+    rx_store.close().await;
+}
+
+#[tokio::test]
+pub async fn test_signal_interceptor() {
+    let rx_store = create_store("signal", ShiftCount).await;
+    rx_store
+        .save_entities(&vec![Note::new_with_id("n1", "one".to_string())])
+        .await
+        .unwrap();
+
+    let count: NoteCount = rx_store.signal(CountNotes::new(10)).await.unwrap();
+    assert_eq!(count.count, 12);
+    let value = rx_store.signal_action(CountNotes::new(0)).await.unwrap();
+    assert_eq!(value["Ok"]["count"], 2);
+    rx_store.close().await;
+}
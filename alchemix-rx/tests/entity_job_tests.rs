@@ -0,0 +1,135 @@
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use alchemix_rx::prelude::*;
+
+#[entity]
+pub struct Upload {
+    size: usize,
+}
+
+#[rx_context(Upload)]
+pub struct UploadContext {
+    processed: AtomicUsize,
+}
+
+#[rx_entity_update(Upload, deferred)]
+async fn process_upload(value: &[Upload], _store: &RxStore, context: &UploadContext) -> Result<(), String> {
+    if value.iter().any(|upload| upload.size == 0) {
+        return Err("Empty upload".to_string());
+    }
+    context.processed.fetch_add(value.len(), Ordering::SeqCst);
+    Ok(())
+}
+
+async fn create_store(name: &str) -> RxStore {
+    let context = UploadContext {
+        processed: AtomicUsize::new(0),
+    };
+    let mut settings = JobSettings::new();
+    settings.max_attempts = 2;
+    settings.base_delay = Duration::ZERO;
+    settings.poll_interval = Duration::from_millis(10);
+    let db_path = format!("test-data/out/entity-jobs-{}.db", name);
+    let mut rx_store = RxStore::new(context, &db_path)
+        .with_entity_hooks(entity_hooks!(process_upload))
+        .with_job_settings(settings);
+    rx_store.open().await;
+    rx_store.clear().await;
+    rx_store
+}
+
+fn processed(rx_store: &RxStore) -> usize {
+    rx_store.get_context::<UploadContext>().processed.load(Ordering::SeqCst)
+}
+
+#[test]
+pub fn test_retry_delay() {
+    let settings = JobSettings::new();
+    assert_eq!(settings.get_retry_delay(1), Duration::from_secs(1));
+    assert_eq!(settings.get_retry_delay(4), Duration::from_secs(8));
+    assert_eq!(settings.get_retry_delay(20), settings.max_delay);
+}
+
+#[tokio::test]
+pub async fn test_deferred_hooks() {
+    let rx_store = create_store("deferred").await;
+
+    // Deferred hooks are queued, the save does not wait for them
+    rx_store
+        .save_entities(&vec![Upload::new(10), Upload::new(20)])
+        .await
+        .unwrap();
+    assert_eq!(processed(&rx_store), 0);
+    let jobs = rx_store.get_jobs().await.unwrap();
+    assert_eq!(jobs.len(), 1);
+    assert_eq!((jobs[0].hook.as_str(), jobs[0].kind.as_str()), ("process_upload", "Upload"));
+
+    assert_eq!(rx_store.run_pending_jobs().await, 1);
+    assert_eq!(processed(&rx_store), 2);
+    assert!(rx_store.get_jobs().await.unwrap().is_empty());
+
+    // Failing jobs are retried then moved to the dead jobs
+    rx_store.save_entities(&vec![Upload::new(0)]).await.unwrap();
+    assert_eq!(rx_store.run_pending_jobs().await, 2);
+    assert!(rx_store.get_jobs().await.unwrap().is_empty());
+    let dead_jobs = rx_store.get_dead_jobs().await.unwrap();
+    assert_eq!(dead_jobs.len(), 1);
+    assert_eq!(dead_jobs[0].attempts, 2);
+    assert_eq!(dead_jobs[0].last_error.as_deref(), Some("Empty upload"));
+
+    rx_store.requeue_dead_job(dead_jobs[0].id).await.unwrap();
+    assert!(rx_store.get_dead_jobs().await.unwrap().is_empty());
+    let jobs = rx_store.get_jobs().await.unwrap();
+    assert_eq!((jobs[0].id, jobs[0].attempts), (dead_jobs[0].id, 0));
+    assert!(rx_store.requeue_dead_job(dead_jobs[0].id).await.is_err());
+
+    rx_store.close().await;
+}
+
+#[tokio::test]
+pub async fn test_job_workers() {
+    let rx_store = create_store("workers").await;
+    rx_store.start_jobs().await.unwrap();
+
+    rx_store.save_entities(&vec![Upload::new(10)]).await.unwrap();
+    rx_store.save_entities(&vec![Upload::new(20)]).await.unwrap();
+    for _ in 0..100 {
+        if processed(&rx_store) == 2 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(processed(&rx_store), 2);
+
+    rx_store.stop_jobs().await;
+    assert!(rx_store.get_jobs().await.unwrap().is_empty());
+    rx_store.close().await;
+}
+
+#[tokio::test]
+pub async fn test_jobs_queued_with_write() {
+    let db_path = "test-data/out/entity-jobs-atomic.db";
+    let rx_store = create_store("atomic").await;
+    // The job is inserted in the write transaction, a failing insert rolls back the save
+    let pool = sqlx::SqlitePool::connect(&format!("sqlite://{}", db_path)).await.unwrap();
+    sqlx::query("DROP TRIGGER IF EXISTS reject_job").execute(&pool).await.unwrap();
+    let trigger = "CREATE TRIGGER reject_job BEFORE INSERT ON jobs
+        BEGIN SELECT RAISE(ABORT, 'job rejected'); END";
+    sqlx::query(trigger).execute(&pool).await.unwrap();
+
+    let upload = Upload::new(10);
+    let res = rx_store.save_entities(&vec![upload.clone()]).await;
+    assert!(matches!(res, Err(RxError::Failure(message)) if message.contains("job rejected")));
+    assert!(rx_store.get_entities(UploadContext::UPLOAD, &vec![upload.get_id()]).await.is_empty());
+
+    sqlx::query("DROP TRIGGER reject_job").execute(&pool).await.unwrap();
+    rx_store.save_entities(&vec![upload.clone()]).await.unwrap();
+    assert_eq!(rx_store.get_entities(UploadContext::UPLOAD, &vec![upload.get_id()]).await.len(), 1);
+    assert_eq!(rx_store.get_jobs().await.unwrap().len(), 1);
+
+    pool.close().await;
+    rx_store.close().await;
+}
@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use alchemix_rx::prelude::*;
+
+#[entity(index(status))]
+pub struct Ticket {
+    status: String,
+    title: String,
+}
+
+#[rx_context(Ticket)]
+pub struct TicketContext {}
+
+#[flux_hook]
+pub async fn save_ticket(action: &Ticket, state: &FluxState, _context: &TicketFlux) -> HookResponse {
+    state.save("default", &vec![action.clone()]).unwrap();
+    HookResponse::ok()
+}
+
+#[flux_context(events(Ticket), hooks(save_ticket))]
+pub struct TicketFlux {}
+
+fn create_ticket(id: &str, status: &str, title: &str) -> Ticket {
+    Ticket::new_with_id(id, status.to_string(), title.to_string())
+}
+
+async fn next_diff(live_query: &mut LiveQuery<'_>) -> QueryDiff {
+    tokio::time::timeout(Duration::from_secs(5), live_query.next())
+        .await
+        .expect("No diff received")
+        .expect("Store dropped")
+}
+
+fn ids(values: &[Value]) -> Vec<&str> {
+    values.iter().map(|value| value["id"].as_str().unwrap()).collect()
+}
+
+#[tokio::test]
+pub async fn test_live_query() {
+    let mut rx_store = RxStore::new(TicketContext {}, "test-data/out/entity-live-query.db");
+    rx_store.open().await;
+    rx_store.clear().await;
+    rx_store
+        .save_entities(&vec![create_ticket("t1", "open", "Login"), create_ticket("t2", "closed", "Logout")])
+        .await
+        .unwrap();
+
+    let mut live_query = rx_store.subscribe_query("Ticket", "status", "value = 'open'").await;
+    assert_eq!(ids(&live_query.get_results()), vec!["t1"]);
+
+    rx_store.save_entities(&vec![create_ticket("t3", "open", "Signup")]).await.unwrap();
+    let diff = next_diff(&mut live_query).await;
+    assert_eq!(ids(&diff.added), vec!["t3"]);
+    assert!(diff.updated.is_empty() && diff.removed.is_empty());
+
+    // Changes outside of the results are not sent
+    rx_store.save_entities(&vec![create_ticket("t2", "closed", "Sign out")]).await.unwrap();
+    rx_store.save_entities(&vec![create_ticket("t1", "open", "Sign in")]).await.unwrap();
+    let diff = next_diff(&mut live_query).await;
+    assert_eq!(diff.updated[0]["title"], "Sign in");
+    assert!(diff.added.is_empty());
+
+    rx_store.save_entities(&vec![create_ticket("t3", "closed", "Signup")]).await.unwrap();
+    assert_eq!(next_diff(&mut live_query).await.removed, vec!["t3"]);
+
+    rx_store.delete_entities(TicketContext::TICKET, &vec!["t1"]).await.unwrap();
+    assert_eq!(next_diff(&mut live_query).await.removed, vec!["t1"]);
+    assert!(live_query.get_results().is_empty());
+
+    // Batches are sent once committed
+    let action = RxAction::new_batch(vec![RxAction::new_update_action(
+        "Ticket",
+        &vec![create_ticket("t4", "open", "Export"), create_ticket("t5", "open", "Import")],
+    )]);
+    rx_store.execute_action(action).await;
+    assert_eq!(ids(&next_diff(&mut live_query).await.added), vec!["t4", "t5"]);
+    rx_store.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+pub async fn test_flux_live_query() {
+    let root_path = "test-data/out/flux-live-query";
+    let _ = std::fs::remove_dir_all(root_path);
+    let flux = Flux::new(root_path, TicketFlux {});
+    flux.push(create_ticket("t1", "open", "Login")).await;
+
+    let query = StateQuery::new("default", "Ticket", "status", "value = 'open'");
+    let mut live_query = flux.subscribe_query(query).await;
+    assert_eq!(ids(&live_query.get_results()), vec!["t1"]);
+
+    flux.push(create_ticket("t2", "open", "Logout")).await;
+    assert_eq!(ids(&next_diff(&mut live_query).await.added), vec!["t2"]);
+    flux.push(create_ticket("t1", "closed", "Login")).await;
+    assert_eq!(next_diff(&mut live_query).await.removed, vec!["t1"]);
+}
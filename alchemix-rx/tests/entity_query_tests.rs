@@ -0,0 +1,95 @@
+use alchemix_rx::prelude::*;
+
+#[entity_part]
+pub struct Team {
+    name: String,
+}
+
+#[entity(index(name), index(rank), index(skills), index(team.name))]
+pub struct Player {
+    name: String,
+    rank: u32,
+    skills: Vec<String>,
+    team: Team,
+}
+
+#[rx_context(Player)]
+pub struct PlayerContext {}
+
+fn create_player(name: &str, rank: u32, skills: Vec<&str>, team: &str) -> Player {
+    Player::new_with_id(
+        name,
+        name.to_string(),
+        rank,
+        skills.iter().map(|skill| skill.to_string()).collect(),
+        Team {
+            name: team.to_string(),
+        },
+    )
+}
+
+fn names(players: &[Player]) -> Vec<&str> {
+    let mut names: Vec<&str> = players.iter().map(|p| p.name.as_str()).collect();
+    names.sort();
+    names
+}
+
+#[tokio::test]
+pub async fn test_typed_query() {
+    let db_path = "test-data/out/entity-query.db";
+    let mut rx_store = RxStore::new(PlayerContext {}, db_path);
+    rx_store.open().await;
+    rx_store.clear().await;
+
+    rx_store
+        .save_entities(&vec![
+            create_player("Alice", 5, vec!["attack", "defense"], "Red"),
+            create_player("Albert", 2, vec!["defense"], "Blue"),
+            create_player("Bob", 10, vec!["attack"], "Red"),
+            create_player("Carol", 3, vec![], "Blue"),
+        ])
+        .await
+        .unwrap();
+
+    let fields = Player::fields();
+
+    // Numbers are compared numerically, "10" > "3" would fail as text
+    let players = rx_store
+        .query_predicate(PlayerContext::PLAYER, &fields.rank.gt(3))
+        .await;
+    assert_eq!(names(&players), vec!["Alice", "Bob"]);
+
+    let predicate = fields.rank.gt(3).and(fields.name.starts_with("A"));
+    let players = rx_store.query_predicate(PlayerContext::PLAYER, &predicate).await;
+    assert_eq!(names(&players), vec!["Alice"]);
+
+    let predicate = fields.name.eq("Carol").or(fields.skills.eq("attack"));
+    let players = rx_store.query_predicate(PlayerContext::PLAYER, &predicate).await;
+    assert_eq!(names(&players), vec!["Alice", "Bob", "Carol"]);
+
+    let predicate = !fields.skills.eq("defense");
+    let players = rx_store.query_predicate(PlayerContext::PLAYER, &predicate).await;
+    assert_eq!(names(&players), vec!["Bob", "Carol"]);
+
+    let predicate = fields.team_name.eq("Blue").and(fields.rank.is_in(vec![2, 5]));
+    let players = rx_store.query_predicate(PlayerContext::PLAYER, &predicate).await;
+    assert_eq!(names(&players), vec!["Albert"]);
+
+    // LIKE wildcards in the prefix are matched literally
+    let players = rx_store
+        .query_predicate(PlayerContext::PLAYER, &fields.name.starts_with("%"))
+        .await;
+    assert!(players.is_empty());
+
+    // Prefixes are case sensitive
+    let players = rx_store
+        .query_predicate(PlayerContext::PLAYER, &fields.name.starts_with("al"))
+        .await;
+    assert!(players.is_empty());
+    let players = rx_store
+        .query_predicate(PlayerContext::PLAYER, &fields.name.starts_with("Al"))
+        .await;
+    assert_eq!(names(&players), vec!["Albert", "Alice"]);
+
+    rx_store.close().await;
+}
@@ -0,0 +1,177 @@
+use std::time::Duration;
+
+use alchemix_rx::prelude::*;
+
+#[entity(index(title))]
+pub struct Note {
+    title: String,
+    tags: Vec<String>,
+}
+
+#[rx_context(Note)]
+pub struct NoteContext {}
+
+fn create_note(id: &str, title: &str, tags: Vec<&str>) -> Note {
+    Note::new_with_id(
+        id,
+        title.to_string(),
+        tags.iter().map(|tag| tag.to_string()).collect(),
+    )
+}
+
+async fn create_node(name: &str) -> RxStore {
+    let db_path = format!("test-data/out/entity-replication-{}.db", name);
+    let mut rx_store = RxStore::new(NoteContext {}, &db_path).with_replication(name);
+    rx_store.open().await;
+    rx_store.clear().await;
+    rx_store
+}
+
+async fn get_note(rx_store: &RxStore, id: &str) -> Option<Note> {
+    rx_store
+        .get_entities(NoteContext::NOTE, &vec![id])
+        .await
+        .pop()
+}
+
+fn merge_tags(local: &Note, remote: &Note) -> Note {
+    let mut merged = remote.clone();
+    for tag in &local.tags {
+        if !merged.tags.contains(tag) {
+            merged.tags.push(tag.clone());
+        }
+    }
+    merged.tags.sort();
+    merged
+}
+
+#[tokio::test]
+pub async fn test_replication_last_writer_wins() {
+    let edge = create_node("edge").await;
+    let central = create_node("central").await;
+
+    edge.save_entities(&vec![create_note("n1", "Edge note", vec![])]).await.unwrap();
+    central
+        .save_entities(&vec![create_note("n2", "Central note", vec![])])
+        .await
+        .unwrap();
+
+    let report = edge.sync_with(&central, 1).await.unwrap();
+    assert_eq!(report.pulled.applied, 1);
+    assert_eq!(report.pushed.applied, 1);
+    assert!(get_note(&edge, "n2").await.is_some());
+    assert!(get_note(&central, "n1").await.is_some());
+
+    // Nothing new to exchange
+    let report = edge.sync_with(&central, 10).await.unwrap();
+    assert_eq!(report, SyncReport::default());
+
+    // An update on one side only is not a conflict
+    edge.save_entities(&vec![create_note("n2", "Edited on edge", vec![])])
+        .await
+        .unwrap();
+    let report = edge.sync_with(&central, 10).await.unwrap();
+    assert_eq!(report.pushed.conflicts, 0);
+    assert_eq!(get_note(&central, "n2").await.unwrap().title, "Edited on edge");
+
+    // Concurrent updates, the last write wins on both nodes
+    central
+        .save_entities(&vec![create_note("n1", "Central first", vec![])])
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    edge.save_entities(&vec![create_note("n1", "Edge last", vec![])])
+        .await
+        .unwrap();
+    let report = edge.sync_with(&central, 10).await.unwrap();
+    assert_eq!(report.pulled.conflicts, 1);
+    assert_eq!(get_note(&edge, "n1").await.unwrap().title, "Edge last");
+    assert_eq!(get_note(&central, "n1").await.unwrap().title, "Edge last");
+
+    // Deletions are replicated, and the query index follows
+    central.delete_entities(NoteContext::NOTE, &vec!["n1"]).await.unwrap();
+    edge.sync_with(&central, 10).await.unwrap();
+    assert!(get_note(&edge, "n1").await.is_none());
+    let notes = edge
+        .query_property(NoteContext::NOTE, "title", "value = 'Edge last'")
+        .await;
+    assert!(notes.is_empty());
+
+    edge.close().await;
+    central.close().await;
+}
+
+#[tokio::test]
+pub async fn test_replication_merge() {
+    let resolver = || MergeResolver::new().with_merge("Note", merge_tags);
+    let mut edge = RxStore::new(NoteContext {}, "test-data/out/entity-replication-merge-edge.db")
+        .with_replication("edge")
+        .with_conflict_resolver(resolver());
+    edge.open().await;
+    edge.clear().await;
+    let mut central =
+        RxStore::new(NoteContext {}, "test-data/out/entity-replication-merge-central.db")
+            .with_replication("central")
+            .with_conflict_resolver(resolver());
+    central.open().await;
+    central.clear().await;
+
+    central
+        .save_entities(&vec![create_note("n1", "Shared", vec!["todo"])])
+        .await
+        .unwrap();
+    edge.sync_with(&central, 10).await.unwrap();
+
+    edge.save_entities(&vec![create_note("n1", "Shared", vec!["todo", "edge"])])
+        .await
+        .unwrap();
+    central
+        .save_entities(&vec![create_note("n1", "Shared", vec!["todo", "central"])])
+        .await
+        .unwrap();
+
+    let report = edge.sync_with(&central, 10).await.unwrap();
+    assert_eq!(report.pulled.conflicts, 1);
+    // The merged revision follows the central one, it is not a conflict there
+    assert_eq!(report.pushed.conflicts, 0);
+    let expected_tags = vec!["central", "edge", "todo"];
+    assert_eq!(get_note(&edge, "n1").await.unwrap().tags, expected_tags);
+    assert_eq!(get_note(&central, "n1").await.unwrap().tags, expected_tags);
+
+    edge.close().await;
+    central.close().await;
+}
+
+mod strict {
+    use alchemix_rx::prelude::*;
+
+    /// Same kind as the outer `Note`, with a validated title
+    #[entity]
+    pub struct Note {
+        #[validate(length(min = 1))]
+        pub title: String,
+        pub tags: Vec<String>,
+    }
+
+    #[rx_context(Note)]
+    pub struct StrictContext {}
+}
+
+#[tokio::test]
+pub async fn test_replication_validation() {
+    let lax = create_node("lax").await;
+    let mut strict_node = RxStore::new(strict::StrictContext {}, "test-data/out/entity-replication-strict.db")
+        .with_replication("strict");
+    strict_node.open().await;
+    strict_node.clear().await;
+
+    lax.save_entities(&vec![create_note("n1", "", vec![]), create_note("n2", "Valid", vec![])])
+        .await
+        .unwrap();
+    let report = strict_node.sync_with(&lax, 10).await.unwrap();
+    assert_eq!(report.pulled.applied, 1);
+    assert_eq!(report.pulled.rejected, vec!["Note#n1".to_string()]);
+    let notes = strict_node.get_entities(strict::StrictContext::NOTE, &vec!["n1", "n2"]).await;
+    assert_eq!(notes.len(), 1);
+    assert_eq!(notes[0].title, "Valid");
+}
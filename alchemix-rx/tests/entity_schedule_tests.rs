@@ -0,0 +1,161 @@
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use alchemix_rx::prelude::*;
+
+#[entity]
+pub struct Cleanup {
+    max_age: u32,
+}
+
+#[entity]
+pub struct CleanupReport {
+    removed: usize,
+}
+
+#[entity]
+pub struct Rollup {
+    period: String,
+}
+
+#[rx_context(Cleanup, CleanupReport, Rollup)]
+pub struct MaintenanceContext {
+    cleanups: AtomicUsize,
+}
+
+#[rx_signal_handler]
+async fn cleanup(
+    _value: &Cleanup,
+    _store: &RxStore,
+    context: &MaintenanceContext,
+) -> Result<CleanupReport, String> {
+    let count = context.cleanups.fetch_add(1, Ordering::SeqCst);
+    Ok(CleanupReport::new(count))
+}
+
+fn create_store(path: &str, interval: Duration) -> RxStore {
+    let context = MaintenanceContext {
+        cleanups: AtomicUsize::new(0),
+    };
+    RxStore::new(context, path)
+        .with_signal_hooks(signal_hooks!(cleanup))
+        .with_scheduled_signal("cleanup", Schedule::every(interval).unwrap(), Cleanup::new(30))
+        .unwrap()
+        // No handler for rollups, their runs fail
+        .with_scheduled_signal("rollup", Schedule::every(interval).unwrap(), Rollup::new("hour".to_string()))
+        .unwrap()
+}
+
+fn cleanups(rx_store: &RxStore) -> usize {
+    rx_store.get_context::<MaintenanceContext>().cleanups.load(Ordering::SeqCst)
+}
+
+#[test]
+pub fn test_schedule_next_run() {
+    assert!(Schedule::cron("every night").is_err());
+
+    // 2024-01-01 12:00:00 UTC, then every day at 3:00
+    let schedule = Schedule::cron("0 0 3 * * *").unwrap();
+    assert_eq!(schedule.next_after(1_704_110_400_000), Some(1_704_164_400_000));
+
+    let schedule = Schedule::every(Duration::from_secs(60)).unwrap();
+    assert_eq!(schedule.next_after(1_000), Some(61_000));
+
+    // Zero intervals would make the scheduler spin
+    assert!(Schedule::every(Duration::ZERO).is_err());
+    assert!(Schedule::every(Duration::from_micros(500)).is_err());
+}
+
+#[tokio::test]
+pub async fn test_scheduled_signals() {
+    let db_path = "test-data/out/entity-schedules.db";
+    let mut rx_store = create_store(db_path, Duration::from_millis(50));
+    rx_store.open().await;
+    rx_store.clear().await;
+
+    // The first pass only plans the next runs
+    assert!(rx_store.run_due_schedules().await.unwrap().is_empty());
+    tokio::time::sleep(Duration::from_millis(60)).await;
+    let runs = rx_store.run_due_schedules().await.unwrap();
+    assert_eq!(runs.len(), 2);
+    assert!(runs[0].success);
+    assert_eq!(runs[0].schedule, "cleanup");
+    assert!(!runs[1].success);
+    assert_eq!(cleanups(&rx_store), 1);
+    assert!(rx_store.run_due_schedules().await.unwrap().is_empty());
+    rx_store.close().await;
+
+    // Several missed runs are caught up once after a restart
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let mut rx_store = create_store(db_path, Duration::from_millis(50));
+    rx_store.open().await;
+    let runs = rx_store.run_due_schedules().await.unwrap();
+    assert_eq!(runs.len(), 2);
+    assert_eq!(cleanups(&rx_store), 1);
+
+    let runs = rx_store.get_scheduled_runs("cleanup").await;
+    assert_eq!(runs.len(), 2);
+    assert!(runs.iter().all(|run| run.success && run.started_at >= run.scheduled_at));
+    rx_store.close().await;
+}
+
+#[tokio::test]
+pub async fn test_zero_interval() {
+    let context = MaintenanceContext {
+        cleanups: AtomicUsize::new(0),
+    };
+    let rx_store = RxStore::new(context, "test-data/out/entity-schedule-zero.db");
+    let schedule = Schedule::Interval(Duration::ZERO);
+    assert!(rx_store.with_scheduled_signal("cleanup", schedule, Cleanup::new(30)).is_err());
+}
+
+#[tokio::test]
+pub async fn test_finished_schedule() {
+    // A cron expression with a year only matches once
+    let at = chrono::Utc::now() + chrono::Duration::seconds(2);
+    let expression = at.format("%S %M %H %d %m * %Y").to_string();
+    let create_store = || {
+        let context = MaintenanceContext {
+            cleanups: AtomicUsize::new(0),
+        };
+        RxStore::new(context, "test-data/out/entity-schedule-finished.db")
+            .with_signal_hooks(signal_hooks!(cleanup))
+            .with_scheduled_signal("once", Schedule::cron(&expression).unwrap(), Cleanup::new(30))
+            .unwrap()
+    };
+    let mut rx_store = create_store();
+    rx_store.open().await;
+    rx_store.clear().await;
+
+    assert!(rx_store.run_due_schedules().await.unwrap().is_empty());
+    let wait = at.timestamp_millis() - chrono::Utc::now().timestamp_millis() + 100;
+    tokio::time::sleep(Duration::from_millis(wait.max(0) as u64)).await;
+    assert_eq!(rx_store.run_due_schedules().await.unwrap().len(), 1);
+    assert!(rx_store.run_due_schedules().await.unwrap().is_empty());
+    rx_store.close().await;
+
+    // Still finished after a restart
+    let mut rx_store = create_store();
+    rx_store.open().await;
+    assert!(rx_store.run_due_schedules().await.unwrap().is_empty());
+    assert_eq!(rx_store.get_scheduled_runs("once").await.len(), 1);
+    rx_store.close().await;
+}
+
+#[tokio::test]
+pub async fn test_scheduler() {
+    let mut rx_store = create_store("test-data/out/entity-scheduler.db", Duration::from_millis(20));
+    rx_store.open().await;
+    rx_store.clear().await;
+
+    rx_store.start_scheduler().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    rx_store.stop_scheduler().await;
+
+    let count = cleanups(&rx_store);
+    assert!(count >= 2, "Expected several runs, got {}", count);
+    assert_eq!(rx_store.get_scheduled_runs("cleanup").await.len(), count);
+    rx_store.close().await;
+}
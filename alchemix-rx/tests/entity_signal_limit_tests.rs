@@ -0,0 +1,94 @@
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use alchemix_rx::prelude::*;
+
+#[entity]
+pub struct Export {
+    delay_ms: u64,
+}
+
+#[entity]
+pub struct ExportResult {
+    delay_ms: u64,
+}
+
+#[entity]
+pub struct Import {
+    delay_ms: u64,
+}
+
+#[rx_context(Export, ExportResult, Import)]
+pub struct ExportContext {
+    completed: AtomicUsize,
+}
+
+#[rx_signal_handler(timeout = "100ms")]
+async fn export(value: &Export, _store: &RxStore, context: &ExportContext) -> Result<ExportResult, String> {
+    tokio::time::sleep(Duration::from_millis(value.delay_ms)).await;
+    context.completed.fetch_add(1, Ordering::SeqCst);
+    Ok(ExportResult::new(value.delay_ms))
+}
+
+#[rx_signal_handler(max_concurrency = 1)]
+async fn import(value: &Import, _store: &RxStore) -> Result<ExportResult, String> {
+    tokio::time::sleep(Duration::from_millis(value.delay_ms)).await;
+    Ok(ExportResult::new(value.delay_ms))
+}
+
+fn create_store() -> RxStore {
+    let context = ExportContext {
+        completed: AtomicUsize::new(0),
+    };
+    RxStore::new(context, "test-data/out/entity-signal-limits.db").with_signal_hooks(signal_hooks!(export, import))
+}
+
+fn completed(rx_store: &RxStore) -> usize {
+    rx_store.get_context::<ExportContext>().completed.load(Ordering::SeqCst)
+}
+
+#[tokio::test]
+pub async fn test_signal_timeout() {
+    let mut rx_store = create_store();
+    rx_store.open().await;
+
+    let result: ExportResult = rx_store.signal(Export::new(10)).await.unwrap();
+    assert_eq!(result.delay_ms, 10);
+    assert_eq!(completed(&rx_store), 1);
+
+    // The handler is cancelled at the timeout
+    let result: Result<ExportResult, String> = rx_store.signal(Export::new(1_000)).await;
+    assert_eq!(result.unwrap_err(), "Signal Export timed out");
+    match rx_store.execute_action(RxAction::new_signal(Export::new(1_000))).await {
+        RxResponse::Timeout(message) => assert_eq!(message, "Signal Export timed out"),
+        response => panic!("Unexpected response {:?}", response),
+    }
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(completed(&rx_store), 1);
+    rx_store.close().await;
+}
+
+#[tokio::test]
+pub async fn test_signal_concurrency() {
+    let mut rx_store = create_store();
+    rx_store.open().await;
+
+    let (first, second) = tokio::join!(
+        rx_store.execute_action(RxAction::new_signal(Import::new(100))),
+        rx_store.execute_action(RxAction::new_signal(Import::new(0))),
+    );
+    assert!(matches!(first, RxResponse::SignalResponse(_)));
+    match second {
+        RxResponse::Overloaded(message) => assert_eq!(message, "Too many concurrent Import signals"),
+        response => panic!("Unexpected response {:?}", response),
+    }
+
+    // The permit is released with the handler
+    assert!(matches!(
+        rx_store.execute_action(RxAction::new_signal(Import::new(0))).await,
+        RxResponse::SignalResponse(_)
+    ));
+    rx_store.close().await;
+}
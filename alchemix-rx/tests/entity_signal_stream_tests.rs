@@ -0,0 +1,162 @@
+use std::time::Duration;
+
+use alchemix_rx::prelude::*;
+
+#[entity]
+pub struct ExportRows {
+    count: usize,
+}
+
+#[entity]
+pub struct Row {
+    index: usize,
+}
+
+#[entity]
+pub struct Progress {
+    percent: u8,
+}
+
+#[entity]
+pub struct SlowRows {
+    delay_ms: u64,
+}
+
+#[entity]
+pub struct FlakyRows {
+    count: u8,
+}
+
+#[rx_context(ExportRows, Row, Progress, SlowRows, FlakyRows)]
+pub struct ReportContext {
+    step: Duration,
+}
+
+#[rx_stream_handler]
+fn export_rows<'a>(value: ExportRows, _store: &'a RxStore) -> impl Stream<Item = Row> + Send + 'a {
+    futures::stream::iter((0..value.count).map(Row::new))
+}
+
+#[rx_stream_handler]
+fn progress<'a>(_value: Progress, _store: &'a RxStore, context: &'a ReportContext) -> impl Stream<Item = Progress> + Send + 'a {
+    futures::stream::unfold(0u8, move |percent| async move {
+        if percent > 100 {
+            return None;
+        }
+        tokio::time::sleep(context.step).await;
+        Some((Progress::new(percent), percent + 50))
+    })
+}
+
+// Rows after the first one wait `delay_ms`
+#[rx_stream_handler(timeout = "100ms", max_concurrency = 1)]
+fn slow_rows<'a>(value: SlowRows, _store: &'a RxStore) -> impl Stream<Item = Row> + Send + 'a {
+    futures::stream::unfold(0usize, move |index| async move {
+        if index == 3 {
+            return None;
+        }
+        if index > 0 {
+            tokio::time::sleep(Duration::from_millis(value.delay_ms)).await;
+        }
+        Some((Row::new(index), index + 1))
+    })
+}
+
+// The row 2 can not be serialized
+struct FlakyRow(u8);
+
+impl Serialize for FlakyRow {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.0 == 2 {
+            return Err(serde::ser::Error::custom("flaky row"));
+        }
+        serializer.serialize_u8(self.0)
+    }
+}
+
+#[rx_stream_handler]
+fn flaky_rows<'a>(value: FlakyRows, _store: &'a RxStore) -> impl Stream<Item = FlakyRow> + Send + 'a {
+    futures::stream::iter((0..value.count).map(FlakyRow))
+}
+
+struct NoExports;
+
+#[async_trait]
+impl RxInterceptor for NoExports {
+    async fn before(&self, _store: &RxStore, action: &mut RxAction) -> Option<RxResponse> {
+        match action {
+            RxAction::Signal(signal) if signal["kind"] == "ExportRows" && signal["count"] == 0 => {
+                Some(RxResponse::Failure("Empty export".to_string()))
+            }
+            _ => None,
+        }
+    }
+
+    async fn after(&self, _store: &RxStore, action: &RxAction, response: RxResponse) -> RxResponse {
+        match action {
+            RxAction::Signal(signal) if signal["kind"] == "ExportRows" && signal["count"] == 1000 => {
+                RxResponse::Failure("Export too large".to_string())
+            }
+            _ => response,
+        }
+    }
+}
+
+#[tokio::test]
+pub async fn test_signal_stream() {
+    let context = ReportContext {
+        step: Duration::from_millis(100),
+    };
+    let mut rx_store = RxStore::new(context, "test-data/out/entity-signal-stream.db")
+        .with_stream_hooks(stream_hooks!(export_rows, progress, slow_rows, flaky_rows))
+        .with_interceptor(NoExports);
+    rx_store.open().await;
+
+    let stream = rx_store.signal_stream(ExportRows::new(3)).await.unwrap();
+    let rows: Vec<Value> = stream.collect().await;
+    let indexes: Vec<&Value> = rows.iter().map(|row| &row["index"]).collect();
+    assert_eq!(indexes, vec![0, 1, 2]);
+
+    // Items are received while the handler still runs
+    let mut stream = rx_store.signal_stream(Progress::new(0)).await.unwrap();
+    let first = tokio::time::timeout(Duration::from_millis(180), stream.next()).await.unwrap();
+    assert_eq!(first.unwrap()["percent"], 0);
+    let percents: Vec<Value> = stream.map(|progress| progress["percent"].clone()).collect().await;
+    assert_eq!(percents, vec![50, 100]);
+
+    assert!(matches!(rx_store.signal_stream(ExportRows::new(0)).await, Err(RxResponse::Failure(_))));
+    let response = rx_store.signal_stream(ExportRows::new(1000)).await;
+    assert!(matches!(response, Err(RxResponse::Failure(message)) if message == "Export too large"));
+    let signal = json!({"kind": "Unknown", "id": "u1"});
+    assert!(rx_store.signal_stream_value(signal).await.is_err());
+    rx_store.close().await;
+}
+
+#[tokio::test]
+pub async fn test_signal_stream_limits() {
+    let context = ReportContext {
+        step: Duration::from_millis(100),
+    };
+    let mut rx_store = RxStore::new(context, "test-data/out/entity-signal-stream-limits.db")
+        .with_stream_hooks(stream_hooks!(slow_rows, flaky_rows));
+    rx_store.open().await;
+
+    let rows: Vec<Value> = rx_store.signal_stream(SlowRows::new(0)).await.unwrap().collect().await;
+    assert_eq!(rows.len(), 3);
+
+    // The stream ends when the next item takes longer than the timeout
+    let rows: Vec<Value> = rx_store.signal_stream(SlowRows::new(300)).await.unwrap().collect().await;
+    assert_eq!(rows.len(), 1);
+
+    // A running stream holds its permit until it is dropped
+    let stream = rx_store.signal_stream(SlowRows::new(0)).await.unwrap();
+    let response = rx_store.signal_stream(SlowRows::new(0)).await;
+    assert!(matches!(response, Err(RxResponse::Overloaded(_))));
+    drop(stream);
+    assert!(rx_store.signal_stream(SlowRows::new(0)).await.is_ok());
+
+    // The stream ends at the first item which can not be serialized
+    let rows: Vec<Value> = rx_store.signal_stream(FlakyRows::new(4)).await.unwrap().collect().await;
+    assert_eq!(rows, vec![json!(0), json!(1)]);
+    rx_store.close().await;
+}
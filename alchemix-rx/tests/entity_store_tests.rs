@@ -1,3 +1,4 @@
+use alchemix_utils::file_io;
 use alchemix_rx::prelude::*;
 
 #[entity(index(name), index(rank))]
@@ -45,6 +46,112 @@ pub async fn test_entity_store() {
     assert_eq!(users.len(), 3);
 
     let keys = vec!["User_2", "User_5"];
-    datastore.remove_entities::<User>("User", &keys).await;
+    datastore.remove_entities::<User>("User", &keys).await.unwrap();
+    datastore.close().await;
+}
+
+#[tokio::test]
+pub async fn test_backup_restore() {
+    let db_path = "./test-data/out/backup/store.db";
+    let backup_path = "./test-data/out/backup/snapshot.db";
+    file_io::remove_dir("./test-data/out/backup");
+
+    let mut datastore = SQLiteEntityStore::new(db_path);
+    let users = vec![User::new_with_id("User_1", "User_1".to_string(), 1, vec![])];
+    datastore.update_entities(&users).await.unwrap();
+
+    assert!(datastore.backup_to(backup_path).await.is_ok());
+    assert!(datastore.backup_to(backup_path).await.is_err());
+
+    datastore.clear().await;
+    let users: Vec<User> = datastore.get_entities_of_kind("User", &vec![]).await;
+    assert_eq!(users.len(), 0);
+
+    assert!(datastore.restore_from(backup_path).is_err());
+    datastore.close().await;
+    assert!(datastore.restore_from(backup_path).is_ok());
+    let _ = datastore.open().await;
+
+    let users: Vec<User> = datastore.get_entities_of_kind("User", &vec![]).await;
+    assert_eq!(users.len(), 1);
+    datastore.close().await;
+}
+
+#[tokio::test]
+pub async fn test_entity_cache() {
+    let datastore = SQLiteEntityStore::new("./test-data/out/cache.db").with_cache(2);
+    datastore.clear().await;
+
+    let users: Vec<User> = (0..3)
+        .map(|i| User::new_with_id(&format!("User_{}", i), format!("User_{}", i), i, vec![]))
+        .collect();
+    datastore.update_entities(&users).await.unwrap();
+
+    let _: Vec<User> = datastore.get_entities_of_kind("User", &vec!["User_0", "User_1"]).await;
+    let cached: Vec<User> = datastore.get_entities_of_kind("User", &vec!["User_0", "User_1"]).await;
+    assert_eq!(cached.len(), 2);
+
+    let stats = datastore.cache_stats();
+    assert_eq!(stats[0].hits, 2);
+    assert_eq!(stats[0].misses, 2);
+    assert_eq!(stats[0].entries, 2);
+
+    // Loading a third entity evicts the least recently used one
+    let _: Vec<User> = datastore.get_entities_of_kind("User", &vec!["User_2"]).await;
+    assert_eq!(datastore.cache_stats()[0].entries, 2);
+
+    let updated = vec![User::new_with_id("User_2", "Updated".to_string(), 2, vec![])];
+    datastore.update_entities(&updated).await.unwrap();
+    let users: Vec<User> = datastore.get_entities_of_kind("User", &vec!["User_2"]).await;
+    assert_eq!(users[0].name, "Updated");
+
+    datastore.remove_entities::<User>("User", &vec!["User_2"]).await.unwrap();
+    let users: Vec<User> = datastore.get_entities_of_kind("User", &vec!["User_2"]).await;
+    assert_eq!(users.len(), 0);
+
+    // Requested order is kept and repeated ids are returned once
+    let users: Vec<User> = datastore.get_entities_of_kind("User", &vec!["User_1", "User_0", "User_1"]).await;
+    let names: Vec<&str> = users.iter().map(|user| user.name.as_str()).collect();
+    assert_eq!(names, vec!["User_1", "User_0"]);
+    datastore.close().await;
+}
+
+#[test]
+pub fn test_entity_cache_generation() {
+    let cache = EntityCache::new(4);
+    let stale = User::new_with_id("User_0", "Stale".to_string(), 0, vec![]);
+
+    // A load started before an invalidation is not cached
+    let generation = cache.get_generation("User");
+    cache.invalidate("User", &["User_0"]);
+    cache.insert_loaded("User", generation, std::slice::from_ref(&stale));
+    assert!(cache.get::<User>("User", "User_0").is_none());
+
+    cache.insert_loaded("User", cache.get_generation("User"), &[stale]);
+    assert!(cache.get::<User>("User", "User_0").is_some());
+}
+
+#[tokio::test]
+pub async fn test_store_stats() {
+    let datastore = SQLiteEntityStore::new("./test-data/out/stats.db");
+    datastore.clear().await;
+
+    let users: Vec<User> = (0..4)
+        .map(|i| User::new_with_id(&format!("User_{}", i), format!("User_{}", i), i, vec![0; 100]))
+        .collect();
+    datastore.update_entities(&users).await.unwrap();
+
+    let stats = datastore.stats().await.unwrap();
+    assert!(stats.file_size > 0);
+    assert_eq!(stats.links, 0);
+    assert_eq!(stats.kinds.len(), 1);
+
+    let user_stats = &stats.kinds[0];
+    assert_eq!(user_stats.kind, "User");
+    assert_eq!(user_stats.count, 4);
+    assert!(user_stats.average_size > 100.0);
+    assert_eq!(user_stats.total_size as f64, user_stats.average_size * 4.0);
+    assert_eq!(user_stats.indexed_properties, vec!["name", "rank"]);
+
     datastore.close().await;
 }
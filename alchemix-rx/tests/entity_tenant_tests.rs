@@ -0,0 +1,115 @@
+use alchemix_rx::prelude::*;
+use alchemix_utils::file_io;
+
+#[entity(index(name))]
+pub struct Customer {
+    name: String,
+}
+
+#[rx_context(Customer)]
+pub struct TenantContext {}
+
+#[tokio::test]
+pub async fn test_tenant_isolation() {
+    let db_path = "test-data/out/entity-tenant.db";
+    file_io::remove_dir("test-data/out/entity-tenant");
+    let mut rx_store = RxStore::new(TenantContext {}, db_path);
+    rx_store.open().await;
+    rx_store.clear().await;
+
+    let acme = rx_store.tenant("acme").await.unwrap();
+    let globex = rx_store.tenant("globex").await.unwrap();
+    assert_eq!(acme.get_tenant(), Some("acme"));
+    assert!(rx_store.tenant("../acme").await.is_err());
+    assert!(acme.tenant("nested").await.is_err());
+
+    acme.save_entities(&vec![Customer::new_with_id("c1", "Wile".to_string())])
+        .await
+        .unwrap();
+    globex
+        .save_entities(&vec![Customer::new_with_id("c2", "Hank".to_string())])
+        .await
+        .unwrap();
+
+    let customers = acme.get_entities(TenantContext::CUSTOMER, &vec![]).await;
+    assert_eq!(customers.len(), 1);
+    assert_eq!(customers[0].name, "Wile");
+    let customers = rx_store.get_entities(TenantContext::CUSTOMER, &vec![]).await;
+    assert!(customers.is_empty());
+    assert_eq!(rx_store.tenants(), vec!["acme", "globex"]);
+
+    // Export then delete a tenant
+    let export_path = "test-data/out/entity-tenant-export/acme.db";
+    file_io::remove_file(export_path);
+    acme.backup_to(export_path).await.unwrap();
+    acme.close().await;
+    rx_store.delete_tenant("acme").unwrap();
+    assert_eq!(rx_store.tenants(), vec!["globex"]);
+    assert!(rx_store.delete_tenant("acme").is_err());
+
+    let exported = SQLiteEntityStore::new(export_path);
+    let customers: Vec<Customer> = exported.get_entities_of_kind("Customer", &vec![]).await;
+    assert_eq!(customers.len(), 1);
+    exported.close().await;
+
+    globex.close().await;
+    rx_store.close().await;
+}
+
+#[tokio::test]
+pub async fn test_flux_state_tenant() {
+    let state = FluxState::new("test-data/out/flux-tenant");
+    file_io::remove_dir("test-data/out/flux-tenant");
+
+    let acme = state.for_tenant("acme").unwrap();
+    acme.save("main", &vec![Customer::new_with_id("c1", "Wile".to_string())]).unwrap();
+    assert!(state.for_tenant("").is_err());
+    assert_eq!(state.tenants(), vec!["acme"]);
+    assert!(state.shards().is_empty());
+
+    let customers = acme.get_entities_of_kind("main", &TenantContext::CUSTOMER, &vec![]);
+    assert_eq!(customers.len(), 1);
+
+    // The backup includes the shards of the tenants
+    let backup_dir = "test-data/out/flux-tenant-backup";
+    file_io::remove_dir(backup_dir);
+    state.save("shared", &vec![Customer::new_with_id("c2", "Hank".to_string())]).unwrap();
+    let backups = state.backup_to(backup_dir).await.unwrap();
+    assert_eq!(
+        backups,
+        vec![
+            format!("{}/shared.db", backup_dir),
+            format!("{}/tenants/acme/main.db", backup_dir)
+        ]
+    );
+
+    // So do the statistics, by tenant
+    let stats = state.stats().await.unwrap();
+    assert_eq!(stats.shards.keys().collect::<Vec<_>>(), vec!["shared"]);
+    assert_eq!(stats.tenants.keys().collect::<Vec<_>>(), vec!["acme"]);
+    assert!(stats.tenants["acme"].contains_key("main"));
+
+    state.delete_tenant("acme").unwrap();
+    assert!(state.tenants().is_empty());
+}
+
+#[tokio::test]
+pub async fn test_tenant_hook_settings() {
+    let db_path = "test-data/out/entity-tenant-settings.db";
+    file_io::remove_dir("test-data/out/entity-tenant-settings");
+    let mut rx_store = RxStore::new(TenantContext {}, db_path);
+    rx_store.open().await;
+
+    // The hook settings can still be changed once tenant handles exist
+    let acme = rx_store.tenant("acme").await.unwrap();
+    let rx_store = rx_store
+        .with_hook_failure_policy(HookFailurePolicy::FailFast)
+        .with_max_hook_depth(2);
+    acme.save_entities(&vec![Customer::new_with_id("c1", "Wile".to_string())])
+        .await
+        .unwrap();
+    assert_eq!(acme.get_entities(TenantContext::CUSTOMER, &vec![]).await.len(), 1);
+
+    acme.close().await;
+    rx_store.close().await;
+}
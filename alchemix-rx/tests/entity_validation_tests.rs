@@ -0,0 +1,99 @@
+use alchemix_rx::prelude::*;
+
+#[entity(index(login), validate(check_account))]
+pub struct Account {
+    #[validate(length(min = 1, max = 16), regex = "^[a-z0-9_]+$")]
+    login: String,
+    #[validate(email)]
+    email: String,
+    #[validate(range(min = 0, max = 100))]
+    quantity: i32,
+    #[validate(length(max = 2))]
+    tags: Vec<String>,
+    #[validate(email)]
+    backup_email: Option<String>,
+}
+
+fn check_account(account: &Account, errors: &mut ValidationErrors) {
+    if account.login == "admin" {
+        errors.add(&account.id, "login", "reserved", "login is reserved");
+    }
+}
+
+#[rx_context(Account)]
+pub struct AccountContext {}
+
+#[flux_context(events(Account))]
+pub struct AccountFlux {}
+
+fn create_account(id: &str, login: &str, email: &str, quantity: i32) -> Account {
+    Account::new_with_id(id, login.to_string(), email.to_string(), quantity, vec![], None)
+}
+
+fn error_codes(errors: &ValidationErrors) -> Vec<(&str, &str)> {
+    errors
+        .errors
+        .iter()
+        .map(|error| (error.field.as_str(), error.code.as_str()))
+        .collect()
+}
+
+#[test]
+pub fn test_field_validation() {
+    assert!(create_account("a1", "wile_e", "wile@acme.com", 10).validate().is_ok());
+
+    let errors = create_account("a2", "", "wile.acme.com", -1).validate().unwrap_err();
+    assert_eq!(
+        error_codes(&errors),
+        vec![("login", "length"), ("login", "regex"), ("email", "email"), ("quantity", "range")]
+    );
+    assert!(errors.errors.iter().all(|error| error.entity_id == "a2"));
+
+    let mut account = create_account("a3", "admin", "admin@acme.com", 101);
+    account.tags = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+    account.backup_email = Some("admin@".to_string());
+    let errors = account.validate().unwrap_err();
+    assert_eq!(
+        error_codes(&errors),
+        vec![("quantity", "range"), ("tags", "length"), ("backup_email", "email"), ("login", "reserved")]
+    );
+}
+
+#[tokio::test]
+pub async fn test_store_validation() {
+    let mut rx_store = RxStore::new(AccountContext {}, "test-data/out/entity-validation.db");
+    rx_store.open().await;
+    rx_store.clear().await;
+
+    // A single invalid entity rejects the whole batch
+    let res = rx_store
+        .save_entities(&vec![
+            create_account("a1", "wile_e", "wile@acme.com", 10),
+            create_account("a2", "Road Runner", "beep@acme.com", 10),
+        ])
+        .await;
+    match res {
+        Err(RxError::Validation(errors)) => assert_eq!(error_codes(&errors), vec![("login", "regex")]),
+        _ => panic!("Expected a validation error"),
+    }
+    let accounts = rx_store.get_entities(AccountContext::ACCOUNT, &vec![]).await;
+    assert!(accounts.is_empty());
+
+    // JSON actions report the field errors
+    let action = RxAction::new_update_action("Account", &vec![create_account("a3", "wile_e", "wile", 10)]);
+    match rx_store.execute_action(action).await {
+        RxResponse::ValidationFailure(errors) => assert_eq!(error_codes(&errors), vec![("email", "email")]),
+        response => panic!("Unexpected response {:?}", response),
+    }
+
+    let action = RxAction::new_update_action("Account", &vec![create_account("a4", "wile_e", "wile@acme.com", 10)]);
+    assert!(matches!(rx_store.execute_action(action).await, RxResponse::Success()));
+    rx_store.close().await;
+
+    let flux = Flux::new("test-data/out/flux-validation", AccountFlux {});
+    let event = serde_json::to_value(create_account("a5", "", "wile@acme.com", 10)).unwrap();
+    let responses = flux.push_json(event).await;
+    assert_eq!(responses.len(), 1);
+    assert!(!responses[0].success);
+    assert_eq!(responses[0].errors[0].field, "login");
+}
@@ -0,0 +1,130 @@
+use alchemix_rx::prelude::*;
+
+#[entity(index(title), vector(embedding, dim = 3))]
+pub struct Document {
+    title: String,
+    embedding: Vec<f32>,
+}
+
+#[rx_context(Document)]
+pub struct DocumentContext {}
+
+fn create_documents() -> Vec<Document> {
+    vec![
+        Document::new_with_id("x", "x".to_string(), vec![1.0, 0.0, 0.0]),
+        Document::new_with_id("xy", "xy".to_string(), vec![0.7, 0.7, 0.0]),
+        Document::new_with_id("y", "y".to_string(), vec![0.0, 1.0, 0.0]),
+        Document::new_with_id("z", "z".to_string(), vec![0.0, 0.0, 2.0]),
+        Document::new_with_id("none", "none".to_string(), vec![]),
+        Document::new_with_id("invalid", "invalid".to_string(), vec![1.0, 0.0]),
+    ]
+}
+
+fn titles(matches: &[(Document, f32)]) -> Vec<&str> {
+    matches.iter().map(|(document, _)| document.title.as_str()).collect()
+}
+
+#[tokio::test]
+pub async fn test_exact_nearest() {
+    let mut rx_store = RxStore::new(DocumentContext {}, "test-data/out/entity-vector.db");
+    rx_store.open().await;
+    rx_store.clear().await;
+    rx_store.save_entities(&create_documents()).await.unwrap();
+
+    let matches = rx_store
+        .nearest(DocumentContext::DOCUMENT, &[1.0, 0.1, 0.0], 2, VectorMetric::Cosine)
+        .await;
+    assert_eq!(titles(&matches), vec!["x", "xy"]);
+
+    let matches = rx_store
+        .nearest(DocumentContext::DOCUMENT, &[0.0, 0.0, 1.0], 10, VectorMetric::Dot)
+        .await;
+    assert_eq!(matches.len(), 4);
+    assert_eq!(matches[0].1, 2.0);
+    rx_store.close().await;
+}
+
+#[tokio::test]
+pub async fn test_hnsw_nearest() {
+    let db_path = "test-data/out/entity-vector-hnsw.db";
+    let mut rx_store = RxStore::new(DocumentContext {}, db_path)
+        .with_vector_index(HnswSettings::new(VectorMetric::Cosine));
+    rx_store.open().await;
+    rx_store.clear().await;
+
+    let mut documents = create_documents();
+    for i in 0..200 {
+        let angle = i as f32 / 100.0;
+        let id = format!("doc_{}", i);
+        documents.push(Document::new_with_id(&id, id.clone(), vec![angle.cos(), angle.sin(), 0.1]));
+    }
+    rx_store.save_entities(&documents).await.unwrap();
+
+    let query = [1.5f32.cos(), 1.5f32.sin(), 0.1];
+    let matches = rx_store
+        .nearest(DocumentContext::DOCUMENT, &query, 1, VectorMetric::Cosine)
+        .await;
+    assert_eq!(titles(&matches), vec!["doc_150"]);
+
+    rx_store
+        .delete_entities(DocumentContext::DOCUMENT, &vec!["doc_150"])
+        .await
+        .unwrap();
+    let matches = rx_store
+        .nearest(DocumentContext::DOCUMENT, &query, 2, VectorMetric::Cosine)
+        .await;
+    assert_eq!(matches.len(), 2);
+    assert!(!titles(&matches).contains(&"doc_150"));
+
+    // The index is rebuilt from the stored vectors when the store is opened again
+    rx_store.close().await;
+    rx_store.open().await;
+    let matches = rx_store
+        .nearest(DocumentContext::DOCUMENT, &query, 1, VectorMetric::Cosine)
+        .await;
+    assert_eq!(matches.len(), 1);
+    assert!(["doc_149", "doc_151"].contains(&titles(&matches)[0]));
+    rx_store.close().await;
+}
+
+fn create_index(max_deleted_ratio: f32) -> HnswIndex {
+    let mut settings = HnswSettings::new(VectorMetric::Cosine);
+    settings.ef_search = 4;
+    settings.max_deleted_ratio = max_deleted_ratio;
+    let mut index = HnswIndex::new(settings);
+    for i in 0..200 {
+        let angle = i as f32 / 100.0;
+        index.insert(&format!("doc_{}", i), vec![angle.cos(), angle.sin(), 0.1]);
+    }
+    index
+}
+
+#[test]
+pub fn test_hnsw_tombstones() {
+    let query = [1.0f32.cos(), 1.0f32.sin(), 0.1];
+
+    // Without a rebuild the search is widened past the tombstones
+    let mut index = create_index(1.0);
+    for i in 60..140 {
+        index.remove(&format!("doc_{}", i));
+    }
+    assert_eq!(index.deleted_len(), 80);
+    let matches = index.search(&query, 10);
+    assert_eq!(matches.len(), 10);
+    let removed = |id: &str| (60..140).contains(&id["doc_".len()..].parse::<i32>().unwrap());
+    assert!(matches.iter().all(|(id, _)| !removed(id)));
+
+    // The graph is rebuilt once half of the nodes are tombstones
+    let mut index = create_index(0.5);
+    for i in 0..100 {
+        index.remove(&format!("doc_{}", i));
+    }
+    assert_eq!(index.deleted_len(), 100);
+    index.remove("doc_100");
+    assert_eq!(index.deleted_len(), 0);
+    assert_eq!(index.len(), 99);
+    let matches = index.search(&query, 5);
+    assert_eq!(matches.len(), 5);
+    assert_eq!(matches[0].0, "doc_101");
+    assert_eq!(index.search(&query, 500).len(), 99);
+}
@@ -1,21 +1,39 @@
 use rocket::{
-    catchers, fairing::AdHoc, http::Status, post, routes, serde::json::Json, Build, Rocket, State,
+    catchers,
+    fairing::AdHoc,
+    get,
+    http::{ContentType, Status},
+    post,
+    response::stream::{Event, EventStream, TextStream},
+    routes,
+    serde::json::Json,
+    tokio::{select, time::sleep},
+    Build, Rocket, Shutdown, State,
 };
 
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
 
 use alchemix_rx::prelude::*;
 
 use crate::{
     analytics::Analytics,
-    auth::{self, AuthService},
+    auth::{self, AuthChecker, AuthService},
+    backup::BackupService,
     spa_services::{self, SPA},
 };
 
+// Blank line sent while an action runs, a write failing once the client is
+// gone drops the response stream and cancels the action
+const ACTION_HEARTBEAT: Duration = Duration::from_secs(1);
+
 pub struct AlchemixWeb {
     data_path: String,
     rx_stores: HashMap<String, RxStore>,
     fluxes: HashMap<String, Flux>,
+    backup_service: Option<BackupService>,
 }
 
 impl AlchemixWeb {
@@ -24,6 +42,7 @@ impl AlchemixWeb {
             data_path: data_path.to_string(),
             rx_stores: HashMap::new(),
             fluxes: HashMap::new(),
+            backup_service: None,
         }
     }
 
@@ -34,6 +53,18 @@ impl AlchemixWeb {
         self
     }
 
+    /// Schedules a backup of every database under `data_path` each `interval`,
+    /// keeping the `retention` most recent snapshots in `backup_path`.
+    pub fn with_backups(mut self, backup_path: &str, interval: Duration, retention: usize) -> Self {
+        self.backup_service = Some(BackupService::new(
+            &self.data_path,
+            backup_path,
+            interval,
+            retention,
+        ));
+        self
+    }
+
     #[deprecated]
     pub fn with_rx(mut self, name: &str, rx: RxStore) -> Self {
         self.rx_stores.insert(name.to_string(), rx);
@@ -62,11 +93,30 @@ impl AlchemixWeb {
                     auth::login,
                     auth::refresh_token,
                     rx_action_post,
+                    rx_live_query,
+                    rx_signal_stream,
+                    rx_signal_events,
+                    rx_replication_node,
+                    rx_replication_pull,
+                    rx_replication_push,
                     flux_post,
                     flux_state_entities,
-                    flux_state_query
+                    flux_state_query,
+                    flux_state_geo,
+                    flux_live_query,
+                    admin_stats
                 ],
             )
+            .attach(AdHoc::on_liftoff("Backup Scheduler", |rocket| {
+                Box::pin(async move {
+                    let backup_service = rocket
+                        .state::<AlchemixWeb>()
+                        .and_then(|web| web.backup_service.as_ref());
+                    if let Some(backup_service) = backup_service {
+                        backup_service.start();
+                    }
+                })
+            }))
             .attach(AdHoc::on_shutdown("Shutdown Printer", |_| {
                 Box::pin(async move {
                     println!("...shutdown has commenced!");
@@ -85,18 +135,158 @@ impl AlchemixWeb {
     }
 }
 
+/// The response is streamed, blank lines are sent before the JSON response
+/// while the action runs. The action is cancelled when the client disconnects.
 #[post("/rx/<rx_name>/action", data = "<action>")]
-pub async fn rx_action_post(
+pub async fn rx_action_post<'r>(
     rx_name: &str,
     action: Json<RxAction>,
-    alchemix_web: &State<AlchemixWeb>,
+    alchemix_web: &'r State<AlchemixWeb>,
     _analytics: &State<Analytics>,
-) -> Result<Json<RxResponse>, Status> {
-    if let Some(rx) = alchemix_web.get_rx(rx_name) {
-        let response = rx.execute_action(action.0).await;
-        Ok(Json(response))
-    } else {
-        Ok(Json(RxResponse::Success()))
+) -> (ContentType, TextStream![String + 'r]) {
+    let rx = alchemix_web.get_rx(rx_name);
+    let lines = TextStream! {
+        let response = match rx {
+            Some(rx) => {
+                let mut execution = Box::pin(rx.execute_action(action.0));
+                loop {
+                    let response = select! {
+                        response = &mut execution => Some(response),
+                        _ = sleep(ACTION_HEARTBEAT) => None,
+                    };
+                    match response {
+                        Some(response) => break response,
+                        None => yield "\n".to_string(),
+                    }
+                }
+            }
+            None => RxResponse::Success(),
+        };
+        yield serde_json::to_string(&response).unwrap_or_default();
+    };
+    (ContentType::JSON, lines)
+}
+
+/// Streams the items of a signal handled by a stream handler, one JSON
+/// document per line.
+#[post("/rx/<rx_name>/stream", data = "<signal>")]
+pub async fn rx_signal_stream<'r>(
+    rx_name: &str,
+    signal: Json<Value>,
+    alchemix_web: &'r State<AlchemixWeb>,
+) -> Result<(ContentType, TextStream![String + 'r]), Status> {
+    let mut stream = start_signal_stream(rx_name, signal.0, alchemix_web).await?;
+    let lines = TextStream! {
+        while let Some(item) = stream.next().await {
+            yield format!("{}\n", item);
+        }
+    };
+    Ok((ContentType::new("application", "x-ndjson"), lines))
+}
+
+/// Same items as `rx_signal_stream`, as server sent events
+#[post("/rx/<rx_name>/stream/events", data = "<signal>")]
+pub async fn rx_signal_events<'r>(
+    rx_name: &str,
+    signal: Json<Value>,
+    alchemix_web: &'r State<AlchemixWeb>,
+) -> Result<EventStream![Event + 'r], Status> {
+    let mut stream = start_signal_stream(rx_name, signal.0, alchemix_web).await?;
+    Ok(EventStream! {
+        while let Some(item) = stream.next().await {
+            yield Event::json(&item);
+        }
+    })
+}
+
+async fn start_signal_stream<'r>(
+    rx_name: &str,
+    signal: Value,
+    alchemix_web: &'r AlchemixWeb,
+) -> Result<SignalStream<'r>, Status> {
+    let rx = alchemix_web.rx_stores.get(rx_name).ok_or(Status::ServiceUnavailable)?;
+    rx.signal_stream_value(signal).await.map_err(|response| {
+        println!("Unable to stream signal : {:?}", response);
+        Status::BadRequest
+    })
+}
+
+/// Server sent events of a live property query, a `results` event with the
+/// current results then a `diff` event for every change.
+#[get("/rx/<rx_name>/live?<kind>&<property_name>&<expression>")]
+pub async fn rx_live_query<'r>(
+    rx_name: &str,
+    kind: &str,
+    property_name: &str,
+    expression: &str,
+    alchemix_web: &'r State<AlchemixWeb>,
+    shutdown: Shutdown,
+) -> Result<EventStream![Event + 'r], Status> {
+    let rx = alchemix_web.rx_stores.get(rx_name).ok_or(Status::ServiceUnavailable)?;
+    let live_query = rx.subscribe_query(kind, property_name, expression).await;
+    Ok(live_query_events(live_query, shutdown))
+}
+
+fn live_query_events(mut live_query: LiveQuery<'_>, mut shutdown: Shutdown) -> EventStream![Event + '_] {
+    EventStream! {
+        yield Event::json(&live_query.get_results()).event("results");
+        loop {
+            let diff = select! {
+                diff = live_query.next() => diff,
+                _ = &mut shutdown => None,
+            };
+            match diff {
+                Some(diff) => yield Event::json(&diff).event("diff"),
+                None => break,
+            }
+        }
+    }
+}
+
+#[get("/rx/<rx_name>/replication/node")]
+pub async fn rx_replication_node(
+    rx_name: &str,
+    alchemix_web: &State<AlchemixWeb>,
+) -> Result<Json<String>, Status> {
+    let rx = alchemix_web.rx_stores.get(rx_name).ok_or(Status::ServiceUnavailable)?;
+    match rx.get_node_id().await {
+        Ok(node_id) => Ok(Json(node_id)),
+        Err(error) => {
+            println!("Replication error : {}", error);
+            Err(Status::BadRequest)
+        }
+    }
+}
+
+#[post("/rx/<rx_name>/replication/pull", data = "<pull>")]
+pub async fn rx_replication_pull(
+    rx_name: &str,
+    pull: Json<PullRequest>,
+    alchemix_web: &State<AlchemixWeb>,
+) -> Result<Json<ChangeSet>, Status> {
+    let rx = alchemix_web.rx_stores.get(rx_name).ok_or(Status::ServiceUnavailable)?;
+    match rx.changes_since(pull.since, pull.limit).await {
+        Ok(change_set) => Ok(Json(change_set)),
+        Err(error) => {
+            println!("Replication error : {}", error);
+            Err(Status::BadRequest)
+        }
+    }
+}
+
+#[post("/rx/<rx_name>/replication/push", data = "<change_set>")]
+pub async fn rx_replication_push(
+    rx_name: &str,
+    change_set: Json<ChangeSet>,
+    alchemix_web: &State<AlchemixWeb>,
+) -> Result<Json<ApplyReport>, Status> {
+    let rx = alchemix_web.rx_stores.get(rx_name).ok_or(Status::ServiceUnavailable)?;
+    match rx.apply_change_set(change_set.0).await {
+        Ok(report) => Ok(Json(report)),
+        Err(error) => {
+            println!("Replication error : {}", error);
+            Err(Status::BadRequest)
+        }
     }
 }
 
@@ -142,3 +332,59 @@ pub async fn flux_state_entities(
         Err(Status::ServiceUnavailable)
     }
 }
+
+#[post("/flux/<flux_name>/geo", data = "<query>")]
+pub async fn flux_state_geo(
+    flux_name: &str,
+    query: Json<StateGeoQuery>,
+    alchemix_web: &State<AlchemixWeb>,
+) -> Result<Json<Value>, Status> {
+    if let Some(flux) = alchemix_web.get_flux(flux_name) {
+        let res = flux.query_geo(&query.0);
+        Ok(Json(res))
+    } else {
+        Err(Status::ServiceUnavailable)
+    }
+}
+
+/// Same events as `rx_live_query` for a query of a flux state
+#[get("/flux/<flux_name>/live?<shard>&<kind>&<property_name>&<expr>")]
+pub async fn flux_live_query<'r>(
+    flux_name: &str,
+    shard: &str,
+    kind: &str,
+    property_name: &str,
+    expr: &str,
+    alchemix_web: &'r State<AlchemixWeb>,
+    shutdown: Shutdown,
+) -> Result<EventStream![Event + 'r], Status> {
+    let flux = alchemix_web.get_flux(flux_name).ok_or(Status::ServiceUnavailable)?;
+    let live_query = flux
+        .subscribe_query(StateQuery::new(shard, kind, property_name, expr))
+        .await;
+    Ok(live_query_events(live_query, shutdown))
+}
+
+/// Statistics of every flux shard by flux name, the tenant shards by tenant.
+#[get("/admin/stats")]
+pub async fn admin_stats(
+    auth: AuthChecker,
+    alchemix_web: &State<AlchemixWeb>,
+) -> Result<Json<BTreeMap<String, FluxStats>>, Status> {
+    if !auth.has_role("admin") {
+        return Err(Status::Forbidden);
+    }
+    let mut stats = BTreeMap::new();
+    for (name, flux) in &alchemix_web.fluxes {
+        match flux.stats().await {
+            Ok(flux_stats) => {
+                stats.insert(name.clone(), flux_stats);
+            }
+            Err(error) => {
+                println!("Unable to read stats of {} : {}", name, error);
+                return Err(Status::InternalServerError);
+            }
+        }
+    }
+    Ok(Json(stats))
+}
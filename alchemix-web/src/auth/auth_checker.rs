@@ -11,6 +11,12 @@ pub struct AuthChecker {
     pub roles: Vec<String>
 }
 
+impl AuthChecker {
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+}
+
 #[rocket::async_trait]
 impl<'r> FromRequest<'r> for AuthChecker {
     type Error = ();
@@ -0,0 +1,159 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use alchemix_rx::prelude::*;
+use alchemix_utils::file_io;
+use rocket::tokio;
+
+// Extension of the snapshots being written, they do not count for the retention
+const PARTIAL_SNAPSHOT_EXTENSION: &str = "partial";
+
+#[derive(Clone)]
+pub struct BackupService {
+    data_path: String,
+    backup_path: String,
+    interval: Duration,
+    retention: usize,
+}
+
+impl BackupService {
+    /// At least one snapshot is kept, a `retention` of 0 keeps the latest one.
+    pub fn new(data_path: &str, backup_path: &str, interval: Duration, retention: usize) -> Self {
+        Self {
+            data_path: data_path.to_string(),
+            backup_path: backup_path.to_string(),
+            interval,
+            retention: retention.max(1),
+        }
+    }
+
+    /// Spawns the periodic backup task on the current runtime.
+    pub fn start(&self) {
+        let service = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(service.interval);
+            // The first tick completes immediately, skip it to back up after one interval
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                match service.run_backup().await {
+                    Ok(snapshot) => println!("Backup complete : {}", snapshot),
+                    Err(message) => println!("Backup failed : {}", message),
+                }
+            }
+        });
+    }
+
+    /// Snapshots every database found under `data_path` into a new timestamped
+    /// folder of `backup_path`, then removes the snapshots exceeding the retention count.
+    /// The folder is renamed once every database is copied, a failed backup
+    /// leaves the previous snapshots untouched.
+    pub async fn run_backup(&self) -> Result<String, String> {
+        let timestamp = chrono::Utc::now().format("%Y%m%dT%H%M%S%3f");
+        let snapshot_path = format!("{}/{}", self.backup_path, timestamp);
+        let partial_path = format!("{}.{}", snapshot_path, PARTIAL_SNAPSHOT_EXTENSION);
+
+        if let Err(message) = self.write_snapshot(&partial_path).await {
+            file_io::remove_dir(&partial_path);
+            return Err(message);
+        }
+        std::fs::rename(&partial_path, &snapshot_path)
+            .map_err(|error| format!("Unable to complete backup {} : {}", snapshot_path, error))?;
+        self.apply_retention();
+        Ok(snapshot_path)
+    }
+
+    async fn write_snapshot(&self, snapshot_path: &str) -> Result<(), String> {
+        std::fs::create_dir_all(snapshot_path)
+            .map_err(|error| format!("Unable to create backup {} : {}", snapshot_path, error))?;
+        for db_file in self.list_databases() {
+            let relative_path = db_file
+                .strip_prefix(&self.data_path)
+                .unwrap_or(&db_file)
+                .to_string_lossy()
+                .to_string();
+            let target_path = format!("{}/{}", snapshot_path, relative_path);
+            let store = SQLiteEntityStore::new(&db_file.to_string_lossy());
+            let res = store.backup_to(&target_path).await;
+            store.close().await;
+            res?;
+        }
+        Ok(())
+    }
+
+    fn list_databases(&self) -> Vec<PathBuf> {
+        let backup_root = file_io::get_absolute_path(&self.backup_path).ok();
+        file_io::list_files(&self.data_path, None)
+            .into_iter()
+            .filter(|path| path.extension().map(|ext| ext == "db").unwrap_or(false))
+            .filter(|path| match (&backup_root, file_io::get_absolute_path(path)) {
+                (Some(backup_root), Ok(path)) => !path.starts_with(backup_root),
+                _ => true,
+            })
+            .collect()
+    }
+
+    fn apply_retention(&self) {
+        let mut snapshots: Vec<PathBuf> = file_io::list_folders(&self.backup_path, Some(1))
+            .into_iter()
+            .filter(|path| path.extension().map(|ext| ext != PARTIAL_SNAPSHOT_EXTENSION).unwrap_or(true))
+            .collect();
+        snapshots.sort();
+        if snapshots.len() > self.retention {
+            let expired_count = snapshots.len() - self.retention;
+            for snapshot in &snapshots[..expired_count] {
+                file_io::remove_dir(Path::new(snapshot));
+            }
+        }
+    }
+}
+
+#[rocket::async_test]
+pub async fn test_backup_retention() {
+    let data_path = "test-data/out/backup-service";
+    // Nested in the data path, its snapshots are not backed up again
+    let backup_path = "test-data/out/backup-service/backups";
+    file_io::remove_dir(data_path);
+    for db_path in [format!("{}/main.db", data_path), format!("{}/tenants/acme/main.db", data_path)] {
+        file_io::create_parent_dirs(&db_path);
+        let store = SQLiteEntityStore::new(&db_path);
+        store.close().await;
+    }
+    // Left by an interrupted backup
+    let partial_path = format!("{}/20000101T000000000.{}", backup_path, PARTIAL_SNAPSHOT_EXTENSION);
+    std::fs::create_dir_all(&partial_path).unwrap();
+
+    let snapshots = |backup_path: &str| {
+        let mut snapshots: Vec<String> = file_io::list_folders(backup_path, Some(1))
+            .iter()
+            .map(|path| path.to_string_lossy().to_string())
+            .filter(|path| !path.ends_with(PARTIAL_SNAPSHOT_EXTENSION))
+            .collect();
+        snapshots.sort();
+        snapshots
+    };
+
+    let service = BackupService::new(data_path, backup_path, Duration::from_secs(60), 2);
+    let mut completed = vec![];
+    for _ in 0..3 {
+        completed.push(service.run_backup().await.unwrap());
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    assert_eq!(snapshots(backup_path), completed[1..].to_vec());
+    for snapshot in &completed[1..] {
+        let mut files: Vec<String> = file_io::list_files(snapshot, None)
+            .iter()
+            .map(|path| path.strip_prefix(snapshot).unwrap().to_string_lossy().to_string())
+            .collect();
+        files.sort();
+        assert_eq!(files, vec!["main.db", "tenants/acme/main.db"]);
+    }
+    assert!(Path::new(&partial_path).is_dir());
+
+    // A retention of 0 still keeps the latest snapshot
+    let service = BackupService::new(data_path, backup_path, Duration::from_secs(60), 0);
+    let latest = service.run_backup().await.unwrap();
+    assert_eq!(snapshots(backup_path), vec![latest]);
+}
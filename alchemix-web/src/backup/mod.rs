@@ -0,0 +1,2 @@
+mod backup_service;
+pub use backup_service::*;
\ No newline at end of file
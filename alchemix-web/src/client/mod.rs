@@ -1,2 +1,5 @@
 #[cfg(test)]
-mod alchemix_client;
\ No newline at end of file
+mod alchemix_client;
+mod replication_client;
+
+pub use replication_client::*;
@@ -0,0 +1,50 @@
+use alchemix_rx::prelude::*;
+use serde::de::DeserializeOwned;
+
+/// Replication peer reached through the replication routes of an `AlchemixWeb` server.
+pub struct HttpReplicationPeer {
+    // e.g. http://localhost:8000/api/rx/demo
+    rx_url: String,
+    client: reqwest::Client,
+}
+
+impl HttpReplicationPeer {
+    pub fn new(server_url: &str, rx_name: &str) -> Self {
+        Self {
+            rx_url: format!("{}/api/rx/{}", server_url.trim_end_matches('/'), rx_name),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    async fn read_response<T: DeserializeOwned>(
+        response: Result<reqwest::Response, reqwest::Error>,
+    ) -> Result<T, String> {
+        let response = response.map_err(|error| format!("Replication request failed : {}", error))?;
+        if !response.status().is_success() {
+            return Err(format!("Replication request failed : {}", response.status()));
+        }
+        response
+            .json::<T>()
+            .await
+            .map_err(|error| format!("Invalid replication response : {}", error))
+    }
+}
+
+#[async_trait]
+impl ReplicationPeer for HttpReplicationPeer {
+    async fn get_node_id(&self) -> Result<String, String> {
+        let url = format!("{}/replication/node", self.rx_url);
+        Self::read_response(self.client.get(url).send().await).await
+    }
+
+    async fn pull(&self, since: i64, limit: usize) -> Result<ChangeSet, String> {
+        let url = format!("{}/replication/pull", self.rx_url);
+        let body = PullRequest { since, limit };
+        Self::read_response(self.client.post(url).json(&body).send().await).await
+    }
+
+    async fn push(&self, change_set: ChangeSet) -> Result<ApplyReport, String> {
+        let url = format!("{}/replication/push", self.rx_url);
+        Self::read_response(self.client.post(url).json(&change_set).send().await).await
+    }
+}
@@ -1,5 +1,6 @@
 pub mod analytics;
 pub mod auth;
+pub mod backup;
 pub mod spa_services;
 
 pub mod alchemix_web;